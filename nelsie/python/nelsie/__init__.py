@@ -2,9 +2,13 @@ from .resources import Resources
 from .slidedeck import SlideDeck, Slide
 from .box import Box, BoxBuilderMixin, GridOptions
 from .textstyle import FontStretch, TextStyle
-from .shapes import Arrow, Path, Stroke, Rect, Point, Oval
+from .shapes import Arrow, Path, Stroke, Rect, Point, Oval, Connector
 from .steps import StepVal
 from .stepcounter import StepCounter
+from .codefile import load_code
+from .layoutexpr import LayoutExpr
+from .page import page
+from .layoutwarning import LayoutWarning
 
 __all__ = [
     "Resources",
@@ -19,8 +23,13 @@ __all__ = [
     "Rect",
     "Point",
     "Oval",
+    "Connector",
     "Stroke",
     "StepVal",
     "GridOptions",
     "StepCounter",
+    "load_code",
+    "LayoutExpr",
+    "page",
+    "LayoutWarning",
 ]
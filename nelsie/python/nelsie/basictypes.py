@@ -63,6 +63,36 @@ def check_text_align(align):
         raise ValueError("Invalid text align value")
 
 
+TextDirection = Literal["auto", "ltr", "rtl"]
+
+TEXT_DIRECTION_VALUES = ("auto", "ltr", "rtl")
+
+
+def check_text_direction(direction):
+    if direction not in TEXT_DIRECTION_VALUES:
+        raise ValueError("Invalid text direction value")
+
+
+WritingMode = Literal["horizontal", "vertical"]
+
+WRITING_MODE_VALUES = ("horizontal", "vertical")
+
+
+def check_writing_mode(writing_mode):
+    if writing_mode not in WRITING_MODE_VALUES:
+        raise ValueError("Invalid writing mode value")
+
+
+SyntaxBackend = Literal["syntect", "tree-sitter"]
+
+SYNTAX_BACKEND_VALUES = ("syntect", "tree-sitter")
+
+
+def check_syntax_backend(backend):
+    if backend not in SYNTAX_BACKEND_VALUES:
+        raise ValueError("Invalid syntax highlighting backend")
+
+
 AlignItems = Literal[
     "start",
     "end",
@@ -125,6 +155,7 @@ def check_align_content(align):
 
 GridTemplate = Sequence[IntOrFloat | str]
 GridPosition = int | str | tuple[int | str]
+GridAutoFlow = Literal["row", "column", "row dense", "column dense"]
 
 
 def parse_debug_layout(value: bool | str) -> str | None:
@@ -1,4 +1,6 @@
+import os
 from dataclasses import dataclass
+from collections.abc import Sequence
 from typing import Union
 
 from .image import (
@@ -25,7 +27,13 @@ from .basictypes import (
     check_position,
     check_size,
     TextAlign,
+    TextDirection,
     check_text_align,
+    check_text_direction,
+    WritingMode,
+    check_writing_mode,
+    SyntaxBackend,
+    check_syntax_backend,
     IntOrFloat,
     Length,
     LengthAuto,
@@ -34,25 +42,42 @@ from .basictypes import (
     AlignItems,
     AlignContent,
     GridTemplate,
+    GridAutoFlow,
     GridPosition,
     check_align_content,
     check_align_items,
 )
-from .nelsie import check_color
-from .text import TextContent
+from .nelsie import check_color, check_grid_template, check_grid_tracks, check_grid_areas, check_grid_auto_flow
+from .text import TextContent, MathContent, parse_line_ranges
+from .codediff import DEFAULT_DIFF_ADDED_COLOR, DEFAULT_DIFF_REMOVED_COLOR
+from .codefile import load_code_with_first_line
 from .textsteps import text_step_parser
 from .textstyle import TextStyle, check_is_text_style, check_is_str_or_text_style
-from .utils import check_is_str, check_is_bool, check_is_int, check_is_int_or_float
+from .utils import check_is_str, check_is_bool, check_is_int, check_is_int_or_float, check_is_type
 from .layoutexpr import LayoutExpr
 from .shapes import Rect, Oval, Path, Point
+from .page import PageOrigin, check_origin
 
 
 @dataclass
 class GridOptions:
     template_rows: Sv[GridTemplate] = ()
     template_columns: Sv[GridTemplate] = ()
+    template_areas: Sv[Sequence[str]] = ()
+    auto_rows: Sv[GridTemplate] = ()
+    auto_columns: Sv[GridTemplate] = ()
+    auto_flow: Sv[GridAutoFlow] = "row"
     row: Sn[GridPosition] = None
     column: Sn[GridPosition] = None
+    area: Sn[str] = None
+
+    def __post_init__(self):
+        sv_check(self.template_rows, check_grid_template)
+        sv_check(self.template_columns, check_grid_template)
+        sv_check(self.template_areas, check_grid_areas)
+        sv_check(self.auto_rows, check_grid_tracks)
+        sv_check(self.auto_columns, check_grid_tracks)
+        sv_check(self.auto_flow, check_grid_auto_flow)
 
 
 class BoxBuilderMixin:
@@ -78,6 +103,10 @@ class BoxBuilderMixin:
         active: BoolStepDef = True,
         width: Sn[Size] = None,
         height: Sn[Size] = None,
+        min_width: Sn[Length] = None,
+        max_width: Sn[Length] = None,
+        min_height: Sn[Length] = None,
+        max_height: Sn[Length] = None,
         bg_color: Sn[str] = None,
         row: Sv[bool] = False,
         reverse: Sv[bool] = False,
@@ -91,6 +120,7 @@ class BoxBuilderMixin:
         m_bottom: Sv[LengthAuto] = 0,
         flex_grow: Sv[float] = 0.0,
         flex_shrink: Sv[float] = 1.0,
+        flex_basis: Sv[LengthAuto] = "auto",
         align_items: Sn[AlignItems] = None,
         align_self: Sn[AlignItems] = None,
         justify_self: Sn[AlignItems] = None,
@@ -103,6 +133,7 @@ class BoxBuilderMixin:
         url: Sn[str] = None,
         name: str = "",
         debug_layout: bool | str | None = None,
+        origin: Union["BoxBuilderMixin", PageOrigin, None] = None,
     ):
         """
         Create a new child box. See [Box reference](https://spirali.github.io/nelsie/guide/box/) for documentation
@@ -115,6 +146,10 @@ class BoxBuilderMixin:
             active=active,
             width=width,
             height=height,
+            min_width=min_width,
+            max_width=max_width,
+            min_height=min_height,
+            max_height=max_height,
             bg_color=bg_color,
             row=row,
             reverse=reverse,
@@ -128,6 +163,7 @@ class BoxBuilderMixin:
             m_bottom=m_bottom,
             flex_grow=flex_grow,
             flex_shrink=flex_shrink,
+            flex_basis=flex_basis,
             align_items=align_items,
             align_self=align_self,
             justify_self=justify_self,
@@ -140,6 +176,7 @@ class BoxBuilderMixin:
             border_radius=border_radius,
             debug_layout=debug_layout,
             url=url,
+            origin=origin,
         )
         self.add(box)
         return box
@@ -160,10 +197,15 @@ class BoxBuilderMixin:
         style: Sn[TextStyle] = None,
         *,
         align: Sv[TextAlign] = "start",
+        direction: Sv[TextDirection] = "auto",
+        writing_mode: Sv[WritingMode] = "horizontal",
         strip: bool = True,
         parse_styles: bool = True,
         style_delimiters: str = "~{}",
         parse_steps: bool | str = False,
+        parse_math: bool = False,
+        markdown: bool = False,
+        links: dict[int, str] | None = None,
         **box_args,
     ):
         if strip and isinstance(text, str):
@@ -172,20 +214,51 @@ class BoxBuilderMixin:
             text = parse_steps_helper(text, parse_steps, strip)
         sv_check(text, check_is_str)
         sv_check(align, check_text_align)
+        sv_check(direction, check_text_direction)
+        sv_check(writing_mode, check_writing_mode)
         sn_check(style, check_is_str_or_text_style)
+        check_is_bool(parse_math)
+        check_is_bool(markdown)
+        if links is not None:
+            for anchor_id, url in links.items():
+                check_is_int(anchor_id)
+                check_is_str(url)
         box = self.box(**box_args)
         box._content = TextContent(
             text=text,
             style=style,
             align=align,
+            direction=direction,
+            writing_mode=writing_mode,
             is_code=False,
             parse_styles=parse_styles,
             style_delimiters=style_delimiters,
             syntax_language=None,
             syntax_theme=None,
+            parse_math=parse_math,
+            markdown=markdown,
+            links=links,
         )
         return box
 
+    def math(
+        self,
+        formula: Sv[str],
+        style: Sn[TextStyle] = None,
+        *,
+        display: Sv[bool] = True,
+        **box_args,
+    ):
+        """
+        Create a new box with a math formula written in a subset of TeX
+        """
+        sv_check(formula, check_is_str)
+        sn_check(style, check_is_str_or_text_style)
+        sv_check(display, check_is_bool)
+        box = self.box(**box_args)
+        box._content = MathContent(formula=formula, style=style, display=display)
+        return box
+
     def code(
         self,
         text: Sv[str],
@@ -193,35 +266,153 @@ class BoxBuilderMixin:
         style: Sn[TextStyle] = None,
         *,
         align: Sv[TextAlign] = "start",
+        direction: Sv[TextDirection] = "auto",
+        writing_mode: Sv[WritingMode] = "horizontal",
         strip: bool = True,
         theme: Sn[str] = None,
+        syntax_backend: Sn[SyntaxBackend] = None,
         parse_styles: bool = False,
         style_delimiters: str = "~{}",
         parse_steps: bool | str = False,
+        line_numbers: bool | int = False,
+        line_numbers_style: Sn[TextStyle | str] = None,
+        line_numbers_separator: str = "  ",
+        highlight_lines: Sn[str | Sequence[int]] = None,
+        highlight_color: Sn[str] = None,
+        theme_background: bool = False,
+        diff_from: Sn[str] = None,
+        unified_diff: bool = False,
+        diff_added_color: Sv[str] = DEFAULT_DIFF_ADDED_COLOR,
+        diff_removed_color: Sv[str] = DEFAULT_DIFF_REMOVED_COLOR,
+        anchors: dict[int, str | tuple[int, int, int]] | None = None,
+        anchor_styles: dict[int, Sn[TextStyle | str]] | None = None,
         **box_args,
     ):
+        """
+        Create a new box with a syntax highlighted code
+
+        * syntax_backend - "syntect" or "tree-sitter"; the default is set by SlideDeck's default_syntax_backend
+        * line_numbers - show line numbers; if an integer is given, it is the number of the first line
+        * line_numbers_style - style of line numbers; named style "line_numbers" is used by default
+        * highlight_lines - line numbers with highlighted background, e.g. "1,3-5" or [1, 3, 4, 5]
+        * highlight_color - background of highlighted lines; the selection color of the theme is used by default
+        * theme_background - use the background color of the theme as bg_color of the box (if bg_color is not set)
+        * diff_from - an older version of the code; the code is shown as a diff against it
+        * unified_diff - the code is a unified diff (e.g. an output of `git diff`)
+        * anchors - inline anchors placed without a markup; a value is a regular expression (its first match,
+            or the first group if there is any) or a tuple (line, first column, last column) counted from 1
+        * anchor_styles - styles of the anchored ranges; a style may be changed in steps
+        """
         if strip and isinstance(text, str):
-            text = text.strip()
+            # Leading spaces are diff markers in a unified diff
+            text = text.strip("\n") if unified_diff else text.strip()
+        if strip and isinstance(diff_from, str):
+            diff_from = diff_from.strip()
         if parse_steps:
             text = parse_steps_helper(text, parse_steps, strip)
         sv_check(text, check_is_str)
         sv_check(align, check_text_align)
+        sv_check(direction, check_text_direction)
+        sv_check(writing_mode, check_writing_mode)
         sn_check(style, check_is_str_or_text_style)
         sn_check(language, check_is_str)
         sn_check(theme, check_is_str)
+        sn_check(syntax_backend, check_syntax_backend)
+        check_is_type(line_numbers, (bool, int))
+        sn_check(line_numbers_style, check_is_str_or_text_style)
+        check_is_str(line_numbers_separator)
+        sn_check(highlight_lines, parse_line_ranges)
+        sn_check(highlight_color, check_color)
+        check_is_bool(theme_background)
+        sn_check(diff_from, check_is_str)
+        check_is_bool(unified_diff)
+        sv_check(diff_added_color, check_color)
+        sv_check(diff_removed_color, check_color)
+        if anchors is not None:
+            for anchor_id, target in anchors.items():
+                check_is_int(anchor_id)
+                if isinstance(target, str):
+                    continue
+                if (
+                    not isinstance(target, tuple)
+                    or len(target) != 3
+                    or not all(isinstance(v, int) and v >= 1 for v in target)
+                ):
+                    raise ValueError(f"Invalid anchor target: {target!r}")
+        if anchor_styles is not None:
+            for anchor_id, anchor_style in anchor_styles.items():
+                if anchors is None or anchor_id not in anchors:
+                    raise ValueError(f"Style for an unknown anchor {anchor_id}")
+                sn_check(anchor_style, check_is_str_or_text_style)
+        if unified_diff and diff_from is not None:
+            raise ValueError("Parameters 'unified_diff' and 'diff_from' cannot be used together")
+        if not isinstance(line_numbers, bool) and line_numbers < 0:
+            raise ValueError(f"Number of the first line cannot be negative: {line_numbers}")
+        if line_numbers is True:
+            line_numbers = 1
+        elif line_numbers is False:
+            line_numbers = None
         box = self.box(**box_args)
         box._content = TextContent(
             text=text,
             style=style,
             align=align,
+            direction=direction,
+            writing_mode=writing_mode,
             is_code=True,
             syntax_language=language,
             syntax_theme=theme,
+            syntax_backend=syntax_backend,
             parse_styles=parse_styles,
             style_delimiters=style_delimiters,
+            line_numbers=line_numbers,
+            line_numbers_style=line_numbers_style,
+            line_numbers_separator=line_numbers_separator,
+            highlight_lines=highlight_lines,
+            highlight_color=highlight_color,
+            theme_background=theme_background,
+            diff_from=diff_from,
+            unified_diff=unified_diff,
+            diff_added_color=diff_added_color,
+            diff_removed_color=diff_removed_color,
+            anchors=anchors,
+            anchor_styles=anchor_styles,
         )
         return box
 
+    def code_file(
+        self,
+        path: str,
+        language: Sn[str] = None,
+        *,
+        region: str | None = None,
+        lines: tuple[int, int] | None = None,
+        dedent: bool = True,
+        line_numbers: bool | int = False,
+        **code_args,
+    ):
+        """
+        Create a new box with a syntax highlighted code loaded from a file
+
+        * language - language of the code; it is derived from the file extension by default
+        * region - name of a region between lines with markers, e.g. "// region: main" and "// endregion: main"
+        * lines - a range of lines (first, last) counted from 1; within a region, lines are counted from the region start
+        * dedent - remove a common indentation
+        * line_numbers - if True, lines are numbered as in the file
+
+        Other arguments are passed to `.code()`. The file is watched when slides are rebuilt in watch mode.
+        """
+        check_is_str(path)
+        sn_check(region, check_is_str)
+        check_is_bool(dedent)
+        text, first_line = load_code_with_first_line(path, region, lines, dedent)
+        if language is None:
+            language = os.path.splitext(path)[1][1:] or None
+        if line_numbers is True:
+            line_numbers = first_line
+        code_args.setdefault("strip", False)
+        return self.code(text, language, line_numbers=line_numbers, **code_args)
+
     def image(
         self,
         path_or_data: Sn[PathOrImageData],
@@ -439,6 +630,10 @@ class Box(BoxBuilderMixin):
         z_level: Sn[int] = None,
         width: Sn[Size] = None,
         height: Sn[Size] = None,
+        min_width: Sn[Length] = None,
+        max_width: Sn[Length] = None,
+        min_height: Sn[Length] = None,
+        max_height: Sn[Length] = None,
         bg_color: Sn[str] = None,
         row: Sv[bool] = False,
         reverse: Sv[bool] = False,
@@ -452,6 +647,7 @@ class Box(BoxBuilderMixin):
         m_bottom: Sv[LengthAuto] = 0,
         flex_grow: Sv[float] = 0.0,
         flex_shrink: Sv[float] = 1.0,
+        flex_basis: Sv[LengthAuto] = "auto",
         align_items: Sn[AlignItems] = None,
         align_self: Sn[AlignItems] = None,
         justify_self: Sn[AlignItems] = None,
@@ -464,6 +660,7 @@ class Box(BoxBuilderMixin):
         url: Sn[str] = None,
         name: str = "",
         debug_layout: bool | str | None = None,
+        origin: Union["BoxBuilderMixin", PageOrigin, None] = None,
     ):
         """
         Create a new box. See [Box reference](https://spirali.github.io/nelsie/guide/box/) for documentation.
@@ -473,6 +670,10 @@ class Box(BoxBuilderMixin):
         sn_check(z_level, check_is_int)
         sn_check(width, check_size)
         sn_check(height, check_size)
+        sn_check(min_width, check_length)
+        sn_check(max_width, check_length)
+        sn_check(min_height, check_length)
+        sn_check(max_height, check_length)
         sn_check(bg_color, check_color)
         sv_check(row, check_is_bool)
         sv_check(reverse, check_is_bool)
@@ -487,6 +688,7 @@ class Box(BoxBuilderMixin):
 
         sv_check(flex_grow, check_is_int_or_float)
         sv_check(flex_shrink, check_is_int_or_float)
+        sv_check(flex_basis, check_length_auto)
         sv_check(gap_x, check_length)
         sv_check(gap_y, check_length)
         sn_check(align_items, check_align_items)
@@ -497,6 +699,7 @@ class Box(BoxBuilderMixin):
         sv_check(border_radius, check_is_int_or_float)
         sn_check(url, check_is_str)
         check_is_str(name)
+        check_origin(origin)
 
         if isinstance(debug_layout, str):
             check_color(debug_layout)
@@ -505,9 +708,14 @@ class Box(BoxBuilderMixin):
         self._active = parse_bool_steps(active)
         self._x = x
         self._y = y
+        self._origin = origin
         self._z_level = z_level
         self._width = width
         self._height = height
+        self._min_width = min_width
+        self._max_width = max_width
+        self._min_height = min_height
+        self._max_height = max_height
         self._bg_color = bg_color
         self._content = None
         self._children = []
@@ -524,6 +732,7 @@ class Box(BoxBuilderMixin):
 
         self._flex_grow = flex_grow
         self._flex_shrink = flex_shrink
+        self._flex_basis = flex_basis
         self._align_items = align_items
         self._align_self = align_self
         self._justify_self = justify_self
@@ -634,14 +843,15 @@ class Box(BoxBuilderMixin):
             self._p_bottom = bottom
         return self
 
-    def draw_line(self, p1: Point, p2: Point, **path_args):
+    def draw_line(self, p1: Point, p2: Point, **path_args) -> Path:
         """
-        Shortcut for drawing a simple line
+        Shortcut for drawing a simple line; returns the created path
         """
         path = Path(**path_args)
         path.move_to(p1)
         path.line_to(p2)
-        return self.add(path)
+        self.add(path)
+        return path
 
     def add(self, item):
         """
@@ -0,0 +1,59 @@
+from difflib import SequenceMatcher
+
+DEFAULT_DIFF_ADDED_COLOR = "#2ea04340"
+DEFAULT_DIFF_REMOVED_COLOR = "#f8514940"
+
+
+def diff_texts(old: str, new: str) -> tuple[str, list[str]]:
+    """
+    Merges two versions of a text into one text where removed lines are placed before added lines.
+    Returns the merged text and kinds of its lines (" " - unchanged, "+" - added, "-" - removed).
+    """
+    old_lines = old.split("\n")
+    new_lines = new.split("\n")
+    lines = []
+    kinds = []
+    for tag, i1, i2, j1, j2 in SequenceMatcher(None, old_lines, new_lines, autojunk=False).get_opcodes():
+        if tag == "equal":
+            lines.extend(new_lines[j1:j2])
+            kinds.extend(" " * (j2 - j1))
+            continue
+        if tag in ("delete", "replace"):
+            lines.extend(old_lines[i1:i2])
+            kinds.extend("-" * (i2 - i1))
+        if tag in ("insert", "replace"):
+            lines.extend(new_lines[j1:j2])
+            kinds.extend("+" * (j2 - j1))
+    return "\n".join(lines), kinds
+
+
+def parse_unified_diff(diff: str) -> tuple[str, list[str]]:
+    """
+    Parses a unified diff (e.g. an output of `git diff`).
+    File headers and hunk headers are skipped.
+    """
+    lines = []
+    kinds = []
+    in_hunk = False
+    for line in diff.split("\n"):
+        if line.startswith("diff "):
+            in_hunk = False
+            continue
+        if line.startswith("@@"):
+            in_hunk = True
+            continue
+        if line.startswith("\\"):
+            # "\ No newline at end of file"
+            continue
+        if not in_hunk and line.startswith(("index ", "--- ", "+++ ", "new file", "deleted file")):
+            continue
+        if line == "":
+            # Some tools strip the trailing space of empty unchanged lines
+            lines.append("")
+            kinds.append(" ")
+        elif line[0] in " +-":
+            lines.append(line[1:])
+            kinds.append(line[0])
+        else:
+            raise ValueError(f"Invalid line in unified diff: '{line}'")
+    return "\n".join(lines), kinds
@@ -0,0 +1,82 @@
+import os
+import re
+import textwrap
+
+from .watch import watch_path
+
+# A marker is a line that starts with "region" or "endregion", optionally after a comment token and '#',
+# e.g. "// region: main", "#region main", or "<!-- #endregion -->"
+REGION_MARKER = re.compile(r"^\s*(?:[^\w\s]+\s*)?#?\s*(end)?region\b:?\s*(\w[\w.-]*)?")
+
+
+def _extract_region(lines: list[str], region: str, path: str) -> tuple[list[str], int]:
+    """
+    Returns lines between markers of the region (markers are not included) and the index of the first one
+    """
+    start = None
+    for idx, line in enumerate(lines):
+        m = REGION_MARKER.match(line)
+        if m is None:
+            continue
+        is_end, name = m.groups()
+        if start is None:
+            if not is_end and name == region:
+                start = idx + 1
+        elif is_end and name in (None, region):
+            return lines[start:idx], start
+    if start is None:
+        raise ValueError(f"Region '{region}' not found in '{path}'")
+    raise ValueError(f"Region '{region}' in '{path}' is not closed")
+
+
+def load_code(
+    path: str,
+    region: str | None = None,
+    lines: tuple[int, int] | None = None,
+    dedent: bool = True,
+) -> str:
+    """
+    Reads a code snippet from a file.
+
+    * region - name of a region between lines with markers, e.g. "// region: main" and "// endregion: main"
+    * lines - a range of lines (first, last) counted from 1; within a region, lines are counted from the region start
+    * dedent - remove a common indentation
+
+    Marker lines of the region are not included. The file is watched when slides are rebuilt in watch mode.
+    """
+    return load_code_with_first_line(path, region, lines, dedent)[0]
+
+
+def load_code_with_first_line(
+    path: str, region: str | None, lines: tuple[int, int] | None, dedent: bool
+) -> tuple[str, int]:
+    """
+    Same as `load_code` but it also returns the number of the first returned line in the file
+    """
+    path = os.path.abspath(path)
+    watch_path(path)
+    with open(path, encoding="utf-8") as f:
+        code_lines = f.read().split("\n")
+    first = 0
+    if region is not None:
+        code_lines, first = _extract_region(code_lines, region, path)
+    if lines is not None:
+        start, end = lines
+        if start < 1 or end < start:
+            raise ValueError(f"Invalid line range: {lines!r}")
+        code_lines = code_lines[start - 1 : end]
+        first += start - 1
+    selected = []
+    for idx, line in enumerate(code_lines):
+        if not selected:
+            # Numbering starts at the first shown line
+            if not line.strip():
+                continue
+            first += idx
+        selected.append(line)
+    while selected and not selected[-1].strip():
+        selected.pop()
+    code = "\n".join(selected)
+    if dedent:
+        code = textwrap.dedent(code)
+    return code, first + 1
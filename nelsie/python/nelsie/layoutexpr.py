@@ -24,10 +24,83 @@ class LayoutExpr:
         check_int_or_float_or_layout_expr(other)
         return LayoutExpr("-", self, other)
 
+    def __truediv__(self, other: IntOrFloatOrLayoutExpr):
+        """
+        Division by zero gives zero
+        """
+        check_int_or_float_or_layout_expr(other)
+        return LayoutExpr("/", self, other)
+
+    def __radd__(self, other: IntOrFloatOrLayoutExpr):
+        check_int_or_float_or_layout_expr(other)
+        return LayoutExpr("+", other, self)
+
+    def __rmul__(self, other: IntOrFloatOrLayoutExpr):
+        check_int_or_float_or_layout_expr(other)
+        return LayoutExpr("*", other, self)
+
+    def __rsub__(self, other: IntOrFloatOrLayoutExpr):
+        check_int_or_float_or_layout_expr(other)
+        return LayoutExpr("-", other, self)
+
+    def __rtruediv__(self, other: IntOrFloatOrLayoutExpr):
+        check_int_or_float_or_layout_expr(other)
+        return LayoutExpr("/", other, self)
+
+    def __neg__(self):
+        return LayoutExpr("neg", self)
+
+    def __abs__(self):
+        return LayoutExpr("abs", self)
+
     @staticmethod
-    def max(expressions: Sequence["LayoutExpr"]):
+    def max(expressions: Sequence[IntOrFloatOrLayoutExpr]):
+        """
+        Maximum of expressions
+        """
+        _check_expressions(expressions)
         return LayoutExpr("max", expressions)
 
+    @staticmethod
+    def min(expressions: Sequence[IntOrFloatOrLayoutExpr]):
+        """
+        Minimum of expressions
+        """
+        _check_expressions(expressions)
+        return LayoutExpr("min", expressions)
+
+    @staticmethod
+    def clamp(
+        value: IntOrFloatOrLayoutExpr,
+        min: IntOrFloatOrLayoutExpr,
+        max: IntOrFloatOrLayoutExpr,
+    ):
+        """
+        Limits a value into the range [min, max]; `min` wins if min > max
+        """
+        _check_expressions((value, min, max))
+        return LayoutExpr("clamp", value, min, max)
+
+    @staticmethod
+    def lerp(
+        a: IntOrFloatOrLayoutExpr,
+        b: IntOrFloatOrLayoutExpr,
+        t: IntOrFloatOrLayoutExpr,
+    ):
+        """
+        Linear interpolation between `a` (t = 0) and `b` (t = 1)
+        """
+        _check_expressions((a, b, t))
+        return LayoutExpr("lerp", a, b, t)
+
+    @staticmethod
+    def page_width(fraction):
+        return LayoutExpr("page_width", fraction)
+
+    @staticmethod
+    def page_height(fraction):
+        return LayoutExpr("page_height", fraction)
+
     @staticmethod
     def x(node_id):
         return LayoutExpr("x", node_id)
@@ -76,9 +149,40 @@ class LayoutExpr:
     def inline_height(node_id, anchor_id, fraction):
         return LayoutExpr("inline_height", node_id, anchor_id, fraction)
 
+    @staticmethod
+    def shape_x(shape_id):
+        return LayoutExpr("shape_x", shape_id)
+
+    @staticmethod
+    def shape_y(shape_id):
+        return LayoutExpr("shape_y", shape_id)
+
+    @staticmethod
+    def shape_width(shape_id, fraction):
+        return LayoutExpr("shape_width", shape_id, fraction)
+
+    @staticmethod
+    def shape_height(shape_id, fraction):
+        return LayoutExpr("shape_height", shape_id, fraction)
+
+    @staticmethod
+    def shape_point_x(shape_id, fraction):
+        return LayoutExpr("shape_point_x", shape_id, fraction)
+
+    @staticmethod
+    def shape_point_y(shape_id, fraction):
+        return LayoutExpr("shape_point_y", shape_id, fraction)
+
 
 int_or_float_or_layout_expr = (int, float, LayoutExpr)
 
 
 def check_int_or_float_or_layout_expr(obj):
     check_is_type(obj, int_or_float_or_layout_expr)
+
+
+def _check_expressions(expressions):
+    if not expressions:
+        raise ValueError("At least one expression is required")
+    for expr in expressions:
+        check_int_or_float_or_layout_expr(expr)
@@ -0,0 +1,33 @@
+from typing import Literal
+
+LayoutWarningKind = Literal["parent", "page", "text", "empty"]
+
+KIND_DESCRIPTIONS = {
+    "parent": "extends beyond its parent box by {amount:.1f}",
+    "page": "extends beyond the page by {amount:.1f}",
+    "text": "has a text that is larger than the box by {amount:.1f}",
+    "empty": "has a content but zero width or height",
+}
+
+
+class LayoutWarning(UserWarning):
+    """
+    A problem found in the layout of a page when slides are rendered.
+
+    * `page_index` - Index of the page in the output, counted from zero
+    * `node_id` - `id()` of the box
+    * `box_name` - Name of the box
+    * `kind` - `"parent"` (the box extends beyond its parent box), `"page"` (the box extends beyond the page),
+      `"text"` (the text of the box is larger than the box), or `"empty"` (a box with a content has zero size)
+    * `amount` - The largest distance (in pixels) by which the box or its text extends beyond the limit
+    """
+
+    def __init__(self, page_index: int, node_id: int, box_name: str, kind: LayoutWarningKind, amount: float):
+        self.page_index = page_index
+        self.node_id = node_id
+        self.box_name = box_name
+        self.kind = kind
+        self.amount = amount
+        label = f"Box '{box_name}'" if box_name else "A box"
+        description = KIND_DESCRIPTIONS[kind].format(amount=amount)
+        super().__init__(f"Page {page_index + 1}: {label} {description}")
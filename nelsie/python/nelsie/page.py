@@ -0,0 +1,61 @@
+from typing import Literal
+
+from .basictypes import Position
+from .layoutexpr import LayoutExpr
+from .shapes import Point
+from .utils import check_is_int_or_float
+
+PageOrigin = Literal["page"]
+
+
+class PageAnchors:
+    """
+    Layout expressions relative to the current page.
+    They can be used anywhere in the slide without a reference to the slide object.
+    """
+
+    def x(self, width_fraction: float = 0) -> LayoutExpr:
+        check_is_int_or_float(width_fraction)
+        return LayoutExpr.page_width(width_fraction)
+
+    def y(self, height_fraction: float = 0) -> LayoutExpr:
+        check_is_int_or_float(height_fraction)
+        return LayoutExpr.page_height(height_fraction)
+
+    def p(self, x: float = 0, y: float = 0) -> Point:
+        return Point(self.x(x), self.y(y))
+
+    def width(self, fraction: float = 1.0) -> LayoutExpr:
+        check_is_int_or_float(fraction)
+        return LayoutExpr.page_width(fraction)
+
+    def height(self, fraction: float = 1.0) -> LayoutExpr:
+        check_is_int_or_float(fraction)
+        return LayoutExpr.page_height(fraction)
+
+
+page = PageAnchors()
+
+
+def check_origin(obj):
+    if obj is None or obj == "page" or hasattr(obj, "x") and hasattr(obj, "width"):
+        return
+    raise Exception(f"Invalid origin: {obj!r}")
+
+
+def position_relative_to(value: Position | None, origin, horizontal: bool) -> Position | None:
+    """
+    Converts a position given by a number or a string (e.g. "50%") into a layout expression relative to the origin.
+    Layout expressions are kept as they are.
+    """
+    if value is None or isinstance(value, LayoutExpr):
+        return value
+    if origin == "page":
+        origin = page
+    if isinstance(value, str):
+        value = value.strip()
+        if value.endswith("%"):
+            fraction = float(value[:-1]) / 100
+            return origin.x(fraction) if horizontal else origin.y(fraction)
+        value = float(value)
+    return (origin.x() if horizontal else origin.y()) + value
@@ -43,11 +43,66 @@ class Resources:
     def load_code_theme_dir(self, path: str):
         self._resources.load_code_theme_dir(path)
 
+    def load_code_syntax_file(self, path: str):
+        """
+        Adds a syntax from a .sublime-syntax file (the YAML format of Sublime Text; .tmLanguage files are not
+        supported); a syntax without a name is named by the file name without the extension.
+        Raises an exception if the file cannot be read or does not contain a valid syntax.
+        """
+        self._resources.load_code_syntax_file(path)
+
+    def load_code_syntax(self, source: str):
+        """
+        Adds a syntax from a content of a .sublime-syntax file
+        """
+        self._resources.load_code_syntax_str(source)
+
+    def load_code_theme_file(self, path: str):
+        """
+        Adds a theme from a .tmTheme file; the theme is named by the file name without the extension
+        """
+        self._resources.load_code_theme_file(path)
+
+    def load_code_theme(self, name: str, source: str):
+        """
+        Adds a theme from a content of a .tmTheme file
+        """
+        self._resources.load_code_theme_str(name, source)
+
+    def dump_code_syntaxes(self, path: str):
+        """
+        Stores all currently loaded syntaxes into a binary file that can be loaded by `load_code_syntax_dump`.
+        """
+        self._resources.dump_code_syntaxes(path)
+
+    def load_code_syntax_dump(self, path: str):
+        """
+        Replaces all currently loaded syntaxes by syntaxes from a file created by `dump_code_syntaxes`.
+        """
+        self._resources.load_code_syntax_dump(path)
+
+    def load_failures(self) -> list[tuple[str, str]]:
+        """
+        Returns syntax and theme files from `load_code_syntax_dir` and `load_code_theme_dir` that
+        failed to load, as pairs (path, error message).
+        """
+        return self._resources.code_load_failures()
+
     def load_fonts_dir(self, path: str):
         self._resources.load_fonts_dir(path)
 
     def syntaxes(self) -> list[tuple[str, list[str]]]:
         return self._resources.syntaxes()
 
+    def tree_sitter_languages(self) -> list[str]:
+        return self._resources.tree_sitter_languages()
+
     def themes(self) -> list[str]:
         return self._resources.themes()
+
+    def theme_colors(self, theme: str) -> dict[str, str | None]:
+        """
+        Returns colors of a code theme: "background", "foreground", "selection", "line_highlight",
+        "gutter" and "gutter_foreground". A color is None if it is not defined by the theme.
+        """
+        return dict(self._resources.theme_colors(theme))
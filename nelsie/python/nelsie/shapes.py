@@ -1,4 +1,5 @@
 from dataclasses import dataclass
+from typing import Literal
 
 from nelsie.nelsie import check_color
 
@@ -61,6 +62,62 @@ def check_is_stroke(obj):
     check_is_type(obj, Stroke)
 
 
+class ShapeAnchors:
+    """
+    Layout expressions that refer to a drawn shape.
+    The shape has to be placed on the same page as the expressions that use it.
+    """
+
+    def x(self, width_fraction: int | float = 0) -> LayoutExpr:
+        """
+        Get an expression with X coordinate relative to the bounding box of the shape.
+        """
+        check_is_int_or_float(width_fraction)
+        expr = LayoutExpr.shape_x(id(self))
+        if width_fraction == 0:
+            return expr
+        return expr + self.width(width_fraction)
+
+    def y(self, height_fraction: int | float = 0) -> LayoutExpr:
+        """
+        Get an expression with Y coordinate relative to the bounding box of the shape.
+        """
+        check_is_int_or_float(height_fraction)
+        expr = LayoutExpr.shape_y(id(self))
+        if height_fraction == 0:
+            return expr
+        return expr + self.height(height_fraction)
+
+    def p(self, x: int | float = 0, y: int | float = 0) -> Point:
+        """
+        Get a point relative to the bounding box of the shape.
+        """
+        return Point(self.x(x), self.y(y))
+
+    def width(self, fraction: int | float = 1.0) -> LayoutExpr:
+        """
+        Get an expression with width of the bounding box of the shape.
+        """
+        check_is_int_or_float(fraction)
+        return LayoutExpr.shape_width(id(self), fraction)
+
+    def height(self, fraction: int | float = 1.0) -> LayoutExpr:
+        """
+        Get an expression with height of the bounding box of the shape.
+        """
+        check_is_int_or_float(fraction)
+        return LayoutExpr.shape_height(id(self), fraction)
+
+    def point_at(self, fraction: int | float) -> Point:
+        """
+        Get a point on the outline of the shape at the given fraction of its length.
+        Paths start at their first point; rectangles at the top-left corner and ovals at the rightmost point,
+        both going clockwise.
+        """
+        check_is_int_or_float(fraction)
+        return Point(LayoutExpr.shape_point_x(id(self), fraction), LayoutExpr.shape_point_y(id(self), fraction))
+
+
 @dataclass(frozen=True)
 class RawRect:
     shape: int  # 0 = rect, 1 = oval
@@ -71,9 +128,10 @@ class RawRect:
     z_level: int
     stroke: Stroke | None
     fill_color: str | None
+    shape_id: int | None = None
 
 
-class BaseRect:
+class BaseRect(ShapeAnchors):
     def __init__(
         self,
         p1: Sv[Point],
@@ -111,6 +169,7 @@ class BaseRect:
             stroke=stroke,
             fill_color=get_step(self.fill_color, step),
             z_level=get_step(self.z_level, step, ctx.z_level),
+            shape_id=id(self),
         )
 
 
@@ -178,9 +237,10 @@ class RawPath:
     commands: list[str]
     points: list[LayoutExpr]
     z_level: int
+    shape_id: int | None = None
 
 
-class Path:
+class Path(ShapeAnchors):
     def __init__(
         self,
         *,
@@ -302,4 +362,106 @@ class Path:
             commands=self.commands,
             points=points,
             z_level=get_step(self.z_level, step, ctx.z_level),
+            shape_id=id(self),
+        )
+
+
+ConnectorSide = Literal["auto", "top", "right", "bottom", "left"]
+ConnectorRouting = Literal["straight", "orthogonal", "curved"]
+
+
+def check_connector_side(obj):
+    if obj not in ("auto", "top", "right", "bottom", "left"):
+        raise ValueError(f"Invalid connector side: {obj!r}")
+
+
+def check_connector_routing(obj):
+    if obj not in ("straight", "orthogonal", "curved"):
+        raise ValueError(f"Invalid connector routing: {obj!r}")
+
+
+@dataclass
+class RawConnector:
+    start: int
+    end: int
+    start_side: str
+    end_side: str
+    routing: str
+    stroke: Stroke | None
+    arrow_start: Arrow | None
+    arrow_end: Arrow | None
+    z_level: int
+    shape_id: int | None = None
+
+
+class Connector(ShapeAnchors):
+    """
+    A path between two boxes that starts and ends on their borders.
+
+    * start_side, end_side - a side of the box where the connector is attached; "auto" chooses the side by positions
+      of the boxes, or for a straight connector, the point where the line between centers of boxes crosses the border
+    * routing - "straight" (a line), "orthogonal" (horizontal and vertical segments), or "curved" (a Bezier curve)
+
+    Both boxes have to be on the same slide as the connector.
+    """
+
+    def __init__(
+        self,
+        start,
+        end,
+        *,
+        start_side: Sv[ConnectorSide] = "auto",
+        end_side: Sv[ConnectorSide] = "auto",
+        routing: Sv[ConnectorRouting] = "straight",
+        stroke: Sn[Stroke] = Stroke(color="black"),
+        arrow_start: Sn[Arrow] = None,
+        arrow_end: Sn[Arrow] = None,
+        z_level: Sn[int] = None,
+        show: BoolStepDef = True,
+    ):
+        from .box import Box
+
+        check_is_type(start, Box)
+        check_is_type(end, Box)
+        sv_check(start_side, check_connector_side)
+        sv_check(end_side, check_connector_side)
+        sv_check(routing, check_connector_routing)
+        sn_check(stroke, check_is_stroke)
+        sn_check(arrow_start, check_is_arrow)
+        sn_check(arrow_end, check_is_arrow)
+        sn_check(z_level, check_is_int)
+        self.show = parse_bool_steps(show)
+        self.start = start
+        self.end = end
+        self.start_side = start_side
+        self.end_side = end_side
+        self.routing = routing
+        self.stroke = stroke
+        self.arrow_start = arrow_start
+        self.arrow_end = arrow_end
+        self.z_level = z_level
+
+    def to_raw(self, step, ctx):
+        if not get_step(self.show, step, False):
+            return None
+        stroke = get_step(self.stroke, step)
+        if stroke is not None:
+            stroke = stroke.to_raw(step)
+        arrow_start = get_step(self.arrow_start, step)
+        if arrow_start is not None:
+            arrow_start = arrow_start.at_step(step)
+        arrow_end = get_step(self.arrow_end, step)
+        if arrow_end is not None:
+            arrow_end = arrow_end.at_step(step)
+        return RawConnector(
+            start=id(self.start),
+            end=id(self.end),
+            start_side=get_step(self.start_side, step),
+            end_side=get_step(self.end_side, step),
+            routing=get_step(self.routing, step),
+            stroke=stroke,
+            arrow_start=arrow_start,
+            arrow_end=arrow_end,
+            z_level=get_step(self.z_level, step, ctx.z_level),
+            shape_id=id(self),
         )
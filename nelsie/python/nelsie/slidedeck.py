@@ -3,6 +3,7 @@ from typing import Literal, Iterable, Sequence, Callable
 
 from nelsie.utils import check_is_int_or_float, check_is_type, check_is_str
 
+from .basictypes import SyntaxBackend, check_syntax_backend
 from .box import BoxBuilderMixin, traverse_children
 from .counters import CounterStorage
 from .resources import Resources
@@ -20,6 +21,12 @@ from .textstyle import (
     DEFAULT_TEXT_STYLE,
     TextStyle,
     DEFAULT_CODE_STYLE,
+    SUPERSCRIPT_STYLE,
+    EMPH_STYLE,
+    STRONG_STYLE,
+    LINK_STYLE,
+    LINE_NUMBERS_STYLE,
+    SUBSCRIPT_STYLE,
     check_is_text_style,
 )
 
@@ -146,6 +153,7 @@ class SlideDeck:
         resources: Resources | None = None,
         default_code_theme: str = "InspiredGitHub",
         default_code_language: str | None = None,
+        default_syntax_backend: SyntaxBackend = "syntect",
     ):
         """
         A top-level class of Nelsie. It represents a set of slides.
@@ -165,6 +173,8 @@ class SlideDeck:
             * "Solarized (dark)"
             * "Solarized (light)"
         * default_code_language - Default language to use for syntax highlighting (.code() method)
+        * default_syntax_backend - Default backend for syntax highlighting (.code() method):
+            "syntect" (TextMate grammars) or "tree-sitter" (bundled grammars, see Resources.tree_sitter_languages())
         """
         if resources is None:
             resources = Resources()
@@ -172,6 +182,7 @@ class SlideDeck:
             check_is_type(resources, Resources)
 
         nelsie_rs.check_color(bg_color)
+        check_syntax_backend(default_syntax_backend)
         check_is_int_or_float(width)
         check_is_int_or_float(height)
 
@@ -190,9 +201,16 @@ class SlideDeck:
         self.resources = resources
         self.default_code_theme = default_code_theme
         self.default_code_language = default_code_language
+        self.default_syntax_backend = default_syntax_backend
         self._text_styles = {
             "default": text_style,
             "code": code_style,
+            "sup": SUPERSCRIPT_STYLE,
+            "sub": SUBSCRIPT_STYLE,
+            "emph": EMPH_STYLE,
+            "strong": STRONG_STYLE,
+            "link": LINK_STYLE,
+            "line_numbers": LINE_NUMBERS_STYLE,
         }
         self.slides = []
 
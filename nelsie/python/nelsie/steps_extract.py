@@ -1,15 +1,16 @@
 from .image import ImageContent
 from .steps import StepVal, Step
 from .textstyle import TextStyle
-from .text import TextContent
+from .text import TextContent, MathContent
 from .box import Box
 from .slidedeck import Slide
-from .shapes import Path, Rect, Oval, Stroke, Arrow
+from .shapes import Path, Rect, Oval, Stroke, Arrow, Connector
 
 containers = (list, tuple, set)
 known_classes = (
     Box,
     TextContent,
+    MathContent,
     TextStyle,
     ImageContent,
     Path,
@@ -43,6 +44,18 @@ def extract_steps(obj, out: set[Step]):
             if k != "subslides":
                 extract_steps(d[k], out)
         return
+    if isinstance(obj, Box):
+        for k, o in obj.__dict__.items():
+            # Origin refers to another box; it is not a part of the box
+            if k != "_origin":
+                extract_steps(o, out)
+        return
+    if isinstance(obj, Connector):
+        for k, o in obj.__dict__.items():
+            # Connected boxes are not parts of the connector
+            if k not in ("start", "end"):
+                extract_steps(o, out)
+        return
     if isinstance(obj, known_classes):
         for o in obj.__dict__.values():
             extract_steps(o, out)
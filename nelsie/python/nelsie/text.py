@@ -1,8 +1,32 @@
+from collections.abc import Sequence
 from dataclasses import dataclass
 
-from .textstyle import TextStyle, merge_in_step
+from .textstyle import TextStyle, heading_styles, merge_in_step
 from .steps import Sv, Sn, Step, get_step
-from .basictypes import TextAlign
+from .basictypes import TextAlign, TextDirection, WritingMode, SyntaxBackend
+from .codediff import diff_texts, parse_unified_diff, DEFAULT_DIFF_ADDED_COLOR, DEFAULT_DIFF_REMOVED_COLOR
+
+
+@dataclass
+class RawLineNumbers:
+    start: int
+    style: TextStyle
+    separator: str
+
+
+@dataclass
+class RawTextDiff:
+    lines: list[str]
+    added_color: str
+    removed_color: str
+
+
+@dataclass
+class RawAnnotation:
+    anchor_id: int
+    # A regular expression or (line, start column, end column) counted from 0 (end column is exclusive)
+    target: str | tuple[int, int, int]
+    style: TextStyle | None = None
 
 
 @dataclass
@@ -10,10 +34,73 @@ class RawText:
     text: str
     style: TextStyle
     align: TextAlign = "start"
+    direction: TextDirection = "auto"
+    writing_mode: WritingMode = "horizontal"
     syntax_language: str | None = None
     syntax_theme: str | None = None
+    syntax_backend: SyntaxBackend = "syntect"
     named_styles: dict[str, TextStyle] | None = None
     style_delimiters: str | None = None
+    parse_math: bool = False
+    markdown: bool = False
+    links: dict[int, str] | None = None
+    annotations: list[RawAnnotation] | None = None
+    line_numbers: RawLineNumbers | None = None
+    diff: RawTextDiff | None = None
+    line_highlights: list[tuple[int, int, str | None]] | None = None
+    theme_background: bool = False
+
+
+MARKDOWN_STYLE_NAMES = ("emph", "strong", "code", "link", "h1", "h2", "h3", "h4", "h5", "h6")
+
+
+@dataclass
+class RawMath:
+    formula: str
+    style: TextStyle
+    display: bool = True
+
+
+def parse_line_ranges(value: str | Sequence[int]) -> list[tuple[int, int]]:
+    """
+    Parses line numbers, e.g. "1,3-5" or [1, 3, 4, 5], into a list of ranges (first, last)
+    """
+    if isinstance(value, str):
+        result = []
+        for part in value.split(","):
+            part = part.strip()
+            if not part:
+                continue
+            first, _, last = part.partition("-")
+            try:
+                first = int(first)
+                last = int(last) if last else first
+            except ValueError:
+                raise Exception(f"Invalid line range: '{part}'")
+            if last < first:
+                raise Exception(f"Invalid line range: '{part}'")
+            result.append((first, last))
+        return result
+    result = []
+    for line in value:
+        if not isinstance(line, int):
+            raise Exception(f"Invalid line number: {line!r}")
+        result.append((line, line))
+    return result
+
+
+def resolve_style_name(style: TextStyle | str | None, step: Step, ctx) -> TextStyle | None:
+    if isinstance(style, str):
+        name = style
+        style = ctx.get_text_style(style, step)
+        if style is None:
+            raise Exception(f"Style '{name}' not found.")
+    return style
+
+
+def resolve_text_style(style: TextStyle | str | None, step: Step, ctx) -> TextStyle:
+    default_style = ctx.get_text_style("default", step)
+    return merge_in_step(default_style, resolve_style_name(style, step, ctx), step)
 
 
 @dataclass
@@ -21,53 +108,171 @@ class TextContent:
     text: Sv[str]
     style: Sn[TextStyle | str]
     align: Sv[TextAlign]
+    direction: Sv[TextDirection]
+    writing_mode: Sv[WritingMode]
     is_code: bool
     parse_styles: bool
     style_delimiters: str
     syntax_language: Sn[str]
     syntax_theme: Sn[str]
+    syntax_backend: Sn[SyntaxBackend] = None
+    parse_math: bool = False
+    markdown: bool = False
+    links: dict[int, str] | None = None
+    anchors: dict[int, str | tuple[int, int, int]] | None = None
+    anchor_styles: dict[int, Sn[TextStyle | str]] | None = None
+    line_numbers: int | None = None
+    line_numbers_style: Sn[TextStyle | str] = None
+    line_numbers_separator: str = "  "
+    diff_from: Sn[str] = None
+    unified_diff: bool = False
+    diff_added_color: Sv[str] = DEFAULT_DIFF_ADDED_COLOR
+    diff_removed_color: Sv[str] = DEFAULT_DIFF_REMOVED_COLOR
+    highlight_lines: Sn[str | Sequence[int]] = None
+    highlight_color: Sn[str] = None
+    theme_background: bool = False
 
     def to_raw(self, step: Step, ctx) -> RawText | None:
         text = get_step(self.text, step)
         if text is None:
             return None
-        if self.parse_styles:
+        diff_lines = None
+        if self.unified_diff:
+            text, diff_lines = parse_unified_diff(text)
+        else:
+            diff_from = get_step(self.diff_from, step)
+            if diff_from is not None:
+                text, diff_lines = diff_texts(diff_from, text)
+        style = get_step(self.style, step)
+        if self.is_code:
+            code_style = ctx.get_text_style("code", step)
+            style = merge_in_step(code_style, resolve_style_name(style, step, ctx), step)
+        style = resolve_text_style(style, step, ctx)
+
+        if self.markdown:
+            style_names = ctx.get_style_names()
+            # Sizes of headings are relative to the size of this text
+            headings = heading_styles(style.size)
+            text_styles = {}
+            for name in MARKDOWN_STYLE_NAMES:
+                named_style = ctx.get_text_style(name, step) if name in style_names else None
+                if name in headings:
+                    named_style = merge_in_step(headings[name], named_style, step)
+                text_styles[name] = named_style if named_style is not None else TextStyle()
+        elif self.parse_styles:
             style_names = ctx.get_style_names()
             text_styles = {}
             for name in style_names:
                 if name in text:
-                    style = ctx.get_text_style(name, step)
-                    if style is None:
-                        style = TextStyle()
-                    text_styles[name] = style
+                    named_style = ctx.get_text_style(name, step)
+                    if named_style is None:
+                        named_style = TextStyle()
+                    text_styles[name] = named_style
         else:
             text_styles = None
 
-        style = get_step(self.style, step)
-        if isinstance(style, str):
-            name = style
-            style = ctx.get_text_style(style, step)
-            if style is None:
-                raise Exception(f"Style '{name}' not found.")
-        if self.is_code:
-            code_style = ctx.get_text_style("code", step)
-            style = merge_in_step(code_style, style, step)
-        default_style = ctx.get_text_style("default", step)
-        style = merge_in_step(default_style, style, step)
         raw_text = RawText(
-            text=get_step(self.text, step),
+            text=text,
             style=style,
             align=get_step(self.align, step),
+            direction=get_step(self.direction, step),
+            writing_mode=get_step(self.writing_mode, step),
             syntax_language=get_step(self.syntax_language, step, ctx.code_language)
             if self.is_code
             else None,
             syntax_theme=get_step(self.syntax_theme, step, ctx.code_theme)
             if self.is_code
             else None,
+            syntax_backend=get_step(self.syntax_backend, step, ctx.syntax_backend),
             named_styles=text_styles,
             style_delimiters=self.style_delimiters if self.parse_styles else None,
+            parse_math=self.parse_math,
+            markdown=self.markdown,
+            links=self.links,
+            annotations=self._raw_annotations(step, ctx),
+            line_numbers=self._raw_line_numbers(step, ctx),
+            diff=RawTextDiff(
+                lines=diff_lines,
+                added_color=get_step(self.diff_added_color, step),
+                removed_color=get_step(self.diff_removed_color, step),
+            )
+            if diff_lines is not None
+            else None,
+            line_highlights=self._raw_line_highlights(step, diff_lines),
+            theme_background=self.theme_background,
         )
         return raw_text
 
+    def _raw_annotations(self, step: Step, ctx) -> list[RawAnnotation] | None:
+        if not self.anchors:
+            return None
+        anchor_styles = self.anchor_styles or {}
+        result = []
+        for anchor_id, target in self.anchors.items():
+            if not isinstance(target, str):
+                # Lines and columns are counted from 1, the end column is inclusive
+                line, start, end = target
+                target = (line - 1, start - 1, end)
+            style = resolve_style_name(get_step(anchor_styles.get(anchor_id), step), step, ctx)
+            result.append(RawAnnotation(anchor_id=anchor_id, target=target, style=style))
+        return result
+
+    def _raw_line_numbers(self, step: Step, ctx) -> RawLineNumbers | None:
+        if self.line_numbers is None:
+            return None
+        style = get_step(self.line_numbers_style, step)
+        if style is None:
+            style = ctx.get_text_style("line_numbers", step) if "line_numbers" in ctx.get_style_names() else None
+        else:
+            style = resolve_style_name(style, step, ctx)
+        return RawLineNumbers(
+            start=self.line_numbers,
+            style=style if style is not None else TextStyle(),
+            separator=self.line_numbers_separator,
+        )
+
+    def _raw_line_highlights(
+        self, step: Step, diff_lines: list[str] | None
+    ) -> list[tuple[int, int, str | None]] | None:
+        lines = get_step(self.highlight_lines, step)
+        if not lines:
+            return None
+        # Lines are given by their displayed numbers
+        first = self.line_numbers if self.line_numbers is not None else 1
+        color = get_step(self.highlight_color, step)
+        if diff_lines is None:
+            return [
+                (max(start - first, 0), max(end - first + 1, 0), color) for start, end in parse_line_ranges(lines)
+            ]
+        # Removed lines of a diff are not numbered
+        numbered = [idx for idx, kind in enumerate(diff_lines) if kind != "-"]
+        result = []
+        for start, end in parse_line_ranges(lines):
+            start = max(start - first, 0)
+            end = min(end - first, len(numbered) - 1)
+            if start <= end:
+                result.append((numbered[start], numbered[end] + 1, color))
+        return result
+
+    def traverse_tree(self, shared_data, steps):
+        pass
+
+
+@dataclass
+class MathContent:
+    formula: Sv[str]
+    style: Sn[TextStyle | str]
+    display: Sv[bool]
+
+    def to_raw(self, step: Step, ctx) -> RawMath | None:
+        formula = get_step(self.formula, step)
+        if formula is None:
+            return None
+        return RawMath(
+            formula=formula,
+            style=resolve_text_style(get_step(self.style, step), step, ctx),
+            display=get_step(self.display, step),
+        )
+
     def traverse_tree(self, shared_data, steps):
         pass
@@ -1,5 +1,6 @@
 from dataclasses import dataclass
 from enum import IntEnum
+from typing import Literal
 
 from .steps import Sn, Step, get_step, sn_check
 from .utils import unpack_dataclass, check_is_type, check_is_int_or_float
@@ -24,6 +25,26 @@ def check_is_non_negative_int_or_float(obj):
         raise Exception("Value has to be non-negative")
 
 
+DecorationStyle = Literal["solid", "double", "dotted", "dashed", "wavy"]
+
+DECORATION_STYLE_VALUES = ("solid", "double", "dotted", "dashed", "wavy")
+
+
+def check_decoration_style(obj):
+    if obj not in DECORATION_STYLE_VALUES:
+        raise ValueError("Invalid decoration style value")
+
+
+ScriptPosition = Literal["normal", "super", "sub"]
+
+SCRIPT_POSITION_VALUES = ("normal", "super", "sub")
+
+
+def check_script_position(obj):
+    if obj not in SCRIPT_POSITION_VALUES:
+        raise ValueError("Invalid script position value")
+
+
 def check_is_weight(obj):
     check_is_int_or_float(obj)
     if obj < 1 or obj > 1000:
@@ -47,11 +68,34 @@ class TextStyle:
     # If True, ignores weight value and forces weight 700
     bold: Sn[bool] = None
 
+    overline: Sn[bool] = None
+
+    # Background color behind the text (marker effect)
+    bg_color: Sn[str] = None
+
+    # Color, thickness and style of underline, overline, and line through;
+    # by default the text color and the font's thickness is used
+    decoration_color: Sn[str] = None
+    decoration_width: Sn[float] = None
+    decoration_style: Sn[DecorationStyle] = None
+
+    # Moves the text up (positive values) or down (negative values) from the baseline
+    baseline_shift: Sn[float] = None
+
+    # "super" or "sub" makes a superscript or subscript (a raised or lowered text with a smaller size)
+    script: Sn[ScriptPosition] = None
+
     def __post_init__(self):
         sn_check(self.color, check_color)
         sn_check(self.size, check_is_non_negative_int_or_float)
         sn_check(self.line_spacing, check_is_non_negative_int_or_float)
         sn_check(self.weight, check_is_weight)
+        sn_check(self.bg_color, check_color)
+        sn_check(self.decoration_color, check_color)
+        sn_check(self.decoration_width, check_is_non_negative_int_or_float)
+        sn_check(self.decoration_style, check_decoration_style)
+        sn_check(self.baseline_shift, check_is_int_or_float)
+        sn_check(self.script, check_script_position)
 
     def merge(self, other: "TextStyle") -> "TextStyle":
         check_is_text_style(other)
@@ -74,6 +118,13 @@ class TextStyle:
             line_through=get_step(self.line_through, step),
             weight=get_step(self.weight, step),
             bold=get_step(self.bold, step),
+            overline=get_step(self.overline, step),
+            bg_color=get_step(self.bg_color, step),
+            decoration_color=get_step(self.decoration_color, step),
+            decoration_width=get_step(self.decoration_width, step),
+            decoration_style=get_step(self.decoration_style, step),
+            baseline_shift=get_step(self.baseline_shift, step),
+            script=get_step(self.script, step),
         )
 
 
@@ -109,6 +160,28 @@ DEFAULT_TEXT_STYLE = TextStyle(
     line_through=False,
     weight=400,
     bold=False,
+    overline=False,
+    decoration_style="solid",
+    baseline_shift=0,
+    script="normal",
 )
 
 DEFAULT_CODE_STYLE = TextStyle(font="monospace")
+
+SUPERSCRIPT_STYLE = TextStyle(script="super")
+
+SUBSCRIPT_STYLE = TextStyle(script="sub")
+
+EMPH_STYLE = TextStyle(italic=True)
+
+STRONG_STYLE = TextStyle(bold=True)
+
+LINK_STYLE = TextStyle(color="#0645ad", underline=True)
+
+LINE_NUMBERS_STYLE = TextStyle()
+
+HEADING_SIZE_FACTORS = (2.0, 1.5, 1.17, 1.0, 0.83, 0.67)
+
+
+def heading_styles(size: float) -> dict[str, TextStyle]:
+    return {f"h{i + 1}": TextStyle(size=size * factor, bold=True) for i, factor in enumerate(HEADING_SIZE_FACTORS)}
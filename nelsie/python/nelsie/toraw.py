@@ -1,4 +1,5 @@
-from dataclasses import dataclass, field
+import warnings
+from dataclasses import dataclass
 from typing import Union, Literal
 from copy import copy
 
@@ -14,18 +15,18 @@ from .basictypes import (
     AlignItems,
 )
 from .image import RawImage
-from .shapes import RawRect, Stroke
+from .shapes import RawRect
 from .steps import Step, get_step, Sn, step_to_str
 from .text import RawText
 from .textstyle import TextStyle, merge_in_step
 from .box import Box, GridOptions
+from .page import position_relative_to
+from .layoutwarning import LayoutWarning
 from .slidedeck import Slide
 from . import nelsie as nelsie_rs
 
 DEBUG_STEPS_FRAME_HEIGHT = 20
 DEBUG_STEPS_FRAME_FONT_SIZE = 15
-DEBUG_LAYOUT_Z_LEVEL = 999999
-DEBUG_LAYOUT_FONT_SIZE = 8
 DEBUG_LAYOUT_DEFAULT_COLOR = "#ff00ff"
 
 
@@ -33,10 +34,15 @@ DEBUG_LAYOUT_DEFAULT_COLOR = "#ff00ff"
 class RawBox:
     node_id: int
     children: list[Union["RawBox", "RawRect", "RawText", "RawImage"]]
+    name: str = ""
     x: Position | None = None
     y: Position | None = None
     width: Size | None = None
     height: Size | None = None
+    min_width: Length | None = None
+    max_width: Length | None = None
+    min_height: Length | None = None
+    max_height: Length | None = None
     show: bool = True
     content: Union[None, RawText, RawImage] = None
     z_level: int = 0
@@ -53,6 +59,7 @@ class RawBox:
     m_bottom: LengthAuto = 0
     flex_grow: float = 0.0
     flex_shrink: float = 1.0
+    flex_basis: LengthAuto = "auto"
     align_items: AlignItems = None
     align_self: AlignItems = None
     justify_self: AlignItems = None
@@ -63,6 +70,7 @@ class RawBox:
     grid: GridOptions = None
     border_radius: IntOrFloat = 0
     url: str | None = None
+    debug_layout: str | None = None
 
 
 @dataclass
@@ -70,9 +78,9 @@ class ToRawContext:
     text_style_stack: list[dict[str, Sn[TextStyle]]]
     code_theme: str
     code_language: str | None
+    syntax_backend: str
     shared_data: dict[int, bytes]
     debug_layout: str | bool = False
-    debug_layout_boxes: list = field(default_factory=list)
     z_level: int = 0
 
     def get_text_style(self, name: str, step: Step):
@@ -135,24 +143,42 @@ def box_to_raw(box: "Box", step: Step, ctx: ToRawContext) -> RawBox:
             grid = GridOptions(
                 template_rows=get_step(grid.template_rows, step, ()),
                 template_columns=get_step(grid.template_columns, step, ()),
+                template_areas=get_step(grid.template_areas, step, ()),
+                auto_rows=get_step(grid.auto_rows, step, ()),
+                auto_columns=get_step(grid.auto_columns, step, ()),
+                auto_flow=get_step(grid.auto_flow, step, "row"),
                 row=get_step(grid.row, step, "auto"),
                 column=get_step(grid.column, step, "auto"),
+                area=get_step(grid.area, step),
             )
 
     debug_layout = box._debug_layout
     if debug_layout is None:
         debug_layout = ctx.debug_layout
-    if debug_layout:
-        ctx.debug_layout_boxes.append(box)
+    if not debug_layout:
+        debug_layout = None
+    elif not isinstance(debug_layout, str):
+        debug_layout = ctx.debug_layout if isinstance(ctx.debug_layout, str) else DEBUG_LAYOUT_DEFAULT_COLOR
+
+    x = get_step(box._x, step)
+    y = get_step(box._y, step)
+    if box._origin is not None:
+        x = position_relative_to(x, box._origin, True)
+        y = position_relative_to(y, box._origin, False)
 
     return RawBox(
         node_id=id(box),
-        x=get_step(box._x, step),
-        y=get_step(box._y, step),
+        name=box.name,
+        x=x,
+        y=y,
         show=get_step(box._show, step, False),
         z_level=ctx.z_level,
         width=get_step(box._width, step),
         height=get_step(box._height, step),
+        min_width=get_step(box._min_width, step),
+        max_width=get_step(box._max_width, step),
+        min_height=get_step(box._min_height, step),
+        max_height=get_step(box._max_height, step),
         bg_color=get_step(box._bg_color, step),
         children=children_to_raw(box._children, step, ctx),
         content=content,
@@ -168,6 +194,7 @@ def box_to_raw(box: "Box", step: Step, ctx: ToRawContext) -> RawBox:
         m_bottom=get_step(box._m_bottom, step),
         flex_grow=get_step(box._flex_grow, step),
         flex_shrink=get_step(box._flex_shrink, step),
+        flex_basis=get_step(box._flex_basis, step),
         align_items=get_step(box._align_items, step),
         align_self=get_step(box._align_self, step),
         justify_self=get_step(box._justify_self, step),
@@ -178,6 +205,7 @@ def box_to_raw(box: "Box", step: Step, ctx: ToRawContext) -> RawBox:
         border_radius=get_step(box._border_radius, step),
         grid=grid,
         url=get_step(box._url, step),
+        debug_layout=debug_layout,
     )
 
 
@@ -204,7 +232,7 @@ class Document:
     ):
         if path is not None:
             path = str(path)
-        return nelsie_rs.render(
+        result, layout_warnings = nelsie_rs.render(
             self.resources._resources,
             self.pages,
             path,
@@ -213,6 +241,23 @@ class Document:
             n_threads,
             progressbar,
         )
+        if layout_warnings:
+            names = {}
+            for page in self.pages:
+                gather_box_names(page.root, names)
+            for page_index, node_id, kind, amount in layout_warnings:
+                warnings.warn(
+                    LayoutWarning(page_index, node_id, names.get(node_id, ""), kind, amount),
+                    stacklevel=3,
+                )
+        return result
+
+
+def gather_box_names(box: RawBox, names: dict[int, str]):
+    names[box.node_id] = box.name
+    for child in box.children:
+        if isinstance(child, RawBox):
+            gather_box_names(child, names)
 
 
 def children_to_raw(children, step: Step, ctx: ToRawContext):
@@ -252,6 +297,7 @@ def slide_to_raw(
         text_style_stack=stack,
         code_theme=deck.default_code_theme,
         code_language=deck.default_code_language,
+        syntax_backend=deck.default_syntax_backend,
         shared_data=shared_data,
         debug_layout=slide.debug_layout,
     )
@@ -267,11 +313,6 @@ def slide_to_raw(
         bg_color=get_step(slide.bg_color, step, deck.bg_color),
         root=root,
     )
-    if ctx.debug_layout_boxes:
-        temp_doc = Document(resources, [page])
-        layout = temp_doc.render(None, "layout")[0]
-        for box in ctx.debug_layout_boxes:
-            insert_debug_layout_frame(ctx, box, layout, root)
     if slide.debug_steps:
         height += DEBUG_STEPS_FRAME_HEIGHT
         page.root = RawBox(
@@ -308,58 +349,3 @@ def debug_steps_frame(step: Step) -> RawBox:
         ],
     )
 
-
-def insert_debug_layout_frame(
-    ctx: ToRawContext, box: Box, layout: dict, target_box: RawBox
-):
-    box_layout = layout[id(box)]
-    x = box_layout["x"]
-    y = box_layout["y"]
-    width = box_layout["width"]
-    height = box_layout["height"]
-
-    color = DEBUG_LAYOUT_DEFAULT_COLOR
-    if isinstance(ctx.debug_layout, str):
-        color = ctx.debug_layout
-    if isinstance(box._debug_layout, str):
-        color = box._debug_layout
-
-    w = max(1, width)
-    h = max(1, height)
-
-    item = RawRect(
-        shape=0,
-        x1=x,
-        y1=y,
-        x2=x + w,
-        y2=y + h,
-        z_level=DEBUG_LAYOUT_Z_LEVEL,
-        stroke=Stroke(color=color, width=1, dash_array=[5, 2]),
-        fill_color=None,
-    )
-    target_box.children.append(item)
-
-    text_w = f"{width:.2f}".rstrip("0").rstrip(".")
-    text_h = f"{height:.2f}".rstrip("0").rstrip(".")
-
-    if box.name:
-        text = f"{box.name} [{text_w}x{text_h}]"
-    else:
-        text = f"[{text_w}x{text_h}]"
-
-    item = RawBox(
-        node_id=id(item),
-        children=[],
-        z_level=DEBUG_LAYOUT_Z_LEVEL,
-        x=x + 1,
-        y=y + 1,
-        content=RawText(
-            text,
-            TextStyle(
-                size=DEBUG_LAYOUT_FONT_SIZE,
-                font="monospace",
-                color=color,
-            ),
-        ),
-    )
-    target_box.children.append(item)
@@ -0,0 +1,258 @@
+use renderer::taffy::style_helpers::{
+    FromFlex, FromLength, FromPercent, TaffyAuto, TaffyFitContent, TaffyGridLine, TaffyGridSpan,
+    TaffyMaxContent, TaffyMinContent,
+};
+use renderer::taffy::{
+    GridPlacement, GridTrackRepetition, LengthPercentage, Line, MaxTrackSizingFunction, MinMax,
+    MinTrackSizingFunction, NonRepeatedTrackSizingFunction, TrackSizingFunction,
+};
+use std::collections::HashMap;
+
+/// Grid lines of a named template area
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct GridArea {
+    pub rows: Line<i16>,
+    pub columns: Line<i16>,
+}
+
+pub(crate) type GridAreas = HashMap<String, GridArea>;
+
+fn invalid_track(value: &str) -> crate::Error {
+    crate::Error::parsing_err(format!("Invalid grid template: {value}"))
+}
+
+/// Splits arguments of a function call, e.g. "repeat(2, minmax(10, 1fr))" -> ["2", "minmax(10, 1fr)"]
+fn split_function<'a>(value: &'a str, name: &str) -> Option<Vec<&'a str>> {
+    let args = value
+        .strip_prefix(name)?
+        .trim_start()
+        .strip_prefix('(')?
+        .strip_suffix(')')?;
+    let mut result = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in args.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                result.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    result.push(args[start..].trim());
+    Some(result)
+}
+
+fn parse_length_percentage(value: &str) -> Option<LengthPercentage> {
+    if let Some(v) = value.strip_suffix('%') {
+        Some(LengthPercentage::Percent(
+            v.trim().parse::<f32>().ok()? / 100.0,
+        ))
+    } else {
+        Some(LengthPercentage::Length(value.parse().ok()?))
+    }
+}
+
+fn parse_min_track(value: &str) -> Option<MinTrackSizingFunction> {
+    Some(match value {
+        "auto" => MinTrackSizingFunction::AUTO,
+        "min-content" => MinTrackSizingFunction::MIN_CONTENT,
+        "max-content" => MinTrackSizingFunction::MAX_CONTENT,
+        _ => MinTrackSizingFunction::Fixed(parse_length_percentage(value)?),
+    })
+}
+
+fn parse_max_track(value: &str) -> Option<MaxTrackSizingFunction> {
+    Some(match value {
+        "auto" => MaxTrackSizingFunction::AUTO,
+        "min-content" => MaxTrackSizingFunction::MIN_CONTENT,
+        "max-content" => MaxTrackSizingFunction::MAX_CONTENT,
+        _ => {
+            if let Some(v) = value.strip_suffix("fr") {
+                MaxTrackSizingFunction::from_flex(v.trim().parse::<f32>().ok()?)
+            } else {
+                MaxTrackSizingFunction::Fixed(parse_length_percentage(value)?)
+            }
+        }
+    })
+}
+
+/// Parses a single track: a number, "N%", "N fr", "auto", "min-content", "max-content",
+/// "minmax(min, max)" or "fit-content(limit)"
+pub(crate) fn parse_grid_track(value: &str) -> crate::Result<NonRepeatedTrackSizingFunction> {
+    let value = value.trim();
+    if let Some(args) = split_function(value, "minmax") {
+        let [min, max] = args.as_slice() else {
+            return Err(invalid_track(value));
+        };
+        return match (parse_min_track(min), parse_max_track(max)) {
+            (Some(min), Some(max)) => Ok(MinMax { min, max }),
+            _ => Err(invalid_track(value)),
+        };
+    }
+    if let Some(args) = split_function(value, "fit-content") {
+        return match args.as_slice() {
+            [limit] => parse_length_percentage(limit)
+                .map(NonRepeatedTrackSizingFunction::fit_content)
+                .ok_or_else(|| invalid_track(value)),
+            _ => Err(invalid_track(value)),
+        };
+    }
+    Ok(match value {
+        "auto" => NonRepeatedTrackSizingFunction::AUTO,
+        "min-content" => NonRepeatedTrackSizingFunction::MIN_CONTENT,
+        "max-content" => NonRepeatedTrackSizingFunction::MAX_CONTENT,
+        _ => {
+            if let Some(v) = value.strip_suffix('%') {
+                NonRepeatedTrackSizingFunction::from_percent(
+                    v.trim().parse::<f32>().map_err(|_| invalid_track(value))? / 100.0,
+                )
+            } else if let Some(v) = value.strip_suffix("fr") {
+                NonRepeatedTrackSizingFunction::from_flex(
+                    v.trim().parse::<f32>().map_err(|_| invalid_track(value))?,
+                )
+            } else {
+                NonRepeatedTrackSizingFunction::from_length(
+                    value.parse::<f32>().map_err(|_| invalid_track(value))?,
+                )
+            }
+        }
+    })
+}
+
+/// Parses a template item: a single track or "repeat(count, track1, track2, ...)",
+/// where count is a positive number, "auto-fill" or "auto-fit"
+pub(crate) fn parse_grid_template_item(value: &str) -> crate::Result<TrackSizingFunction> {
+    let value = value.trim();
+    if let Some(args) = split_function(value, "repeat") {
+        let Some((count, tracks)) = args.split_first() else {
+            return Err(invalid_track(value));
+        };
+        let repetition = match *count {
+            "auto-fill" => GridTrackRepetition::AutoFill,
+            "auto-fit" => GridTrackRepetition::AutoFit,
+            _ => match count.parse::<u16>() {
+                Ok(n) if n > 0 => GridTrackRepetition::Count(n),
+                _ => return Err(invalid_track(value)),
+            },
+        };
+        if tracks.is_empty() {
+            return Err(invalid_track(value));
+        }
+        let tracks = tracks
+            .iter()
+            .map(|t| parse_grid_track(t))
+            .collect::<crate::Result<Vec<_>>>()?;
+        return Ok(TrackSizingFunction::Repeat(repetition, tracks));
+    }
+    Ok(TrackSizingFunction::Single(parse_grid_track(value)?))
+}
+
+/// Parses template areas; each string is a row of whitespace separated area names,
+/// "." marks an unnamed cell
+pub(crate) fn parse_grid_areas(rows: &[String]) -> crate::Result<(GridAreas, usize)> {
+    // Areas are kept in the order of their first appearance, so errors are deterministic
+    let mut areas: Vec<(&str, _)> = Vec::new();
+    let mut n_columns = None;
+    for (row_idx, row) in rows.iter().enumerate() {
+        let cells: Vec<&str> = row.split_whitespace().collect();
+        if *n_columns.get_or_insert(cells.len()) != cells.len() {
+            return Err(crate::Error::parsing_err(
+                "All rows of grid template areas must have the same number of cells",
+            ));
+        }
+        for (col_idx, name) in cells.into_iter().enumerate() {
+            if name == "." {
+                continue;
+            }
+            let idx = areas
+                .iter()
+                .position(|(n, _)| *n == name)
+                .unwrap_or_else(|| {
+                    let span = |idx| Line {
+                        start: idx,
+                        end: idx,
+                    };
+                    areas.push((name, (span(row_idx), span(col_idx))));
+                    areas.len() - 1
+                });
+            let (row_span, column_span) = &mut areas[idx].1;
+            row_span.end = row_idx;
+            column_span.start = column_span.start.min(col_idx);
+            column_span.end = column_span.end.max(col_idx);
+        }
+    }
+    // Every area has to fill exactly its bounding rectangle
+    let mut result = GridAreas::new();
+    for (name, (rows_span, columns_span)) in areas {
+        for row in &rows[rows_span.start..=rows_span.end] {
+            let cells: Vec<&str> = row.split_whitespace().collect();
+            if cells
+                .iter()
+                .enumerate()
+                .any(|(i, c)| (*c == name) != (columns_span.start..=columns_span.end).contains(&i))
+            {
+                return Err(crate::Error::parsing_err(format!(
+                    "Grid area '{name}' is not a rectangle"
+                )));
+            }
+        }
+        let to_lines = |span: Line<usize>| Line {
+            start: span.start as i16 + 1,
+            end: span.end as i16 + 2,
+        };
+        result.insert(
+            name.to_string(),
+            GridArea {
+                rows: to_lines(rows_span),
+                columns: to_lines(columns_span),
+            },
+        );
+    }
+    Ok((result, n_columns.unwrap_or(0)))
+}
+
+/// Parses a placement of a box in one axis: "auto", "span N", a line index,
+/// an area name, "<area>-start" or "<area>-end".
+/// An area name alone refers to the start line of the area when `is_end` is false, otherwise to its end line.
+/// `lines` returns lines of an area in the placed axis.
+pub(crate) fn parse_grid_placement(
+    value: &str,
+    is_end: bool,
+    lines: impl Fn(&str) -> Option<Line<i16>>,
+) -> crate::Result<GridPlacement> {
+    let value = value.trim();
+    if value == "auto" {
+        return Ok(GridPlacement::AUTO);
+    }
+    if let Some(span) = value.strip_prefix("span ") {
+        return match span.trim().parse::<u16>() {
+            Ok(span) if span > 0 => Ok(GridPlacement::from_span(span)),
+            _ => Err(crate::Error::parsing_err("Invalid grid placement")),
+        };
+    }
+    if let Ok(index) = value.parse::<i16>() {
+        // There is no line 0, taffy would treat it as "auto"
+        if index == 0 {
+            return Err(crate::Error::parsing_err("Invalid grid placement"));
+        }
+        return Ok(GridPlacement::from_line_index(index));
+    }
+    let (name, is_end) = if let Some(name) = value.strip_suffix("-start") {
+        (name, false)
+    } else if let Some(name) = value.strip_suffix("-end") {
+        (name, true)
+    } else {
+        (value, is_end)
+    };
+    let line = lines(name)
+        .ok_or_else(|| crate::Error::parsing_err(format!("Unknown grid area '{value}'")))?;
+    Ok(GridPlacement::from_line_index(if is_end {
+        line.end
+    } else {
+        line.start
+    }))
+}
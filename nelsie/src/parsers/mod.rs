@@ -1,2 +1,3 @@
+pub(crate) mod grid;
 pub(crate) mod length;
 pub(crate) mod steps;
@@ -1,3 +1,5 @@
+use crate::parsers::grid::parse_grid_areas;
+use crate::pyinterface::extract::{PyGridAutoFlow, PyGridTemplateItem, PyGridTrack};
 use pyo3::exceptions::PyException;
 use pyo3::types::PyAnyMethods;
 use pyo3::{pyfunction, Bound, PyAny, PyResult};
@@ -13,3 +15,27 @@ pub(crate) fn check_color<'py>(obj: &Bound<'py, PyAny>) -> PyResult<()> {
     }
     Err(PyException::new_err(format!("Invalid color: '{}'", obj)))
 }
+
+#[pyfunction]
+pub(crate) fn check_grid_template<'py>(obj: &Bound<'py, PyAny>) -> PyResult<()> {
+    obj.extract::<Vec<PyGridTemplateItem>>()?;
+    Ok(())
+}
+
+#[pyfunction]
+pub(crate) fn check_grid_tracks<'py>(obj: &Bound<'py, PyAny>) -> PyResult<()> {
+    obj.extract::<Vec<PyGridTrack>>()?;
+    Ok(())
+}
+
+#[pyfunction]
+pub(crate) fn check_grid_areas<'py>(obj: &Bound<'py, PyAny>) -> PyResult<()> {
+    parse_grid_areas(&obj.extract::<Vec<String>>()?)?;
+    Ok(())
+}
+
+#[pyfunction]
+pub(crate) fn check_grid_auto_flow<'py>(obj: &Bound<'py, PyAny>) -> PyResult<()> {
+    obj.extract::<PyGridAutoFlow>()?;
+    Ok(())
+}
@@ -1,21 +1,23 @@
+use crate::parsers::grid::{
+    parse_grid_areas, parse_grid_placement, parse_grid_template_item, parse_grid_track, GridAreas,
+};
 use crate::parsers::length::parse_string_length;
 use crate::pyinterface::common::PyColor;
 use crate::pyinterface::image::{PyImage, PyImageData};
 use crate::pyinterface::layoutexpr::extract_layout_expr;
-use crate::pyinterface::shapes::{DimX, DimY, PyPath, PyPosition, PyRect};
-use crate::pyinterface::text::PyTextContent;
+use crate::pyinterface::shapes::{DimX, DimY, PyConnector, PyPath, PyPosition, PyRect};
+use crate::pyinterface::text::{PyMathContent, PyTextContent};
 use pyo3::exceptions::PyValueError;
 use pyo3::prelude::PyAnyMethods;
 use pyo3::types::PyList;
 use pyo3::{intern, Bound, FromPyObject, PyAny, PyResult};
-use renderer::taffy::style_helpers::{
-    FromFlex, FromLength, FromPercent, TaffyGridLine, TaffyGridSpan,
-};
+use renderer::taffy::style_helpers::{FromLength, TaffyAuto, TaffyGridLine};
 use renderer::taffy::{
-    AlignContent, AlignItems, GridPlacement, Line, NonRepeatedTrackSizingFunction,
+    AlignContent, AlignItems, GridAutoFlow, GridPlacement, Line, NonRepeatedTrackSizingFunction,
+    TrackSizingFunction,
 };
 use renderer::{
-    Length, LengthOrAuto, LengthOrExpr, Node, NodeChild, NodeId, Page, Rectangle, Register,
+    Length, LengthOrAuto, LengthOrExpr, Math, Node, NodeChild, NodeId, Page, Rectangle, Register,
     Resources, Text,
 };
 
@@ -62,7 +64,11 @@ impl<'py> FromPyObject<'py> for PyLengthOrAuto {
         Ok(PyLengthOrAuto(if let Ok(value) = obj.extract::<f32>() {
             LengthOrAuto::Length(Length::Points { value })
         } else if let Ok(value) = obj.extract::<&str>() {
-            LengthOrAuto::Length(parse_string_length(value)?)
+            if value.trim() == "auto" {
+                LengthOrAuto::Auto
+            } else {
+                LengthOrAuto::Length(parse_string_length(value)?)
+            }
         } else {
             return Err(PyValueError::new_err("Invalid length definition"));
         }))
@@ -119,28 +125,15 @@ impl From<PyAlignContent> for AlignContent {
     }
 }
 
-struct PyGridTemplateItem(NonRepeatedTrackSizingFunction);
+pub(crate) struct PyGridTemplateItem(TrackSizingFunction);
 
 impl<'py> FromPyObject<'py> for PyGridTemplateItem {
     fn extract_bound(obj: &Bound<'py, PyAny>) -> PyResult<Self> {
         Ok(PyGridTemplateItem(
             if let Ok(value) = obj.extract::<f32>() {
-                NonRepeatedTrackSizingFunction::from_length(value)
+                TrackSizingFunction::from_length(value)
             } else if let Ok(value) = obj.extract::<&str>() {
-                let value = value.trim();
-                if let Some(value) = value.strip_suffix("%") {
-                    let value = value.trim();
-                    NonRepeatedTrackSizingFunction::from_percent(value.parse::<f32>()? / 100.0)
-                } else if let Some(value) = value.strip_suffix("fr") {
-                    let value = value.trim();
-                    NonRepeatedTrackSizingFunction::from_flex(value.parse::<f32>()?)
-                } else if let Ok(value) = value.parse::<f32>() {
-                    NonRepeatedTrackSizingFunction::from_length(value)
-                } else {
-                    return Err(PyValueError::new_err(format!(
-                        "Invalid grid template: {value}"
-                    )));
-                }
+                parse_grid_template_item(value)?
             } else {
                 return Err(PyValueError::new_err("Invalid grid template"));
             },
@@ -148,80 +141,160 @@ impl<'py> FromPyObject<'py> for PyGridTemplateItem {
     }
 }
 
-impl From<PyGridTemplateItem> for NonRepeatedTrackSizingFunction {
-    fn from(value: PyGridTemplateItem) -> Self {
-        value.0
+pub(crate) struct PyGridTrack(NonRepeatedTrackSizingFunction);
+
+impl<'py> FromPyObject<'py> for PyGridTrack {
+    fn extract_bound(obj: &Bound<'py, PyAny>) -> PyResult<Self> {
+        Ok(PyGridTrack(if let Ok(value) = obj.extract::<f32>() {
+            NonRepeatedTrackSizingFunction::from_length(value)
+        } else if let Ok(value) = obj.extract::<&str>() {
+            parse_grid_track(value)?
+        } else {
+            return Err(PyValueError::new_err("Invalid grid track"));
+        }))
     }
 }
 
-struct PyGridLinePlacement(Line<GridPlacement>);
+pub(crate) struct PyGridAutoFlow(GridAutoFlow);
 
-fn parse_grid_placement_item(obj: &Bound<PyAny>) -> PyResult<GridPlacement> {
-    if obj.is_none() {
-        return Ok(GridPlacement::Auto);
+impl<'py> FromPyObject<'py> for PyGridAutoFlow {
+    fn extract_bound(obj: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let s: &str = obj.extract()?;
+        Ok(PyGridAutoFlow(match s {
+            "row" => GridAutoFlow::Row,
+            "column" => GridAutoFlow::Column,
+            "row dense" => GridAutoFlow::RowDense,
+            "column dense" => GridAutoFlow::ColumnDense,
+            _ => return Err(PyValueError::new_err("Invalid grid auto flow")),
+        }))
     }
-    if let Ok(value) = obj.extract::<&str>() {
-        let value = value.trim();
-        if value == "auto" {
-            return Ok(GridPlacement::Auto);
-        }
-        if let Some(value) = value.strip_prefix("span ") {
-            let value: u16 = value.trim().parse()?;
-            return Ok(GridPlacement::from_span(value));
-        }
-        if let Ok(value) = value.parse() {
-            return Ok(GridPlacement::from_line_index(value));
+}
+
+enum PyGridPlacementItem {
+    Index(i16),
+    Text(String),
+}
+
+impl<'py> FromPyObject<'py> for PyGridPlacementItem {
+    fn extract_bound(obj: &Bound<'py, PyAny>) -> PyResult<Self> {
+        if obj.is_none() {
+            Ok(PyGridPlacementItem::Text("auto".to_string()))
+        } else if let Ok(value) = obj.extract::<i16>() {
+            Ok(PyGridPlacementItem::Index(value))
+        } else if let Ok(value) = obj.extract::<String>() {
+            Ok(PyGridPlacementItem::Text(value))
+        } else {
+            Err(PyValueError::new_err("Invalid grid placement"))
         }
-        Err(PyValueError::new_err("Invalid grid placement"))
-    } else if let Ok(value) = obj.extract::<i16>() {
-        Ok(GridPlacement::from_line_index(value))
-    } else {
-        Err(PyValueError::new_err("Invalid grid placement"))
     }
 }
 
+impl PyGridPlacementItem {
+    fn parse(
+        &self,
+        is_end: bool,
+        areas: Option<&GridAreas>,
+        is_row: bool,
+    ) -> PyResult<GridPlacement> {
+        Ok(match self {
+            PyGridPlacementItem::Index(0) => {
+                return Err(PyValueError::new_err("Invalid grid placement"))
+            }
+            PyGridPlacementItem::Index(index) => GridPlacement::from_line_index(*index),
+            PyGridPlacementItem::Text(value) => {
+                parse_grid_placement(value, is_end, |name| area_lines(areas, name, is_row))?
+            }
+        })
+    }
+}
+
+fn area_lines(areas: Option<&GridAreas>, name: &str, is_row: bool) -> Option<Line<i16>> {
+    areas?
+        .get(name)
+        .map(|area| if is_row { area.rows } else { area.columns })
+}
+
+/// Grid placement is resolved when the node is extracted, because it may refer to areas of the parent grid
+enum PyGridLinePlacement {
+    Single(PyGridPlacementItem),
+    Pair(PyGridPlacementItem, PyGridPlacementItem),
+}
+
 impl<'py> FromPyObject<'py> for PyGridLinePlacement {
     fn extract_bound(obj: &Bound<'py, PyAny>) -> PyResult<Self> {
-        if let Ok(value) = parse_grid_placement_item(obj) {
-            return Ok(PyGridLinePlacement(Line {
-                start: value,
-                end: GridPlacement::Auto,
-            }));
+        if let Ok(value) = obj.extract() {
+            return Ok(PyGridLinePlacement::Single(value));
         } else if let Ok((value1, value2)) = obj.extract::<(Bound<'py, PyAny>, Bound<'py, PyAny>)>()
         {
-            if let Ok(start) = parse_grid_placement_item(&value1) {
-                if let Ok(end) = parse_grid_placement_item(&value2) {
-                    return Ok(PyGridLinePlacement(Line { start, end }));
-                }
+            if let (Ok(start), Ok(end)) = (value1.extract(), value2.extract()) {
+                return Ok(PyGridLinePlacement::Pair(start, end));
             }
         }
         Err(PyValueError::new_err("Invalid grid placement"))
     }
 }
 
+impl PyGridLinePlacement {
+    fn resolve(&self, areas: Option<&GridAreas>, is_row: bool) -> PyResult<Line<GridPlacement>> {
+        if let PyGridLinePlacement::Single(PyGridPlacementItem::Text(name)) = self {
+            if let Some(lines) = area_lines(areas, name.trim(), is_row) {
+                return Ok(Line {
+                    start: GridPlacement::from_line_index(lines.start),
+                    end: GridPlacement::from_line_index(lines.end),
+                });
+            }
+        }
+        Ok(match self {
+            PyGridLinePlacement::Single(item) => Line {
+                start: item.parse(false, areas, is_row)?,
+                end: GridPlacement::Auto,
+            },
+            PyGridLinePlacement::Pair(start, end) => Line {
+                start: start.parse(false, areas, is_row)?,
+                end: end.parse(true, areas, is_row)?,
+            },
+        })
+    }
+}
+
 #[derive(FromPyObject)]
 struct PyGridOptions {
     template_rows: Vec<PyGridTemplateItem>,
     template_columns: Vec<PyGridTemplateItem>,
+    template_areas: Vec<String>,
+    auto_rows: Vec<PyGridTrack>,
+    auto_columns: Vec<PyGridTrack>,
+    auto_flow: PyGridAutoFlow,
     row: PyGridLinePlacement,
     column: PyGridLinePlacement,
+    area: Option<String>,
+}
+
+fn extract_text_content(obj: &Bound<PyAny>) -> PyResult<Box<PyTextContent>> {
+    obj.extract().map(Box::new)
 }
 
 #[derive(FromPyObject)]
 enum NodeContent<'py> {
-    Text(PyTextContent),
+    Text(#[pyo3(from_py_with = extract_text_content)] Box<PyTextContent>),
+    Math(PyMathContent),
     Image(Bound<'py, PyImage>),
 }
 
 #[derive(FromPyObject)]
 struct PyNode<'py> {
     node_id: usize,
+    name: String,
     x: Option<PyPosition<DimX>>,
     y: Option<PyPosition<DimY>>,
     show: bool,
     z_level: i32,
     width: Option<PyLengthOrExpr>,
     height: Option<PyLengthOrExpr>,
+    min_width: Option<PyLength>,
+    max_width: Option<PyLength>,
+    min_height: Option<PyLength>,
+    max_height: Option<PyLength>,
     bg_color: Option<PyColor>,
     border_radius: f32,
     row: bool,
@@ -238,6 +311,7 @@ struct PyNode<'py> {
     m_bottom: PyLengthOrAuto,
     flex_grow: f32,
     flex_shrink: f32,
+    flex_basis: PyLengthOrAuto,
     align_items: Option<PyAlignItems>,
     align_self: Option<PyAlignItems>,
     justify_self: Option<PyAlignItems>,
@@ -247,6 +321,7 @@ struct PyNode<'py> {
     gap_y: PyLength,
     grid: Option<PyGridOptions>,
     url: Option<String>,
+    debug_layout: Option<PyColor>,
 }
 
 fn check_font_or_fail(font: &str, resources: &mut Resources) -> PyResult<()> {
@@ -260,14 +335,23 @@ fn obj_to_node(
     obj: Bound<PyAny>,
     register: &mut Register,
     resources: &mut Resources,
+    parent_areas: Option<&GridAreas>,
 ) -> PyResult<Node> {
     let node: PyNode = obj.extract()?;
+    let mut theme_bg_color = None;
     let content = node
         .content
         .map(|content| -> PyResult<_> {
             Ok(match content {
                 NodeContent::Text(text) => {
-                    let text: Text = text.try_into()?;
+                    let theme_background = text.theme_background;
+                    let text: Text = (*text).try_into()?;
+                    if let (true, Some(hl)) = (theme_background, &text.syntax_highlight) {
+                        theme_bg_color = resources
+                            .theme_colors(&hl.theme)
+                            .map_err(crate::Error::from)?
+                            .background;
+                    }
                     text.style
                         .font
                         .as_ref()
@@ -275,6 +359,15 @@ fn obj_to_node(
                         .transpose()?;
                     Some(register.register_text(text))
                 }
+                NodeContent::Math(math) => {
+                    let math: Math = math.try_into()?;
+                    math.style
+                        .font
+                        .as_ref()
+                        .map(|f| check_font_or_fail(f, resources))
+                        .transpose()?;
+                    Some(register.register_math(math))
+                }
                 NodeContent::Image(image) => {
                     let image = image.get();
                     match &image.image_data {
@@ -347,32 +440,73 @@ fn obj_to_node(
 
     let i_node_id = intern!(obj.py(), "node_id");
     let i_shape = intern!(obj.py(), "shape");
+    let i_routing = intern!(obj.py(), "routing");
 
-    let (grid_template_rows, grid_template_columns, grid_row, grid_column) =
-        if let Some(o) = node.grid {
+    let mut grid_areas = None;
+    let (
+        grid_template_rows,
+        grid_template_columns,
+        grid_auto_rows,
+        grid_auto_columns,
+        grid_auto_flow,
+        grid_row,
+        grid_column,
+    ) = if let Some(o) = node.grid {
+        let mut template_rows: Vec<_> = o.template_rows.into_iter().map(|x| x.0).collect();
+        let mut template_columns: Vec<_> = o.template_columns.into_iter().map(|x| x.0).collect();
+        if !o.template_areas.is_empty() {
+            let (areas, n_columns) = parse_grid_areas(&o.template_areas)?;
+            // Areas define the explicit grid when there are no templates
+            if template_rows.is_empty() {
+                template_rows = vec![TrackSizingFunction::AUTO; o.template_areas.len()];
+            }
+            if template_columns.is_empty() {
+                template_columns = vec![TrackSizingFunction::AUTO; n_columns];
+            }
+            grid_areas = Some(areas);
+        }
+        // Area is a shortcut for the same name in both axes
+        let (row, column) = if let Some(area) = o.area {
+            let area = PyGridLinePlacement::Single(PyGridPlacementItem::Text(area));
             (
-                o.template_rows.into_iter().map(|x| x.into()).collect(),
-                o.template_columns.into_iter().map(|x| x.into()).collect(),
-                o.row.0,
-                o.column.0,
+                area.resolve(parent_areas, true)?,
+                area.resolve(parent_areas, false)?,
             )
         } else {
             (
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
+                o.row.resolve(parent_areas, true)?,
+                o.column.resolve(parent_areas, false)?,
             )
         };
+        (
+            template_rows,
+            template_columns,
+            o.auto_rows.into_iter().map(|x| x.0).collect(),
+            o.auto_columns.into_iter().map(|x| x.0).collect(),
+            o.auto_flow.0,
+            row,
+            column,
+        )
+    } else {
+        Default::default()
+    };
 
     Ok(Node {
         grid_template_rows,
         grid_template_columns,
+        grid_auto_rows,
+        grid_auto_columns,
+        grid_auto_flow,
         grid_row,
         grid_column,
         node_id: NodeId::new(node.node_id),
+        name: node.name,
         width: node.width.map(|x| x.0),
         height: node.height.map(|x| x.0),
+        min_width: node.min_width.map(|x| x.0),
+        max_width: node.max_width.map(|x| x.0),
+        min_height: node.min_height.map(|x| x.0),
+        max_height: node.max_height.map(|x| x.0),
         show: node.show,
         x: node.x.map(|x| x.expr),
         y: node.y.map(|x| x.expr),
@@ -382,6 +516,7 @@ fn obj_to_node(
         flex_wrap: Default::default(),
         flex_grow: node.flex_grow,
         flex_shrink: node.flex_shrink,
+        flex_basis: node.flex_basis.0,
         align_items: node.align_items.map(|x| x.into()),
         align_self: node.align_self.map(|x| x.into()),
         justify_self: node.justify_self.map(|x| x.into()),
@@ -397,20 +532,29 @@ fn obj_to_node(
         m_bottom: node.m_bottom.0,
         m_left: node.m_left.0,
         m_right: node.m_right.0,
-        bg_color: node.bg_color.map(|x| x.into()),
+        bg_color: node.bg_color.map(|x| x.into()).or(theme_bg_color),
         z_level: node.z_level,
         content,
         url: node.url,
+        debug_layout: node.debug_layout.map(|x| x.into()),
         children: node
             .children
             .try_iter()?
             .map(|child| {
                 let child = child?;
                 Ok(if child.hasattr(i_node_id)? {
-                    NodeChild::Node(obj_to_node(child, register, resources)?)
+                    NodeChild::Node(Box::new(obj_to_node(
+                        child,
+                        register,
+                        resources,
+                        grid_areas.as_ref(),
+                    )?))
                 } else if child.hasattr(i_shape)? {
                     let rect: PyRect = child.extract()?;
                     NodeChild::Shape(rect.into_shape())
+                } else if child.hasattr(i_routing)? {
+                    let connector: PyConnector = child.extract()?;
+                    NodeChild::Shape(connector.into_shape()?)
                 } else {
                     let path: PyPath = child.extract()?;
                     NodeChild::Shape(path.into_shape()?)
@@ -427,7 +571,7 @@ pub fn obj_to_page(
 ) -> PyResult<Page> {
     let py_page: PyPage = obj.extract()?;
     Ok(Page::new(
-        obj_to_node(py_page.root, register, resources)?,
+        obj_to_node(py_page.root, register, resources, None)?,
         py_page.width,
         py_page.height,
         py_page.bg_color.into(),
@@ -1,7 +1,7 @@
 use pyo3::exceptions::PyValueError;
 use pyo3::types::PyAnyMethods;
 use pyo3::{intern, Bound, PyAny, PyResult};
-use renderer::{InlineId, LayoutExpr, NodeId};
+use renderer::{InlineId, LayoutExpr, NodeId, ShapeId};
 
 // #[derive(Debug)]
 // pub(crate) struct PyLayoutExpr(LayoutExpr);
@@ -34,16 +34,35 @@ pub(crate) fn extract_layout_expr(obj: &Bound<PyAny>) -> PyResult<LayoutExpr> {
     let op = obj.getattr(intern!(py, "_op"))?;
     let name: &str = op.extract()?;
     match name {
-        "+" | "-" | "*" => {
+        "+" | "-" | "*" | "/" => {
             let expr_a = extract_layout_expr(&v0)?;
             let expr_b = extract_layout_expr(&v1)?;
             Ok(match name {
                 "+" => LayoutExpr::add(expr_a, expr_b),
                 "-" => LayoutExpr::sub(expr_a, expr_b),
                 "*" => LayoutExpr::mul(expr_a, expr_b),
+                "/" => LayoutExpr::div(expr_a, expr_b),
                 _ => unreachable!(),
             })
         }
+        "neg" => Ok(LayoutExpr::neg(extract_layout_expr(&v0)?)),
+        "abs" => Ok(LayoutExpr::abs(extract_layout_expr(&v0)?)),
+        "clamp" => Ok(LayoutExpr::clamp(
+            extract_layout_expr(&v0)?,
+            extract_layout_expr(&v1)?,
+            extract_layout_expr(&obj.getattr(arg2)?)?,
+        )),
+        "lerp" => Ok(LayoutExpr::lerp(
+            extract_layout_expr(&v0)?,
+            extract_layout_expr(&v1)?,
+            extract_layout_expr(&obj.getattr(arg2)?)?,
+        )),
+        "page_width" => Ok(LayoutExpr::PageWidth {
+            fraction: v0.extract()?,
+        }),
+        "page_height" => Ok(LayoutExpr::PageHeight {
+            fraction: v0.extract()?,
+        }),
         "x" => Ok(LayoutExpr::X {
             node_id: NodeId::new(v0.extract()?),
         }),
@@ -94,12 +113,40 @@ pub(crate) fn extract_layout_expr(obj: &Bound<PyAny>) -> PyResult<LayoutExpr> {
             inline_id: InlineId::new(v1.extract()?),
             fraction: obj.getattr(arg2)?.extract()?,
         }),
+        "shape_x" => Ok(LayoutExpr::ShapeX {
+            shape_id: ShapeId::new(v0.extract()?),
+        }),
+        "shape_y" => Ok(LayoutExpr::ShapeY {
+            shape_id: ShapeId::new(v0.extract()?),
+        }),
+        "shape_width" => Ok(LayoutExpr::ShapeWidth {
+            shape_id: ShapeId::new(v0.extract()?),
+            fraction: v1.extract()?,
+        }),
+        "shape_height" => Ok(LayoutExpr::ShapeHeight {
+            shape_id: ShapeId::new(v0.extract()?),
+            fraction: v1.extract()?,
+        }),
+        "shape_point_x" => Ok(LayoutExpr::ShapePointX {
+            shape_id: ShapeId::new(v0.extract()?),
+            fraction: v1.extract()?,
+        }),
+        "shape_point_y" => Ok(LayoutExpr::ShapePointY {
+            shape_id: ShapeId::new(v0.extract()?),
+            fraction: v1.extract()?,
+        }),
         "max" => Ok(LayoutExpr::max({
             let v: Vec<Bound<PyAny>> = v0.extract()?;
             v.into_iter()
                 .map(|obj| extract_layout_expr(&obj))
                 .collect::<PyResult<_>>()?
         })),
+        "min" => Ok(LayoutExpr::min({
+            let v: Vec<Bound<PyAny>> = v0.extract()?;
+            v.into_iter()
+                .map(|obj| extract_layout_expr(&obj))
+                .collect::<PyResult<_>>()?
+        })),
         _ => Err(PyValueError::new_err("Invalid expression")),
     }
 }
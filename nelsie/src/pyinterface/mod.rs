@@ -21,6 +21,10 @@ use pyo3::prelude::*;
 fn nelsie(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<Resources>()?;
     m.add_function(wrap_pyfunction!(check::check_color, m)?)?;
+    m.add_function(wrap_pyfunction!(check::check_grid_template, m)?)?;
+    m.add_function(wrap_pyfunction!(check::check_grid_tracks, m)?)?;
+    m.add_function(wrap_pyfunction!(check::check_grid_areas, m)?)?;
+    m.add_function(wrap_pyfunction!(check::check_grid_auto_flow, m)?)?;
     m.add_function(wrap_pyfunction!(rendering::render, m)?)?;
     m.add_function(wrap_pyfunction!(image::create_mem_image, m)?)?;
     m.add_function(wrap_pyfunction!(image::load_image, m)?)?;
@@ -3,10 +3,13 @@ use crate::pyinterface::resources::Resources;
 use pyo3::exceptions::PyException;
 use pyo3::types::{PyDict, PyDictMethods, PyList};
 use pyo3::{pyfunction, Bound, IntoPyObjectExt, PyAny, PyResult, Python};
-use renderer::{Document, PageLayout, Register, RenderingOptions};
+use renderer::{
+    Document, LayoutWarning, LayoutWarningKind, PageLayout, Register, RenderingOptions,
+};
 use std::collections::HashMap;
 
-/// Formats the sum of two numbers as string.
+/// Renders pages; returns the output and a list of layout warnings
+/// as tuples (page index, node id, kind, amount)
 #[pyfunction]
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn render<'py>(
@@ -31,8 +34,19 @@ pub(crate) fn render<'py>(
         n_threads,
         progressbar,
     };
-    let result = py.detach(|| run_rendering(resources, &options, path, format, doc))?;
-    Ok(match result {
+    let (result, warnings) = py.detach(|| run_rendering(resources, &options, path, format, doc))?;
+    let warnings: Vec<_> = warnings
+        .into_iter()
+        .map(|w| {
+            (
+                w.page_idx,
+                w.node_id.as_usize(),
+                warning_kind_name(w.kind),
+                w.amount,
+            )
+        })
+        .collect();
+    let output = match result {
         RenderingOutput::None => py.None().into_bound(py),
         RenderingOutput::LayoutInfo(info) => {
             let v = info
@@ -56,7 +70,17 @@ pub(crate) fn render<'py>(
         RenderingOutput::SingleBinOutput(output) => output.into_bound_py_any(py)?,
         RenderingOutput::ManyBinOutputs(outputs) => outputs.into_bound_py_any(py)?,
         RenderingOutput::ManyStringOutputs(outputs) => outputs.into_bound_py_any(py)?,
-    })
+    };
+    (output, warnings).into_bound_py_any(py)
+}
+
+fn warning_kind_name(kind: LayoutWarningKind) -> &'static str {
+    match kind {
+        LayoutWarningKind::ParentOverflow => "parent",
+        LayoutWarningKind::PageOverflow => "page",
+        LayoutWarningKind::TextOverflow => "text",
+        LayoutWarningKind::EmptyNode => "empty",
+    }
 }
 
 enum RenderingOutput {
@@ -73,46 +97,49 @@ fn run_rendering(
     path: Option<&str>,
     format: &str,
     doc: Document,
-) -> PyResult<RenderingOutput> {
+) -> PyResult<(RenderingOutput, Vec<LayoutWarning>)> {
     Ok(match (path, format) {
         (Some(path), "pdf") => {
-            doc.render_pdf_to_file(&resources.resources, options, std::path::Path::new(path))
+            let warnings = doc
+                .render_pdf_to_file(&resources.resources, options, std::path::Path::new(path))
                 .map_err(crate::Error::from)?;
-            RenderingOutput::None
+            (RenderingOutput::None, warnings)
         }
         (Some(path), "png") => {
-            doc.render_png_to_dir(&resources.resources, options, std::path::Path::new(path))
+            let warnings = doc
+                .render_png_to_dir(&resources.resources, options, std::path::Path::new(path))
                 .map_err(crate::Error::from)?;
-            RenderingOutput::None
+            (RenderingOutput::None, warnings)
         }
         (Some(path), "svg") => {
-            doc.render_svg_to_dir(&resources.resources, options, std::path::Path::new(path))
+            let warnings = doc
+                .render_svg_to_dir(&resources.resources, options, std::path::Path::new(path))
                 .map_err(crate::Error::from)?;
-            RenderingOutput::None
+            (RenderingOutput::None, warnings)
         }
         (_, "layout") => {
-            let layout = doc
+            let (layout, warnings) = doc
                 .render_layout_info(&resources.resources, options)
                 .map_err(crate::Error::from)?;
-            RenderingOutput::LayoutInfo(layout)
+            (RenderingOutput::LayoutInfo(layout), warnings)
         }
         (None, "pdf") => {
-            let output = doc
+            let (output, warnings) = doc
                 .render_pdf_to_mem(&resources.resources, options)
                 .map_err(crate::Error::from)?;
-            RenderingOutput::SingleBinOutput(output)
+            (RenderingOutput::SingleBinOutput(output), warnings)
         }
         (None, "svg") => {
-            let output = doc
+            let (output, warnings) = doc
                 .render_svg_to_vec(&resources.resources, options)
                 .map_err(crate::Error::from)?;
-            RenderingOutput::ManyStringOutputs(output)
+            (RenderingOutput::ManyStringOutputs(output), warnings)
         }
         (None, "png") => {
-            let output = doc
+            let (output, warnings) = doc
                 .render_png_to_vec(&resources.resources, options)
                 .map_err(crate::Error::from)?;
-            RenderingOutput::ManyBinOutputs(output)
+            (RenderingOutput::ManyBinOutputs(output), warnings)
         }
 
         // (None, "png") => {
@@ -47,6 +47,52 @@ impl Resources {
         Ok(())
     }
 
+    fn load_code_syntax_file(&mut self, path: &str) -> PyResult<()> {
+        self.resources
+            .load_code_syntax_file(Path::new(path))
+            .map_err(crate::Error::from)?;
+        Ok(())
+    }
+
+    fn load_code_syntax_str(&mut self, source: &str) -> PyResult<()> {
+        self.resources
+            .load_code_syntax_str(source)
+            .map_err(crate::Error::from)?;
+        Ok(())
+    }
+
+    fn load_code_theme_file(&mut self, path: &str) -> PyResult<()> {
+        self.resources
+            .load_code_theme_file(Path::new(path))
+            .map_err(crate::Error::from)?;
+        Ok(())
+    }
+
+    fn load_code_theme_str(&mut self, name: &str, source: &str) -> PyResult<()> {
+        self.resources
+            .load_code_theme_str(name, source)
+            .map_err(crate::Error::from)?;
+        Ok(())
+    }
+
+    fn dump_code_syntaxes(&self, path: &str) -> PyResult<()> {
+        self.resources
+            .dump_code_syntaxes(Path::new(path))
+            .map_err(crate::Error::from)?;
+        Ok(())
+    }
+
+    fn load_code_syntax_dump(&mut self, path: &str) -> PyResult<()> {
+        self.resources
+            .load_code_syntax_dump(Path::new(path))
+            .map_err(crate::Error::from)?;
+        Ok(())
+    }
+
+    fn code_load_failures(&self) -> PyResult<Vec<(String, String)>> {
+        Ok(self.resources.code_load_failures.clone())
+    }
+
     fn load_fonts_dir(&mut self, path: &str) -> PyResult<()> {
         self.resources
             .load_fonts_dir(Path::new(path))
@@ -64,6 +110,28 @@ impl Resources {
             .collect_vec())
     }
 
+    fn theme_colors(&self, theme: &str) -> PyResult<Vec<(&'static str, Option<String>)>> {
+        let colors = self
+            .resources
+            .theme_colors(theme)
+            .map_err(crate::Error::from)?;
+        Ok([
+            ("background", colors.background),
+            ("foreground", colors.foreground),
+            ("selection", colors.selection),
+            ("line_highlight", colors.line_highlight),
+            ("gutter", colors.gutter),
+            ("gutter_foreground", colors.gutter_foreground),
+        ]
+        .into_iter()
+        .map(|(name, color)| (name, color.map(|c| c.to_string())))
+        .collect())
+    }
+
+    fn tree_sitter_languages(&self) -> PyResult<Vec<&'static str>> {
+        Ok(self.resources.tree_sitter_languages())
+    }
+
     fn themes(&self) -> PyResult<Vec<String>> {
         Ok(self
             .resources
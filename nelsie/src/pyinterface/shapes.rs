@@ -5,7 +5,10 @@ use pyo3::exceptions::PyValueError;
 use pyo3::types::{PyAnyMethods, PyIterator, PyList};
 use pyo3::{Bound, FromPyObject, PyAny, PyResult};
 use renderer::Path;
-use renderer::{Arrow, FillAndStroke, LayoutExpr, Length, PathPart, Shape, ShapeRect, Stroke};
+use renderer::{
+    Arrow, Connector, ConnectorRouting, ConnectorSide, FillAndStroke, LayoutExpr, Length, NodeId,
+    PathPart, Shape, ShapeId, ShapeRect, Stroke,
+};
 use std::marker::PhantomData;
 
 pub(crate) trait Dimension {
@@ -88,6 +91,7 @@ pub(crate) struct PyRect {
     z_level: i32,
     stroke: Option<PyStroke>,
     fill_color: Option<PyColor>,
+    shape_id: Option<usize>,
 }
 
 impl PyRect {
@@ -102,6 +106,7 @@ impl PyRect {
                 fill_color: self.fill_color.map(|x| x.into()),
                 stroke: self.stroke.map(|x| x.into()),
             },
+            shape_id: self.shape_id.map(ShapeId::new),
         };
         if self.shape == 1 {
             Shape::Oval(rect)
@@ -141,6 +146,7 @@ pub(crate) struct PyPath<'py> {
     fill_color: Option<PyColor>,
     arrow_start: Option<PyArrow>,
     arrow_end: Option<PyArrow>,
+    shape_id: Option<usize>,
 }
 
 impl<'py> PyPath<'py> {
@@ -200,6 +206,66 @@ impl<'py> PyPath<'py> {
             arrow_start: self.arrow_start.map(|x| x.into()),
             arrow_end: self.arrow_end.map(|x| x.into()),
             z_level: self.z_level,
+            shape_id: self.shape_id.map(ShapeId::new),
+        }))
+    }
+}
+
+fn parse_connector_side(value: &str) -> PyResult<Option<ConnectorSide>> {
+    Ok(match value {
+        "auto" => None,
+        "top" => Some(ConnectorSide::Top),
+        "right" => Some(ConnectorSide::Right),
+        "bottom" => Some(ConnectorSide::Bottom),
+        "left" => Some(ConnectorSide::Left),
+        _ => {
+            return Err(PyValueError::new_err(format!(
+                "Invalid connector side: '{value}'"
+            )));
+        }
+    })
+}
+
+#[derive(FromPyObject)]
+pub(crate) struct PyConnector {
+    start: usize,
+    end: usize,
+    start_side: String,
+    end_side: String,
+    routing: String,
+    z_level: i32,
+    stroke: Option<PyStroke>,
+    arrow_start: Option<PyArrow>,
+    arrow_end: Option<PyArrow>,
+    shape_id: Option<usize>,
+}
+
+impl PyConnector {
+    pub fn into_shape(self) -> PyResult<Shape> {
+        let routing = match self.routing.as_str() {
+            "straight" => ConnectorRouting::Straight,
+            "orthogonal" => ConnectorRouting::Orthogonal,
+            "curved" => ConnectorRouting::Curved,
+            routing => {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid connector routing: '{routing}'"
+                )));
+            }
+        };
+        Ok(Shape::Connector(Connector {
+            start: NodeId::new(self.start),
+            end: NodeId::new(self.end),
+            start_side: parse_connector_side(&self.start_side)?,
+            end_side: parse_connector_side(&self.end_side)?,
+            routing,
+            fill_and_stroke: FillAndStroke {
+                fill_color: None,
+                stroke: self.stroke.map(|x| x.into()),
+            },
+            arrow_start: self.arrow_start.map(|x| x.into()),
+            arrow_end: self.arrow_end.map(|x| x.into()),
+            z_level: self.z_level,
+            shape_id: self.shape_id.map(ShapeId::new),
         }))
     }
 }
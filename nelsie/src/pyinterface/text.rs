@@ -3,11 +3,13 @@ use pyo3::exceptions::PyValueError;
 use pyo3::types::PyAnyMethods;
 use pyo3::{Bound, FromPyObject, PyAny, PyErr, PyResult};
 use renderer::{
-    FontStretch, ParsingChars, SyntaxHighlightSettings, Text, TextAlign, TextStyle, TextStyling,
+    AnnotationTarget, DecorationStyle, DiffLine, FontStretch, InlineId, LineHighlight, LineNumbers,
+    Math, ParsingChars, ScriptPosition, SyntaxHighlightBackend, SyntaxHighlightSettings, Text,
+    TextAlign, TextAnnotation, TextDiff, TextDirection, TextStyle, TextStyling, WritingMode,
 };
 use std::collections::HashMap;
 use std::sync::Arc;
-use strict_num::PositiveF32;
+use strict_num::{FiniteF32, PositiveF32};
 
 #[derive(FromPyObject)]
 pub(crate) struct PyTextStyle {
@@ -18,9 +20,54 @@ pub(crate) struct PyTextStyle {
     italic: Option<bool>,
     stretch: Option<u8>,
     underline: Option<bool>,
+    overline: Option<bool>,
     line_through: Option<bool>,
     weight: Option<u16>,
     bold: Option<bool>,
+    bg_color: Option<PyColor>,
+    decoration_color: Option<PyColor>,
+    decoration_width: Option<f32>,
+    decoration_style: Option<PyDecorationStyle>,
+    baseline_shift: Option<f32>,
+    script: Option<PyScriptPosition>,
+}
+
+pub(crate) struct PyScriptPosition(ScriptPosition);
+
+impl<'py> FromPyObject<'py> for PyScriptPosition {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let s: &str = ob.extract()?;
+        Ok(PyScriptPosition(match s {
+            "normal" => ScriptPosition::Normal,
+            "super" => ScriptPosition::Super,
+            "sub" => ScriptPosition::Sub,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid script position: '{s}'"
+                )))
+            }
+        }))
+    }
+}
+
+pub(crate) struct PyDecorationStyle(DecorationStyle);
+
+impl<'py> FromPyObject<'py> for PyDecorationStyle {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let s: &str = ob.extract()?;
+        Ok(PyDecorationStyle(match s {
+            "solid" => DecorationStyle::Solid,
+            "double" => DecorationStyle::Double,
+            "dotted" => DecorationStyle::Dotted,
+            "dashed" => DecorationStyle::Dashed,
+            "wavy" => DecorationStyle::Wavy,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid decoration style: '{s}'"
+                )))
+            }
+        }))
+    }
 }
 
 impl TryFrom<PyTextStyle> for TextStyle {
@@ -38,6 +85,18 @@ impl TryFrom<PyTextStyle> for TextStyle {
                     .ok_or_else(|| PyValueError::new_err("Invalid line spacing size"))
             })
             .transpose()?;
+        let decoration_width = value
+            .decoration_width
+            .map(|s| {
+                PositiveF32::new(s).ok_or_else(|| PyValueError::new_err("Invalid decoration width"))
+            })
+            .transpose()?;
+        let baseline_shift = value
+            .baseline_shift
+            .map(|s| {
+                FiniteF32::new(s).ok_or_else(|| PyValueError::new_err("Invalid baseline shift"))
+            })
+            .transpose()?;
         let stretch = value
             .stretch
             .map(|s| match s {
@@ -69,7 +128,14 @@ impl TryFrom<PyTextStyle> for TextStyle {
             stretch,
             weight,
             underline: value.underline,
+            overline: value.overline,
             line_through: value.line_through,
+            bg_color: value.bg_color.map(|x| x.into()),
+            decoration_color: value.decoration_color.map(|x| x.into()),
+            decoration_width,
+            decoration_style: value.decoration_style.map(|x| x.0),
+            baseline_shift,
+            script: value.script.map(|x| x.0),
         })
     }
 }
@@ -102,15 +168,140 @@ impl From<PyTextAlign> for TextAlign {
     }
 }
 
+pub(crate) struct PyTextDirection(TextDirection);
+
+impl<'py> FromPyObject<'py> for PyTextDirection {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let s: &str = ob.extract()?;
+        Ok(PyTextDirection(match s {
+            "auto" => TextDirection::Auto,
+            "ltr" => TextDirection::Ltr,
+            "rtl" => TextDirection::Rtl,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid text direction: '{s}'"
+                )))
+            }
+        }))
+    }
+}
+
+pub(crate) struct PySyntaxHighlightBackend(SyntaxHighlightBackend);
+
+impl<'py> FromPyObject<'py> for PySyntaxHighlightBackend {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let s: &str = ob.extract()?;
+        Ok(PySyntaxHighlightBackend(match s {
+            "syntect" => SyntaxHighlightBackend::Syntect,
+            "tree-sitter" => SyntaxHighlightBackend::TreeSitter,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid syntax highlighting backend: '{s}'"
+                )))
+            }
+        }))
+    }
+}
+
+pub(crate) struct PyDiffLine(DiffLine);
+
+impl<'py> FromPyObject<'py> for PyDiffLine {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let s: &str = ob.extract()?;
+        Ok(PyDiffLine(match s {
+            " " => DiffLine::Unchanged,
+            "+" => DiffLine::Added,
+            "-" => DiffLine::Removed,
+            _ => return Err(PyValueError::new_err(format!("Invalid diff line: '{s}'"))),
+        }))
+    }
+}
+
+#[derive(FromPyObject)]
+pub(crate) struct PyTextDiff {
+    lines: Vec<PyDiffLine>,
+    added_color: PyColor,
+    removed_color: PyColor,
+}
+
+#[derive(FromPyObject)]
+pub(crate) enum PyAnnotationTarget {
+    Regex(String),
+    Position((u32, u32, u32)),
+}
+
+#[derive(FromPyObject)]
+pub(crate) struct PyAnnotation {
+    anchor_id: u32,
+    target: PyAnnotationTarget,
+    style: Option<PyTextStyle>,
+}
+
+impl TryFrom<PyAnnotation> for TextAnnotation {
+    type Error = PyErr;
+
+    fn try_from(value: PyAnnotation) -> Result<Self, Self::Error> {
+        Ok(TextAnnotation {
+            anchor_id: InlineId::new(value.anchor_id),
+            target: match value.target {
+                PyAnnotationTarget::Regex(pattern) => AnnotationTarget::Regex(pattern),
+                PyAnnotationTarget::Position((line, start_column, end_column)) => {
+                    AnnotationTarget::Position {
+                        line,
+                        start_column,
+                        end_column,
+                    }
+                }
+            },
+            style: value.style.map(|s| s.try_into()).transpose()?,
+        })
+    }
+}
+
+pub(crate) struct PyWritingMode(WritingMode);
+
+impl<'py> FromPyObject<'py> for PyWritingMode {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let s: &str = ob.extract()?;
+        Ok(PyWritingMode(match s {
+            "horizontal" => WritingMode::Horizontal,
+            "vertical" => WritingMode::Vertical,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid writing mode: '{s}'"
+                )))
+            }
+        }))
+    }
+}
+
 #[derive(FromPyObject)]
 pub(crate) struct PyTextContent {
     pub(crate) text: String,
     pub(crate) style: PyTextStyle,
     pub(crate) align: PyTextAlign,
+    pub(crate) direction: PyTextDirection,
+    pub(crate) writing_mode: PyWritingMode,
     pub(crate) syntax_language: Option<String>,
     pub(crate) syntax_theme: Option<String>,
+    pub(crate) syntax_backend: PySyntaxHighlightBackend,
     pub(crate) named_styles: Option<HashMap<String, PyTextStyle>>,
     pub(crate) style_delimiters: Option<String>,
+    pub(crate) parse_math: bool,
+    pub(crate) markdown: bool,
+    pub(crate) links: Option<HashMap<u32, String>>,
+    pub(crate) annotations: Option<Vec<PyAnnotation>>,
+    pub(crate) line_numbers: Option<PyLineNumbers>,
+    pub(crate) diff: Option<PyTextDiff>,
+    pub(crate) line_highlights: Option<Vec<(u32, u32, Option<PyColor>)>>,
+    pub(crate) theme_background: bool,
+}
+
+#[derive(FromPyObject)]
+pub(crate) struct PyLineNumbers {
+    start: u32,
+    style: PyTextStyle,
+    separator: String,
 }
 
 impl TryFrom<PyTextContent> for Text {
@@ -118,26 +309,42 @@ impl TryFrom<PyTextContent> for Text {
 
     fn try_from(value: PyTextContent) -> Result<Self, Self::Error> {
         let style = value.style.try_into()?;
+        let mut links: Vec<_> = value
+            .links
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(anchor_id, url)| (InlineId::new(anchor_id), url))
+            .collect();
+        links.sort_unstable();
         let syntax_highlight =
             if let (Some(language), Some(theme)) = (value.syntax_language, value.syntax_theme) {
-                Some(SyntaxHighlightSettings { language, theme })
+                Some(SyntaxHighlightSettings {
+                    language,
+                    theme,
+                    backend: value.syntax_backend.0,
+                })
             } else {
                 None
             };
         Ok(Text {
             text: value.text,
             style,
-            styling: if let Some(style_delimiters) = value.style_delimiters {
-                if style_delimiters.len() != 3 {
-                    return Err(PyValueError::new_err(
-                        "Style delimiters must be 3 characters long",
-                    ));
-                }
-                let mut chars = style_delimiters.chars();
-                let parsing_chars = ParsingChars {
-                    escape_char: chars.next().unwrap(),
-                    block_begin: chars.next().unwrap(),
-                    block_end: chars.next().unwrap(),
+            styling: if value.style_delimiters.is_some() || value.markdown {
+                // Markdown uses only named styles, parsing chars are not used
+                let parsing_chars = if let Some(style_delimiters) = value.style_delimiters {
+                    if style_delimiters.len() != 3 {
+                        return Err(PyValueError::new_err(
+                            "Style delimiters must be 3 characters long",
+                        ));
+                    }
+                    let mut chars = style_delimiters.chars();
+                    ParsingChars {
+                        escape_char: chars.next().unwrap(),
+                        block_begin: chars.next().unwrap(),
+                        block_end: chars.next().unwrap(),
+                    }
+                } else {
+                    ParsingChars::default()
                 };
                 let mut named_styles: Vec<_> = value
                     .named_styles
@@ -157,7 +364,62 @@ impl TryFrom<PyTextContent> for Text {
                 None
             },
             text_align: value.align.into(),
+            direction: value.direction.0,
+            writing_mode: value.writing_mode.0,
             syntax_highlight,
+            parse_math: value.parse_math,
+            markdown: value.markdown,
+            links,
+            annotations: value
+                .annotations
+                .unwrap_or_default()
+                .into_iter()
+                .map(|a| a.try_into())
+                .collect::<PyResult<_>>()?,
+            line_numbers: value
+                .line_numbers
+                .map(|n| {
+                    PyResult::Ok(LineNumbers {
+                        start: n.start,
+                        style: n.style.try_into()?,
+                        separator: n.separator,
+                    })
+                })
+                .transpose()?,
+            diff: value.diff.map(|diff| TextDiff {
+                lines: diff.lines.into_iter().map(|line| line.0).collect(),
+                added_color: diff.added_color.into(),
+                removed_color: diff.removed_color.into(),
+            }),
+            line_highlights: value
+                .line_highlights
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(start, end, color)| LineHighlight {
+                    start,
+                    end,
+                    color: color.map(|c| c.into()),
+                })
+                .collect(),
+        })
+    }
+}
+
+#[derive(FromPyObject)]
+pub(crate) struct PyMathContent {
+    pub(crate) formula: String,
+    pub(crate) style: PyTextStyle,
+    pub(crate) display: bool,
+}
+
+impl TryFrom<PyMathContent> for Math {
+    type Error = PyErr;
+
+    fn try_from(value: PyMathContent) -> Result<Self, Self::Error> {
+        Ok(Math {
+            formula: value.formula,
+            style: value.style.try_into()?,
+            display: value.display,
         })
     }
 }
@@ -13,7 +13,7 @@ impl Hash for Color {
 }
 
 impl Color {
-    pub fn new(color: svgtypes::Color) -> Self {
+    pub const fn new(color: svgtypes::Color) -> Self {
         Color(color)
     }
 
@@ -28,6 +28,14 @@ impl Color {
     pub fn alpha(&self) -> u8 {
         self.0.alpha
     }
+
+    pub fn opaque(&self) -> Color {
+        self.with_alpha(255)
+    }
+
+    pub fn with_alpha(&self, alpha: u8) -> Color {
+        Color(svgtypes::Color { alpha, ..self.0 })
+    }
 }
 
 impl Default for Color {
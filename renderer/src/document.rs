@@ -1,4 +1,4 @@
-use crate::layout_info::{LayoutInfoComposer, PageLayout};
+use crate::layout_info::{LayoutInfoComposer, LayoutWarning, PageLayout};
 use crate::node::ContentId;
 use crate::render::composer::{
     Composer, PngCollectingComposer, PngWriteComposer, SvgCollectingComposer, SvgWriteComposer,
@@ -7,18 +7,18 @@ use crate::render::composer_pdf::PdfComposer;
 use crate::render::content::{Content, ContentBody};
 use crate::render::context::RenderContext;
 use crate::render::layout::compute_page_layout;
+use crate::render::math::render_math;
 use crate::render::text::{TextContext, render_text};
 use crate::resources::Resources;
-use crate::text::Text;
+use crate::text::{Math, Text};
 use crate::utils::fileutils::{ensure_directory, safe_write};
 use crate::{InMemoryBinImage, InMemorySvgImage, Page, Rectangle};
-use itertools::Itertools;
-use parley::FontContext;
+use itertools::{Either, Itertools};
 use pdf_writer::Finish;
 use rayon::iter::{IndexedParallelIterator, IntoParallelIterator};
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 pub struct Composition {
     content_id: ContentId,
@@ -30,6 +30,7 @@ pub struct Composition {
 pub struct Register {
     content_id_counter: ContentId,
     texts: HashMap<Text, (ContentId, u32)>,
+    maths: HashMap<Math, (ContentId, u32)>,
     bin_images: HashMap<InMemoryBinImage, (ContentId, f32, f32)>,
     svg_images: HashMap<InMemorySvgImage, (ContentId, usize, f32, f32)>,
     compositions: Vec<Composition>,
@@ -46,6 +47,7 @@ impl Register {
         Self {
             content_id_counter: ContentId::new(0),
             texts: HashMap::new(),
+            maths: HashMap::new(),
             bin_images: HashMap::new(),
             svg_images: HashMap::new(),
             compositions: Vec::new(),
@@ -61,6 +63,15 @@ impl Register {
         entry.0
     }
 
+    pub fn register_math(&mut self, math: Math) -> ContentId {
+        let entry = self
+            .maths
+            .entry(math)
+            .or_insert_with(|| (self.content_id_counter.bump(), 0));
+        entry.1 += 1;
+        entry.0
+    }
+
     pub fn register_bin_image(
         &mut self,
         image: InMemoryBinImage,
@@ -130,7 +141,7 @@ impl Document {
         resources: &Resources,
         options: &RenderingOptions,
         composer: &mut dyn Composer,
-    ) -> crate::Result<()> {
+    ) -> crate::Result<Vec<LayoutWarning>> {
         let mut thread_pool_builder = rayon::ThreadPoolBuilder::new();
         if let Some(n_threads) = options.n_threads {
             thread_pool_builder = thread_pool_builder.num_threads(n_threads);
@@ -138,6 +149,7 @@ impl Document {
         let thread_pool = thread_pool_builder.build().unwrap();
         let progressbar = options.progressbar.then(|| {
             let total = self.register.texts.len()
+                + self.register.maths.len()
                 + self.register.bin_images.len()
                 + self.register.svg_images.len()
                 + self.pages.len();
@@ -149,19 +161,22 @@ impl Document {
                     self.register
                         .texts
                         .iter()
+                        .map(|(text, entry)| (Either::Left(text), entry))
+                        .chain(
+                            self.register
+                                .maths
+                                .iter()
+                                .map(|(math, entry)| (Either::Right(math), entry)),
+                        )
                         .collect_vec()
                         .into_par_iter()
                         .map_init(
-                            || TextContext {
-                                layout_cx: Default::default(),
-                                font_cx: FontContext {
-                                    collection: resources.font_context.collection.clone(),
-                                    source_cache: resources.font_context.source_cache.clone(),
-                                },
-                            },
+                            || TextContext::new(resources),
                             |text_ctx, (text, (content_id, count))| {
-                                let (rtext, width, height) =
-                                    render_text(resources, text_ctx, text)?;
+                                let (rtext, width, height) = match text {
+                                    Either::Left(text) => render_text(resources, text_ctx, text)?,
+                                    Either::Right(math) => render_math(text_ctx, math)?,
+                                };
                                 let content = Content::new(
                                     width,
                                     height,
@@ -240,48 +255,61 @@ impl Document {
 
             composer.preprocessing_finished();
 
-            let r = self
-                .pages
-                .par_iter()
-                .enumerate()
-                .try_for_each(|(page_idx, page)| {
+            let warnings = Mutex::new(Vec::new());
+            self.pages.par_iter().enumerate().try_for_each_init(
+                || TextContext::new(resources),
+                |text_ctx, (page_idx, page)| {
                     let mut render_ctx = RenderContext {
                         content_map: &content_map,
+                        pages: &self.pages,
                     };
-                    let layout = compute_page_layout(&mut render_ctx, page);
-                    let canvas = page.render_to_canvas(&layout);
+                    let layout = compute_page_layout(&mut render_ctx, page)?;
+                    warnings
+                        .lock()
+                        .unwrap()
+                        .extend(layout.overflows().iter().map(|o| LayoutWarning {
+                            page_idx,
+                            node_id: o.node_id,
+                            kind: o.kind,
+                            amount: o.amount,
+                        }));
+                    let canvas = page.render_to_canvas(resources, text_ctx, &layout)?;
                     let r = composer.add_page(page_idx, canvas, render_ctx.content_map, &layout);
                     if let Some(p) = &progressbar {
                         p.inc(1);
                     }
                     r
-                });
+                },
+            )?;
             if let Some(p) = progressbar {
                 p.finish();
             }
-            r
+            let mut warnings = warnings.into_inner().unwrap();
+            warnings.sort_by_key(|w| w.page_idx);
+            Ok(warnings)
         })
     }
 
+    /// Renders pages into a PDF file; returns warnings found in layouts of pages
     pub fn render_pdf_to_file(
         &self,
         resources: &Resources,
         options: &RenderingOptions,
         path: &std::path::Path,
-    ) -> crate::Result<()> {
-        let data = self.render_pdf_to_mem(resources, options)?;
+    ) -> crate::Result<Vec<LayoutWarning>> {
+        let (data, warnings) = self.render_pdf_to_mem(resources, options)?;
         safe_write(path, &data)?;
-        Ok(())
+        Ok(warnings)
     }
 
     pub fn render_pdf_to_mem(
         &self,
         resources: &Resources,
         options: &RenderingOptions,
-    ) -> crate::Result<Vec<u8>> {
+    ) -> crate::Result<(Vec<u8>, Vec<LayoutWarning>)> {
         let mut composer = PdfComposer::new(self.pages.len(), options.compression_level);
-        self.render(resources, options, &mut composer)?;
-        Ok(composer.finish())
+        let warnings = self.render(resources, options, &mut composer)?;
+        Ok((composer.finish(), warnings))
     }
 
     pub fn render_svg_to_dir(
@@ -289,7 +317,7 @@ impl Document {
         resources: &Resources,
         options: &RenderingOptions,
         path: &std::path::Path,
-    ) -> crate::Result<()> {
+    ) -> crate::Result<Vec<LayoutWarning>> {
         ensure_directory(path)?;
         let mut composer = SvgWriteComposer::new(path, self.pages.len());
         self.render(resources, options, &mut composer)
@@ -300,7 +328,7 @@ impl Document {
         resources: &Resources,
         options: &RenderingOptions,
         path: &std::path::Path,
-    ) -> crate::Result<()> {
+    ) -> crate::Result<Vec<LayoutWarning>> {
         ensure_directory(path)?;
         let mut composer = PngWriteComposer::new(resources, path, self.pages.len());
         self.render(resources, options, &mut composer)
@@ -310,29 +338,29 @@ impl Document {
         &self,
         resources: &Resources,
         options: &RenderingOptions,
-    ) -> crate::Result<Vec<String>> {
+    ) -> crate::Result<(Vec<String>, Vec<LayoutWarning>)> {
         let mut composer = SvgCollectingComposer::new(self.pages.len());
-        self.render(resources, options, &mut composer)?;
-        Ok(composer.finish())
+        let warnings = self.render(resources, options, &mut composer)?;
+        Ok((composer.finish(), warnings))
     }
 
     pub fn render_png_to_vec(
         &self,
         resources: &Resources,
         options: &RenderingOptions,
-    ) -> crate::Result<Vec<Vec<u8>>> {
+    ) -> crate::Result<(Vec<Vec<u8>>, Vec<LayoutWarning>)> {
         let mut composer = PngCollectingComposer::new(resources, self.pages.len());
-        self.render(resources, options, &mut composer)?;
-        Ok(composer.finish())
+        let warnings = self.render(resources, options, &mut composer)?;
+        Ok((composer.finish(), warnings))
     }
 
     pub fn render_layout_info(
         &self,
         resources: &Resources,
         options: &RenderingOptions,
-    ) -> crate::Result<Vec<PageLayout>> {
+    ) -> crate::Result<(Vec<PageLayout>, Vec<LayoutWarning>)> {
         let mut composer = LayoutInfoComposer::new(self.pages.len());
-        self.render(resources, options, &mut composer)?;
-        Ok(composer.finish())
+        let warnings = self.render(resources, options, &mut composer)?;
+        Ok((composer.finish(), warnings))
     }
 }
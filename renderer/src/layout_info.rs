@@ -6,6 +6,28 @@ use crate::{NodeId, Rectangle};
 use std::collections::HashMap;
 use std::sync::Mutex;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayoutWarningKind {
+    /// A node placed by the layout of its parent extends beyond the parent
+    ParentOverflow,
+    /// A node extends beyond the page
+    PageOverflow,
+    /// A text is larger than its node
+    TextOverflow,
+    /// A node with a content has zero width or height
+    EmptyNode,
+}
+
+/// A problem found in a computed layout of a page
+#[derive(Clone, Debug)]
+pub struct LayoutWarning {
+    pub page_idx: usize,
+    pub node_id: NodeId,
+    pub kind: LayoutWarningKind,
+    /// The largest distance by which the node (or its text) extends beyond its limit
+    pub amount: f32,
+}
+
 #[derive(Clone, Debug)]
 pub struct PageLayout {
     pub node_layouts: HashMap<NodeId, Rectangle>,
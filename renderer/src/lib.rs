@@ -18,16 +18,20 @@ pub use color::Color;
 pub use document::{Document, Register, RenderingOptions};
 pub use error::RendererError as Error;
 pub use image::{InMemoryBinImage, InMemorySvgImage};
-pub use layout_info::PageLayout;
+pub use layout_info::{LayoutWarning, LayoutWarningKind, PageLayout};
 pub use node::{ContentId, Node, NodeChild};
 pub use page::Page;
 pub use rectangle::Rectangle;
-pub use resources::Resources;
-pub use shapes::{Arrow, FillAndStroke, Path, PathPart, Shape, ShapeRect, Stroke};
+pub use resources::{Resources, ThemeColors};
+pub use shapes::{
+    Arrow, Connector, ConnectorRouting, ConnectorSide, FillAndStroke, Path, PathPart, Shape,
+    ShapeRect, Stroke,
+};
 pub use taffy;
 pub use text::{
-    FontStretch, InlineId, ParsingChars, SyntaxHighlightSettings, Text, TextAlign, TextStyle,
-    TextStyling,
+    AnnotationTarget, DecorationStyle, DiffLine, FontStretch, InlineId, LineHighlight, LineNumbers,
+    Math, ParsingChars, ScriptPosition, SyntaxHighlightBackend, SyntaxHighlightSettings, Text,
+    TextAlign, TextAnnotation, TextDiff, TextDirection, TextStyle, TextStyling, WritingMode,
 };
-pub use types::{LayoutExpr, Length, LengthOrAuto, LengthOrExpr, NodeId};
+pub use types::{LayoutExpr, Length, LengthOrAuto, LengthOrExpr, NodeId, ShapeId};
 pub type Result<T> = std::result::Result<T, Error>;
@@ -2,12 +2,13 @@ use crate::shapes::Shape;
 use crate::types::{LayoutExpr, Length, LengthOrAuto, LengthOrExpr};
 use crate::{Color, NodeId};
 use taffy::{
-    AlignContent, AlignItems, FlexWrap, GridPlacement, Line, NonRepeatedTrackSizingFunction,
+    AlignContent, AlignItems, FlexWrap, GridAutoFlow, GridPlacement, Line,
+    NonRepeatedTrackSizingFunction, TrackSizingFunction,
 };
 
 #[derive(Debug)]
 pub enum NodeChild {
-    Node(Node),
+    Node(Box<Node>),
     Shape(Shape),
 }
 
@@ -33,6 +34,9 @@ impl ContentId {
 pub struct Node {
     pub node_id: NodeId,
 
+    /// Name used in error messages, may be empty
+    pub name: String,
+
     pub children: Vec<NodeChild>,
 
     pub show: bool,
@@ -43,6 +47,12 @@ pub struct Node {
     pub width: Option<LengthOrExpr>,
     pub height: Option<LengthOrExpr>,
 
+    /// Limits of the size; `None` means no limit
+    pub min_width: Option<Length>,
+    pub max_width: Option<Length>,
+    pub min_height: Option<Length>,
+    pub max_height: Option<Length>,
+
     pub border_radius: f32,
 
     pub row: bool,
@@ -55,6 +65,8 @@ pub struct Node {
 
     pub flex_shrink: f32,
 
+    pub flex_basis: LengthOrAuto,
+
     pub align_items: Option<AlignItems>,
     pub align_self: Option<AlignItems>,
     pub justify_self: Option<AlignItems>,
@@ -64,9 +76,14 @@ pub struct Node {
     pub column_gap: Length,
     pub row_gap: Length,
 
-    pub grid_template_rows: Vec<NonRepeatedTrackSizingFunction>,
+    pub grid_template_rows: Vec<TrackSizingFunction>,
 
-    pub grid_template_columns: Vec<NonRepeatedTrackSizingFunction>,
+    pub grid_template_columns: Vec<TrackSizingFunction>,
+
+    /// Sizes of rows and columns that are not defined by templates
+    pub grid_auto_rows: Vec<NonRepeatedTrackSizingFunction>,
+    pub grid_auto_columns: Vec<NonRepeatedTrackSizingFunction>,
+    pub grid_auto_flow: GridAutoFlow,
 
     pub grid_row: Line<GridPlacement>,
     pub grid_column: Line<GridPlacement>,
@@ -88,12 +105,16 @@ pub struct Node {
     pub content: Option<ContentId>,
 
     pub url: Option<String>,
+
+    /// Color of the debug overlay of the node (frames, gaps, label, and rects of text lines and anchors);
+    /// `None` disables the overlay
+    pub debug_layout: Option<Color>,
 }
 
 impl Node {
     pub fn child_nodes(&self) -> impl Iterator<Item = &Node> {
         self.children.iter().filter_map(|child| match child {
-            NodeChild::Node(node) => Some(node),
+            NodeChild::Node(node) => Some(node.as_ref()),
             NodeChild::Shape(_) => None,
         })
     }
@@ -114,8 +135,24 @@ impl Node {
         }
     }
 
+    pub fn is_grid(&self) -> bool {
+        !self.grid_template_rows.is_empty()
+            || !self.grid_template_columns.is_empty()
+            || !self.grid_auto_rows.is_empty()
+            || !self.grid_auto_columns.is_empty()
+    }
+
+    /// Identification of the node for error messages
+    pub(crate) fn label(&self) -> String {
+        if self.name.is_empty() {
+            format!("box #{}", self.node_id.as_usize())
+        } else {
+            format!("box '{}'", self.name)
+        }
+    }
+
     pub fn add_child_node(&mut self, node: Node) {
-        self.children.push(NodeChild::Node(node));
+        self.children.push(NodeChild::Node(Box::new(node)));
     }
 
     // fn collect_z_levels(&self, out: &mut BTreeSet<i32>) {
@@ -1,8 +1,11 @@
+use crate::Resources;
 use crate::color::Color;
 use crate::node::Node;
 use crate::render::canvas::Canvas;
+use crate::render::debug::render_debug_layout;
 use crate::render::layout::ComputedLayout;
 use crate::render::node::render_node;
+use crate::render::text::TextContext;
 
 pub struct Page {
     pub(crate) node: Node,
@@ -21,11 +24,17 @@ impl Page {
         }
     }
 
-    pub(crate) fn render_to_canvas(&self, layout: &ComputedLayout) -> Canvas {
+    pub(crate) fn render_to_canvas(
+        &self,
+        resources: &Resources,
+        text_ctx: &mut TextContext,
+        layout: &ComputedLayout,
+    ) -> crate::Result<Canvas> {
         let mut canvas = Canvas::new(self.width, self.height, self.bg_color);
         render_node(&self.node, layout, &mut canvas);
+        render_debug_layout(&self.node, resources, text_ctx, layout, &mut canvas)?;
         canvas.finish();
-        canvas
+        Ok(canvas)
     }
 
     // fn render(&self, render_ctx: &mut RenderContext) {
@@ -1,4 +1,4 @@
-use crate::render::draw::{DrawItem, DrawRect, PathBuilder};
+use crate::render::draw::{DrawItem, DrawPath, DrawRect, PathBuilder};
 use crate::shapes::FillAndStroke;
 
 #[derive(Debug, Clone)]
@@ -33,22 +33,30 @@ impl Rectangle {
         if border_radius < 0.001 {
             self.draw(fill_and_stroke)
         } else {
-            let mut builder = PathBuilder::new(fill_and_stroke);
-            let x2 = self.x + self.width;
-            let y2 = self.y + self.height;
-            builder.move_to(self.x + border_radius, self.y);
-            builder.line_to(x2 - border_radius, self.y);
-            builder.quad_to(x2, self.y, x2, self.y + border_radius);
-            builder.line_to(x2, y2 - border_radius);
-            builder.quad_to(x2, y2, x2 - border_radius, y2);
-            builder.line_to(self.x + border_radius, y2);
-            builder.quad_to(self.x, y2, self.x, y2 - border_radius);
-            builder.line_to(self.x, self.y + border_radius);
-            builder.quad_to(self.x, self.y, self.x + border_radius, self.y);
-            DrawItem::Path(builder.build())
+            DrawItem::Path(self.rounded_path(fill_and_stroke, border_radius))
         }
     }
 
+    pub(crate) fn rounded_path(
+        &self,
+        fill_and_stroke: FillAndStroke,
+        border_radius: f32,
+    ) -> DrawPath {
+        let mut builder = PathBuilder::new(fill_and_stroke);
+        let x2 = self.x + self.width;
+        let y2 = self.y + self.height;
+        builder.move_to(self.x + border_radius, self.y);
+        builder.line_to(x2 - border_radius, self.y);
+        builder.quad_to(x2, self.y, x2, self.y + border_radius);
+        builder.line_to(x2, y2 - border_radius);
+        builder.quad_to(x2, y2, x2 - border_radius, y2);
+        builder.line_to(self.x + border_radius, y2);
+        builder.quad_to(self.x, y2, self.x, y2 - border_radius);
+        builder.line_to(self.x, self.y + border_radius);
+        builder.quad_to(self.x, self.y, self.x + border_radius, self.y);
+        builder.build()
+    }
+
     pub(crate) fn fit_content_with_aspect_ratio(&self, orig_w: f32, orig_h: f32) -> Rectangle {
         let target_w = self.width;
         let target_h = self.height;
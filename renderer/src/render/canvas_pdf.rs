@@ -1,23 +1,25 @@
 use crate::render::canvas::{Canvas, CanvasItem};
 
-use crate::render::composer_pdf::PdfRefAllocator;
+use crate::render::composer_pdf::{GlyphImageRefs, PdfRefAllocator, text_to_pdf};
 use crate::render::content::{ContentBody, ContentMap};
-use crate::render::pdfdraw::{PdfWriter, annotations_to_pdf, draw_item_to_pdf, path_to_pdf};
+use crate::render::pdfdraw::{PdfWriter, annotations_to_pdf, draw_item_to_pdf};
 use crate::{ContentId, Rectangle};
 use pdf_writer::{Chunk, Filter, Finish, Name, Rect, Ref};
 use std::collections::HashMap;
 
 impl Canvas {
+    #[allow(clippy::too_many_arguments)]
     pub fn into_pdf_page(
         self,
         ref_allocator: &PdfRefAllocator,
+        glyph_images: &GlyphImageRefs,
         page_ref: Ref,
         page_tree_ref: Ref,
         compression_level: u8,
         content_map: &ContentMap,
         content_to_ref: &HashMap<ContentId, Ref>,
     ) -> crate::Result<Chunk> {
-        let mut pdf_writer = PdfWriter::new(ref_allocator);
+        let mut pdf_writer = PdfWriter::new(ref_allocator, glyph_images);
         pdf_writer.content.save_state();
         let [r, g, b] = self.bg_color.as_f32s();
         pdf_writer.content.set_fill_rgb(r, g, b);
@@ -119,9 +121,7 @@ fn content_into_pdf(
                     rect.x,
                     rect.y,
                 ]);
-                for path in text.paths() {
-                    path_to_pdf(pdf_writer, path)
-                }
+                text_to_pdf(pdf_writer, text);
                 pdf_writer.content.restore_state();
             }
             ContentBody::BinImage(_) | ContentBody::SvgImage(_) => {
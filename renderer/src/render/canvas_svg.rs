@@ -1,4 +1,4 @@
-use crate::render::canvas::{Canvas, CanvasItem};
+use crate::render::canvas::{Canvas, CanvasItem, Link};
 use crate::render::content::{ContentBody, ContentMap};
 use crate::render::draw::DrawItem;
 use crate::render::svgpath::{svg_ellipse, svg_path, svg_rect};
@@ -27,11 +27,34 @@ impl Canvas {
                 CanvasItem::DrawItem(item) => write_draw_item_to_svg(&mut writer, item),
             }
         }
+        for link in &self.links {
+            write_link_to_svg(&mut writer, link);
+        }
         writer.end("svg");
         Ok(writer.into_string())
     }
 }
 
+/// Link is an invisible rectangle wrapped in `<a>`, placed over the rest of the page
+fn write_link_to_svg(xml: &mut SimpleXmlWriter, link: &Link) {
+    let url = link
+        .url()
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('\'', "&apos;");
+    xml.begin("a");
+    xml.attr("xlink:href", url);
+    let rect = link.rect();
+    xml.begin("rect");
+    xml.attr("x", rect.x);
+    xml.attr("y", rect.y);
+    xml.attr("width", rect.width);
+    xml.attr("height", rect.height);
+    xml.attr("fill", "transparent");
+    xml.end("rect");
+    xml.end("a");
+}
+
 fn write_draw_item_to_svg(xml: &mut SimpleXmlWriter, item: &DrawItem) {
     match item {
         DrawItem::Rect(rect) => svg_rect(xml, rect),
@@ -40,6 +63,14 @@ fn write_draw_item_to_svg(xml: &mut SimpleXmlWriter, item: &DrawItem) {
     }
 }
 
+fn write_bin_image_to_svg(rect: &Rectangle, image: &InMemoryBinImage, xml: &mut SimpleXmlWriter) {
+    let (format, data) = match image {
+        InMemoryBinImage::Png(data) => ("png", data),
+        InMemoryBinImage::Jpeg(data) => ("jpeg", data),
+    };
+    write_raster_image_to_svg(rect, format, data, xml);
+}
+
 fn write_raster_image_to_svg(
     rect: &Rectangle,
     format: &str,
@@ -101,6 +132,9 @@ fn render_text_into_svg(
     for path in rendered_text.paths() {
         svg_path(writer, path);
     }
+    for glyph_image in rendered_text.images() {
+        write_bin_image_to_svg(&glyph_image.rect, &glyph_image.image, writer);
+    }
     writer.end("g")
 }
 
@@ -130,11 +164,7 @@ fn render_content_to_svg(
             render_text_into_svg(writer, text, &rect, width, height);
         }
         ContentBody::BinImage(image) => {
-            let (format, data) = match image {
-                InMemoryBinImage::Png(data) => ("png", data),
-                InMemoryBinImage::Jpeg(data) => ("jpeg", data),
-            };
-            write_raster_image_to_svg(&rect, format, data, writer);
+            write_bin_image_to_svg(&rect, image, writer);
         }
         ContentBody::SvgImage(image) => {
             render_svg_image_into_svg(writer, &image.as_string(), &rect, width, height);
@@ -0,0 +1,897 @@
+use crate::render::draw::{DrawPath, PathBuilder};
+use crate::render::text::GlyphImage;
+use crate::shapes::FillAndStroke;
+use crate::{Color, InMemoryBinImage, Rectangle};
+use resvg::{tiny_skia, usvg};
+use skrifa::color::{
+    Brush, ColorGlyph, ColorGlyphCollection, ColorGlyphFormat, ColorPainter, ColorStop,
+    CompositeMode, Extend, Transform,
+};
+use skrifa::instance::{LocationRef, Size};
+use skrifa::outline::{DrawSettings, OutlineGlyphCollection, OutlinePen};
+use skrifa::raw::tables::bitmap::{BitmapContent, BitmapDataFormat, BitmapMetrics};
+use skrifa::raw::tables::cpal::Cpal;
+use skrifa::raw::types::{BoundingBox, Tag};
+use skrifa::raw::{FontRef, TableProvider};
+use skrifa::{GlyphId, MetadataProvider};
+use std::sync::Arc;
+
+/// Palette index that refers to the color of the text instead of a color from CPAL
+const TEXT_COLOR_INDEX: u16 = 0xFFFF;
+
+/// Pixels per point used when SVG and COLR v1 glyphs are rasterized
+const GLYPH_RASTER_SCALE: f32 = 4.0;
+
+/// Draws glyphs that carry their own colors, i.e. layered glyphs (COLR v0/v1),
+/// bitmap glyphs (CBDT, sbix) and SVG glyphs.
+///
+/// COLR v0 glyphs consist of solid layers only, so they become ordinary paths and stay
+/// vector graphics in all outputs; all other color glyphs are placed as PNG images.
+pub(crate) struct ColorGlyphs<'a> {
+    font_ref: FontRef<'a>,
+    location: LocationRef<'a>,
+    font_size: f32,
+    text_color: Color,
+    colr: ColorGlyphCollection<'a>,
+    cpal: Option<Cpal<'a>>,
+    outlines: OutlineGlyphCollection<'a>,
+    has_bitmaps: bool,
+}
+
+impl<'a> ColorGlyphs<'a> {
+    /// Returns `None` if the font has no color glyphs at all
+    pub fn new(
+        font_ref: FontRef<'a>,
+        location: LocationRef<'a>,
+        font_size: f32,
+        text_color: Color,
+    ) -> Option<Self> {
+        let has_colr = font_ref.colr().is_ok() && font_ref.cpal().is_ok();
+        let has_bitmaps =
+            (font_ref.cblc().is_ok() && font_ref.cbdt().is_ok()) || font_ref.sbix().is_ok();
+        if !has_colr && !has_bitmaps && font_ref.svg().is_err() {
+            return None;
+        }
+        Some(ColorGlyphs {
+            font_ref: font_ref.clone(),
+            location,
+            font_size,
+            text_color,
+            colr: font_ref.color_glyphs(),
+            cpal: font_ref.cpal().ok(),
+            outlines: font_ref.outline_glyphs(),
+            has_bitmaps,
+        })
+    }
+
+    /// Draws a glyph with the pen position (x, y) on the baseline.
+    /// Returns false if the glyph is not a color glyph and has to be drawn as an outline.
+    pub fn draw(
+        &self,
+        glyph_id: GlyphId,
+        x: f32,
+        y: f32,
+        paths: &mut Vec<DrawPath>,
+        images: &mut Vec<GlyphImage>,
+    ) -> bool {
+        if let Some(color_glyph) = self.colr.get(glyph_id) {
+            match color_glyph.format() {
+                ColorGlyphFormat::ColrV0 => {
+                    let mut painter = PathPainter {
+                        glyphs: self,
+                        x,
+                        y,
+                        scale: self.font_size / self.units_per_em(),
+                        paths: Vec::new(),
+                    };
+                    if color_glyph.paint(self.location, &mut painter).is_ok() {
+                        paths.append(&mut painter.paths);
+                        return true;
+                    }
+                }
+                ColorGlyphFormat::ColrV1 => {
+                    if let Some(image) = self.colr_v1_glyph(&color_glyph, x, y) {
+                        images.push(image);
+                        return true;
+                    }
+                }
+            }
+        }
+        let image = self
+            .has_bitmaps
+            .then(|| self.bitmap_glyph(glyph_id, x, y))
+            .flatten()
+            .or_else(|| self.svg_glyph(glyph_id, x, y));
+        if let Some(image) = image {
+            images.push(image);
+            return true;
+        }
+        false
+    }
+
+    fn units_per_em(&self) -> f32 {
+        self.font_ref
+            .head()
+            .map(|head| head.units_per_em() as f32)
+            .unwrap_or(1000.0)
+    }
+
+    fn palette_color(&self, palette_index: u16, alpha: f32) -> Color {
+        let (r, g, b, a) = if palette_index == TEXT_COLOR_INDEX {
+            let c: svgtypes::Color = (&self.text_color).into();
+            (c.red, c.green, c.blue, c.alpha)
+        } else {
+            self.cpal
+                .as_ref()
+                .and_then(|cpal| {
+                    let first = cpal.color_record_indices().first()?.get();
+                    let records = cpal.color_records_array()?.ok()?;
+                    let record = records.get(first as usize + palette_index as usize)?;
+                    Some((record.red, record.green, record.blue, record.alpha))
+                })
+                .unwrap_or((0, 0, 0, 255))
+        };
+        let a = (a as f32 * alpha.clamp(0.0, 1.0)).round() as u8;
+        Color::new(svgtypes::Color::new_rgba(r, g, b, a))
+    }
+
+    fn glyph_path(&self, glyph_id: GlyphId) -> Option<tiny_skia::Path> {
+        let outline = self.outlines.get(glyph_id)?;
+        let mut pen = SkiaPen(tiny_skia::PathBuilder::new());
+        let settings = DrawSettings::unhinted(Size::unscaled(), self.location);
+        outline.draw(settings, &mut pen).ok()?;
+        pen.0.finish()
+    }
+
+    /// Gradient stops with premultiplied colors
+    fn gradient_stops(&self, color_stops: &[ColorStop]) -> Vec<(f32, [f32; 4])> {
+        color_stops
+            .iter()
+            .map(|stop| {
+                let c: svgtypes::Color =
+                    (&self.palette_color(stop.palette_index, stop.alpha)).into();
+                let a = c.alpha as f32 / 255.0;
+                let premultiply = |v: u8| v as f32 / 255.0 * a;
+                (
+                    stop.offset,
+                    [
+                        premultiply(c.red),
+                        premultiply(c.green),
+                        premultiply(c.blue),
+                        a,
+                    ],
+                )
+            })
+            .collect()
+    }
+
+    fn bitmap_glyph(&self, glyph_id: GlyphId, x: f32, y: f32) -> Option<GlyphImage> {
+        self.cbdt_glyph(glyph_id, x, y)
+            .or_else(|| self.sbix_glyph(glyph_id, x, y))
+    }
+
+    fn cbdt_glyph(&self, glyph_id: GlyphId, x: f32, y: f32) -> Option<GlyphImage> {
+        let cblc = self.font_ref.cblc().ok()?;
+        let cbdt = self.font_ref.cbdt().ok()?;
+        // The largest strike gives the best result when the slide is scaled up
+        let (size, location) = cblc
+            .bitmap_sizes()
+            .iter()
+            .filter_map(|size| {
+                let location = size.location(cblc.offset_data(), glyph_id).ok()?;
+                (!location.is_empty()).then_some((size, location))
+            })
+            .max_by_key(|(size, _)| size.ppem_y())?;
+        let data = cbdt.data(&location).ok()?;
+        let BitmapContent::Data(BitmapDataFormat::Png, png) = data.content else {
+            return None;
+        };
+        let (bearing_x, bearing_y, width, height) = match data.metrics {
+            BitmapMetrics::Small(m) => (m.bearing_x(), m.bearing_y(), m.width, m.height),
+            BitmapMetrics::Big(m) => (m.hori_bearing_x(), m.hori_bearing_y(), m.width, m.height),
+        };
+        let scale = self.font_size / size.ppem_y() as f32;
+        Some(GlyphImage {
+            rect: Rectangle::new(
+                x + bearing_x as f32 * scale,
+                y - bearing_y as f32 * scale,
+                width as f32 * scale,
+                height as f32 * scale,
+            ),
+            image: InMemoryBinImage::new_png(Arc::new(png.to_vec())),
+        })
+    }
+
+    fn sbix_glyph(&self, glyph_id: GlyphId, x: f32, y: f32) -> Option<GlyphImage> {
+        let sbix = self.font_ref.sbix().ok()?;
+        let strikes = sbix.strikes();
+        let (strike, data) = strikes
+            .iter()
+            .filter_map(|strike| {
+                let strike = strike.ok()?;
+                let data = strike.glyph_data(glyph_id).ok()??;
+                Some((strike, data))
+            })
+            .max_by_key(|(strike, _)| strike.ppem())?;
+        if data.graphic_type() != Tag::new(b"png ") {
+            return None;
+        }
+        let png = data.data();
+        let (width, height) = png_size(png)?;
+        let scale = self.font_size / strike.ppem() as f32;
+        Some(GlyphImage {
+            rect: Rectangle::new(
+                x + data.origin_offset_x() as f32 * scale,
+                y - (data.origin_offset_y() as f32 + height) * scale,
+                width * scale,
+                height * scale,
+            ),
+            image: InMemoryBinImage::new_png(Arc::new(png.to_vec())),
+        })
+    }
+
+    /// SVG glyphs are rasterized, because their documents may be shared by many glyphs
+    /// and cannot be simply embedded into all output formats
+    fn svg_glyph(&self, glyph_id: GlyphId, x: f32, y: f32) -> Option<GlyphImage> {
+        let document = self.font_ref.svg().ok()?.glyph_data(glyph_id).ok()??;
+        let tree = usvg::Tree::from_data(document, &usvg::Options::default()).ok()?;
+        let node = tree.node_by_id(&format!("glyph{}", glyph_id.to_u32()))?;
+        let bbox = node.abs_layer_bounding_box()?;
+        // SVG glyphs are defined in font units with the origin on the baseline
+        let scale = self.font_size / self.units_per_em();
+        let raster_scale = scale * GLYPH_RASTER_SCALE;
+        let mut pixmap = tiny_skia::Pixmap::new(
+            (bbox.width() * raster_scale).ceil() as u32,
+            (bbox.height() * raster_scale).ceil() as u32,
+        )?;
+        resvg::render_node(
+            node,
+            tiny_skia::Transform::from_scale(raster_scale, raster_scale),
+            &mut pixmap.as_mut(),
+        )?;
+        let png = pixmap.encode_png().ok()?;
+        Some(GlyphImage {
+            rect: Rectangle::new(
+                x + bbox.x() * scale,
+                y + bbox.y() * scale,
+                bbox.width() * scale,
+                bbox.height() * scale,
+            ),
+            image: InMemoryBinImage::new_png(Arc::new(png)),
+        })
+    }
+
+    /// COLR v1 glyphs may be filled by gradients and composed from blended layers,
+    /// which cannot be expressed by paths, so they are rasterized like SVG glyphs
+    fn colr_v1_glyph(&self, color_glyph: &ColorGlyph, x: f32, y: f32) -> Option<GlyphImage> {
+        let bbox = color_glyph
+            .bounding_box(self.location, Size::unscaled())
+            .or_else(|| self.paint_bounds(color_glyph))?;
+        let scale = self.font_size / self.units_per_em();
+        let raster_scale = scale * GLYPH_RASTER_SCALE;
+        let width = ((bbox.x_max - bbox.x_min) * raster_scale).ceil() as u32;
+        let height = ((bbox.y_max - bbox.y_min) * raster_scale).ceil() as u32;
+        let pixmap = tiny_skia::Pixmap::new(width, height)?;
+        let mut full_clip = tiny_skia::Mask::new(width, height)?;
+        full_clip.data_mut().fill(255);
+        let mut painter = RasterPainter {
+            glyphs: self,
+            // Font units are y-up, pixels are y-down
+            base_transform: tiny_skia::Transform::from_row(
+                raster_scale,
+                0.0,
+                0.0,
+                -raster_scale,
+                -bbox.x_min * raster_scale,
+                bbox.y_max * raster_scale,
+            ),
+            transforms: Vec::new(),
+            full_clip,
+            clips: Vec::new(),
+            layers: vec![(pixmap, CompositeMode::SrcOver)],
+        };
+        color_glyph.paint(self.location, &mut painter).ok()?;
+        let (pixmap, _) = painter.layers.into_iter().next()?;
+        let png = pixmap.encode_png().ok()?;
+        Some(GlyphImage {
+            rect: Rectangle::new(
+                x + bbox.x_min * scale,
+                y - bbox.y_max * scale,
+                width as f32 / GLYPH_RASTER_SCALE,
+                height as f32 / GLYPH_RASTER_SCALE,
+            ),
+            image: InMemoryBinImage::new_png(Arc::new(png)),
+        })
+    }
+
+    /// Bounds of all outlines of a COLR v1 glyph that has no clip box
+    fn paint_bounds(&self, color_glyph: &ColorGlyph) -> Option<BoundingBox<f32>> {
+        let mut painter = BoundsPainter {
+            glyphs: self,
+            transforms: Vec::new(),
+            bounds: None,
+        };
+        color_glyph.paint(self.location, &mut painter).ok()?;
+        painter.bounds
+    }
+}
+
+/// Reads the image size from the IHDR chunk of a PNG file
+fn png_size(data: &[u8]) -> Option<(f32, f32)> {
+    if data.len() < 24 || &data[12..16] != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(data[16..20].try_into().unwrap());
+    let height = u32::from_be_bytes(data[20..24].try_into().unwrap());
+    Some((width as f32, height as f32))
+}
+
+/// Turns the layers of a COLR v0 glyph into filled paths.
+///
+/// COLR v0 glyphs consist of solid glyph layers only, so everything except
+/// `fill_glyph` is never called.
+struct PathPainter<'p, 'a> {
+    glyphs: &'p ColorGlyphs<'a>,
+    x: f32,
+    y: f32,
+    scale: f32,
+    paths: Vec<DrawPath>,
+}
+
+impl ColorPainter for PathPainter<'_, '_> {
+    fn push_transform(&mut self, _transform: Transform) {}
+
+    fn pop_transform(&mut self) {}
+
+    fn push_clip_glyph(&mut self, _glyph_id: GlyphId) {}
+
+    fn push_clip_box(&mut self, _clip_box: BoundingBox<f32>) {}
+
+    fn pop_clip(&mut self) {}
+
+    fn fill(&mut self, _brush: Brush<'_>) {}
+
+    fn fill_glyph(
+        &mut self,
+        glyph_id: GlyphId,
+        _brush_transform: Option<Transform>,
+        brush: Brush<'_>,
+    ) {
+        let Brush::Solid {
+            palette_index,
+            alpha,
+        } = brush
+        else {
+            return;
+        };
+        let Some(outline) = self.glyphs.outlines.get(glyph_id) else {
+            return;
+        };
+        let color = self.glyphs.palette_color(palette_index, alpha);
+        let mut pen = GlyphPen {
+            path_builder: PathBuilder::new(FillAndStroke::new_fill(color)),
+            x: self.x,
+            y: self.y,
+            scale: self.scale,
+        };
+        let settings = DrawSettings::unhinted(Size::unscaled(), self.glyphs.location);
+        if outline.draw(settings, &mut pen).is_ok() {
+            self.paths.push(pen.path_builder.build());
+        }
+    }
+
+    fn push_layer(&mut self, _composite_mode: CompositeMode) {}
+}
+
+/// Pen for outlines in font units that places them at the pen position
+struct GlyphPen {
+    path_builder: PathBuilder,
+    x: f32,
+    y: f32,
+    scale: f32,
+}
+
+impl GlyphPen {
+    fn point(&self, x: f32, y: f32) -> (f32, f32) {
+        (self.x + x * self.scale, self.y - y * self.scale)
+    }
+}
+
+impl OutlinePen for GlyphPen {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.point(x, y);
+        self.path_builder.move_to(x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.point(x, y);
+        self.path_builder.line_to(x, y);
+    }
+
+    fn quad_to(&mut self, cx0: f32, cy0: f32, x: f32, y: f32) {
+        let (cx0, cy0) = self.point(cx0, cy0);
+        let (x, y) = self.point(x, y);
+        self.path_builder.quad_to(cx0, cy0, x, y);
+    }
+
+    fn curve_to(&mut self, cx0: f32, cy0: f32, cx1: f32, cy1: f32, x: f32, y: f32) {
+        let (cx0, cy0) = self.point(cx0, cy0);
+        let (cx1, cy1) = self.point(cx1, cy1);
+        let (x, y) = self.point(x, y);
+        self.path_builder.cubic_to(cx0, cy0, cx1, cy1, x, y);
+    }
+
+    fn close(&mut self) {
+        self.path_builder.close();
+    }
+}
+
+/// Pen that keeps outlines in font units
+struct SkiaPen(tiny_skia::PathBuilder);
+
+impl OutlinePen for SkiaPen {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.0.move_to(x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.0.line_to(x, y);
+    }
+
+    fn quad_to(&mut self, cx0: f32, cy0: f32, x: f32, y: f32) {
+        self.0.quad_to(cx0, cy0, x, y);
+    }
+
+    fn curve_to(&mut self, cx0: f32, cy0: f32, cx1: f32, cy1: f32, x: f32, y: f32) {
+        self.0.cubic_to(cx0, cy0, cx1, cy1, x, y);
+    }
+
+    fn close(&mut self) {
+        self.0.close();
+    }
+}
+
+fn skia_transform(t: Transform) -> tiny_skia::Transform {
+    tiny_skia::Transform::from_row(t.xx, t.yx, t.xy, t.yy, t.dx, t.dy)
+}
+
+fn blend_mode(mode: CompositeMode) -> tiny_skia::BlendMode {
+    use tiny_skia::BlendMode;
+    match mode {
+        CompositeMode::Clear => BlendMode::Clear,
+        CompositeMode::Src => BlendMode::Source,
+        CompositeMode::Dest => BlendMode::Destination,
+        CompositeMode::DestOver => BlendMode::DestinationOver,
+        CompositeMode::SrcIn => BlendMode::SourceIn,
+        CompositeMode::DestIn => BlendMode::DestinationIn,
+        CompositeMode::SrcOut => BlendMode::SourceOut,
+        CompositeMode::DestOut => BlendMode::DestinationOut,
+        CompositeMode::SrcAtop => BlendMode::SourceAtop,
+        CompositeMode::DestAtop => BlendMode::DestinationAtop,
+        CompositeMode::Xor => BlendMode::Xor,
+        CompositeMode::Plus => BlendMode::Plus,
+        CompositeMode::Screen => BlendMode::Screen,
+        CompositeMode::Overlay => BlendMode::Overlay,
+        CompositeMode::Darken => BlendMode::Darken,
+        CompositeMode::Lighten => BlendMode::Lighten,
+        CompositeMode::ColorDodge => BlendMode::ColorDodge,
+        CompositeMode::ColorBurn => BlendMode::ColorBurn,
+        CompositeMode::HardLight => BlendMode::HardLight,
+        CompositeMode::SoftLight => BlendMode::SoftLight,
+        CompositeMode::Difference => BlendMode::Difference,
+        CompositeMode::Exclusion => BlendMode::Exclusion,
+        CompositeMode::Multiply => BlendMode::Multiply,
+        CompositeMode::HslHue => BlendMode::Hue,
+        CompositeMode::HslSaturation => BlendMode::Saturation,
+        CompositeMode::HslColor => BlendMode::Color,
+        CompositeMode::HslLuminosity => BlendMode::Luminosity,
+        _ => BlendMode::SourceOver,
+    }
+}
+
+/// Paints a COLR v1 glyph into a pixmap.
+///
+/// Every clip is kept as a mask and every layer as a pixmap that is blended
+/// into the layer below when it is popped.
+struct RasterPainter<'p, 'a> {
+    glyphs: &'p ColorGlyphs<'a>,
+    /// Maps font units to pixels
+    base_transform: tiny_skia::Transform,
+    transforms: Vec<Transform>,
+    full_clip: tiny_skia::Mask,
+    clips: Vec<tiny_skia::Mask>,
+    layers: Vec<(tiny_skia::Pixmap, CompositeMode)>,
+}
+
+impl RasterPainter<'_, '_> {
+    fn current_transform(&self) -> tiny_skia::Transform {
+        let transform = self.transforms.last().copied().unwrap_or_default();
+        self.base_transform.pre_concat(skia_transform(transform))
+    }
+
+    fn current_clip(&self) -> &tiny_skia::Mask {
+        self.clips.last().unwrap_or(&self.full_clip)
+    }
+
+    /// Intersects the current clip with a path in font units; `None` clips everything
+    fn push_clip_path(&mut self, path: Option<tiny_skia::Path>) {
+        let mut mask = self.current_clip().clone();
+        match path {
+            Some(path) => mask.intersect_path(
+                &path,
+                tiny_skia::FillRule::Winding,
+                true,
+                self.current_transform(),
+            ),
+            None => mask.clear(),
+        }
+        self.clips.push(mask);
+    }
+
+    /// Evaluates a gradient for every pixel
+    fn gradient_pixmap(
+        &self,
+        gradient: Gradient,
+        color_stops: &[ColorStop],
+        extend: Extend,
+    ) -> Option<tiny_skia::Pixmap> {
+        let inverse = self.current_transform().invert()?;
+        let stops = self.glyphs.gradient_stops(color_stops);
+        let width = self.full_clip.width();
+        let mut pixmap = tiny_skia::Pixmap::new(width, self.full_clip.height())?;
+        for (i, pixel) in pixmap.pixels_mut().iter_mut().enumerate() {
+            let mut point = tiny_skia::Point::from_xy(
+                (i as u32 % width) as f32 + 0.5,
+                (i as u32 / width) as f32 + 0.5,
+            );
+            inverse.map_point(&mut point);
+            if let Some(offset) = gradient.offset(point.x, point.y) {
+                *pixel = premultiplied_color(stop_color(&stops, extend_offset(offset, extend)));
+            }
+        }
+        Some(pixmap)
+    }
+}
+
+impl ColorPainter for RasterPainter<'_, '_> {
+    fn push_transform(&mut self, transform: Transform) {
+        let current = self.transforms.last().copied().unwrap_or_default();
+        self.transforms.push(current * transform);
+    }
+
+    fn pop_transform(&mut self) {
+        self.transforms.pop();
+    }
+
+    fn push_clip_glyph(&mut self, glyph_id: GlyphId) {
+        self.push_clip_path(self.glyphs.glyph_path(glyph_id));
+    }
+
+    fn push_clip_box(&mut self, clip_box: BoundingBox<f32>) {
+        let rect = tiny_skia::Rect::from_ltrb(
+            clip_box.x_min,
+            clip_box.y_min,
+            clip_box.x_max,
+            clip_box.y_max,
+        );
+        self.push_clip_path(rect.map(tiny_skia::PathBuilder::from_rect));
+    }
+
+    fn pop_clip(&mut self) {
+        self.clips.pop();
+    }
+
+    fn fill(&mut self, brush: Brush<'_>) {
+        let (gradient, color_stops, extend) = match brush {
+            Brush::Solid {
+                palette_index,
+                alpha,
+            } => {
+                let c: svgtypes::Color = (&self.glyphs.palette_color(palette_index, alpha)).into();
+                let mut paint = tiny_skia::Paint::default();
+                paint.set_color_rgba8(c.red, c.green, c.blue, c.alpha);
+                let rect = tiny_skia::Rect::from_xywh(
+                    0.0,
+                    0.0,
+                    self.full_clip.width() as f32,
+                    self.full_clip.height() as f32,
+                );
+                let mask = self.clips.last().unwrap_or(&self.full_clip);
+                if let (Some(rect), Some((layer, _))) = (rect, self.layers.last_mut()) {
+                    layer.fill_rect(rect, &paint, tiny_skia::Transform::identity(), Some(mask));
+                }
+                return;
+            }
+            Brush::LinearGradient {
+                p0,
+                p1,
+                color_stops,
+                extend,
+            } => (
+                Gradient::Linear {
+                    p0: (p0.x, p0.y),
+                    p1: (p1.x, p1.y),
+                },
+                color_stops,
+                extend,
+            ),
+            Brush::RadialGradient {
+                c0,
+                r0,
+                c1,
+                r1,
+                color_stops,
+                extend,
+            } => (
+                Gradient::Radial {
+                    c0: (c0.x, c0.y),
+                    r0,
+                    c1: (c1.x, c1.y),
+                    r1,
+                },
+                color_stops,
+                extend,
+            ),
+            Brush::SweepGradient {
+                c0,
+                start_angle,
+                end_angle,
+                color_stops,
+                extend,
+            } => (
+                Gradient::Sweep {
+                    c0: (c0.x, c0.y),
+                    start_angle,
+                    end_angle,
+                },
+                color_stops,
+                extend,
+            ),
+        };
+        let Some(shade) = self.gradient_pixmap(gradient, color_stops, extend) else {
+            return;
+        };
+        let mask = self.clips.last().unwrap_or(&self.full_clip);
+        if let Some((layer, _)) = self.layers.last_mut() {
+            layer.draw_pixmap(
+                0,
+                0,
+                shade.as_ref(),
+                &tiny_skia::PixmapPaint::default(),
+                tiny_skia::Transform::identity(),
+                Some(mask),
+            );
+        }
+    }
+
+    fn push_layer(&mut self, composite_mode: CompositeMode) {
+        let mut pixmap = self.layers[0].0.clone();
+        pixmap.fill(tiny_skia::Color::TRANSPARENT);
+        self.layers.push((pixmap, composite_mode));
+    }
+
+    fn pop_layer(&mut self) {
+        if self.layers.len() < 2 {
+            return;
+        }
+        let Some((pixmap, composite_mode)) = self.layers.pop() else {
+            return;
+        };
+        if let Some((layer, _)) = self.layers.last_mut() {
+            let paint = tiny_skia::PixmapPaint {
+                blend_mode: blend_mode(composite_mode),
+                ..Default::default()
+            };
+            layer.draw_pixmap(
+                0,
+                0,
+                pixmap.as_ref(),
+                &paint,
+                tiny_skia::Transform::identity(),
+                None,
+            );
+        }
+    }
+}
+
+/// Collects bounds of all outlines that a COLR v1 glyph is clipped to
+struct BoundsPainter<'p, 'a> {
+    glyphs: &'p ColorGlyphs<'a>,
+    transforms: Vec<Transform>,
+    bounds: Option<BoundingBox<f32>>,
+}
+
+impl ColorPainter for BoundsPainter<'_, '_> {
+    fn push_transform(&mut self, transform: Transform) {
+        let current = self.transforms.last().copied().unwrap_or_default();
+        self.transforms.push(current * transform);
+    }
+
+    fn pop_transform(&mut self) {
+        self.transforms.pop();
+    }
+
+    fn push_clip_glyph(&mut self, glyph_id: GlyphId) {
+        let transform = skia_transform(self.transforms.last().copied().unwrap_or_default());
+        let Some(rect) = self
+            .glyphs
+            .glyph_path(glyph_id)
+            .and_then(|path| path.transform(transform))
+            .map(|path| path.bounds())
+        else {
+            return;
+        };
+        let bounds = self.bounds.get_or_insert(BoundingBox {
+            x_min: rect.left(),
+            y_min: rect.top(),
+            x_max: rect.right(),
+            y_max: rect.bottom(),
+        });
+        bounds.x_min = bounds.x_min.min(rect.left());
+        bounds.y_min = bounds.y_min.min(rect.top());
+        bounds.x_max = bounds.x_max.max(rect.right());
+        bounds.y_max = bounds.y_max.max(rect.bottom());
+    }
+
+    fn push_clip_box(&mut self, _clip_box: BoundingBox<f32>) {}
+
+    fn pop_clip(&mut self) {}
+
+    fn fill(&mut self, _brush: Brush<'_>) {}
+
+    fn push_layer(&mut self, _composite_mode: CompositeMode) {}
+}
+
+/// Geometry of a gradient brush in the coordinates of its fill
+enum Gradient {
+    Linear {
+        p0: (f32, f32),
+        p1: (f32, f32),
+    },
+    Radial {
+        c0: (f32, f32),
+        r0: f32,
+        c1: (f32, f32),
+        r1: f32,
+    },
+    Sweep {
+        c0: (f32, f32),
+        start_angle: f32,
+        end_angle: f32,
+    },
+}
+
+impl Gradient {
+    /// Returns the offset of a point on the color line before the extend mode is applied,
+    /// or `None` if the gradient does not paint the point
+    fn offset(&self, x: f32, y: f32) -> Option<f32> {
+        match *self {
+            Gradient::Linear { p0, p1 } => {
+                let (dx, dy) = (p1.0 - p0.0, p1.1 - p0.1);
+                let length2 = dx * dx + dy * dy;
+                (length2 > f32::EPSILON).then(|| ((x - p0.0) * dx + (y - p0.1) * dy) / length2)
+            }
+            Gradient::Radial { c0, r0, c1, r1 } => {
+                // Two point conical gradient: the largest t where the point lies
+                // on the circle interpolated between both circles
+                let (r0, r1) = (r0.max(0.0), r1.max(0.0));
+                let (cdx, cdy, dr) = (c1.0 - c0.0, c1.1 - c0.1, r1 - r0);
+                let (pdx, pdy) = (x - c0.0, y - c0.1);
+                let a = cdx * cdx + cdy * cdy - dr * dr;
+                let b = pdx * cdx + pdy * cdy + r0 * dr;
+                let c = pdx * pdx + pdy * pdy - r0 * r0;
+                let is_valid = |t: &f32| r0 + t * dr >= 0.0;
+                if a.abs() < f32::EPSILON {
+                    return (b.abs() > f32::EPSILON)
+                        .then(|| c / (2.0 * b))
+                        .filter(is_valid);
+                }
+                let discriminant = b * b - a * c;
+                if discriminant < 0.0 {
+                    return None;
+                }
+                let t0 = (b + discriminant.sqrt()) / a;
+                let t1 = (b - discriminant.sqrt()) / a;
+                [t0.max(t1), t0.min(t1)].into_iter().find(is_valid)
+            }
+            Gradient::Sweep {
+                c0,
+                start_angle,
+                end_angle,
+            } => {
+                // Angles are clockwise in font units
+                let angle = (c0.1 - y).atan2(x - c0.0).to_degrees().rem_euclid(360.0);
+                let span = end_angle - start_angle;
+                if span.abs() < f32::EPSILON {
+                    return Some(if angle < start_angle { 0.0 } else { 1.0 });
+                }
+                Some((angle - start_angle) / span)
+            }
+        }
+    }
+}
+
+fn extend_offset(offset: f32, extend: Extend) -> f32 {
+    match extend {
+        Extend::Repeat => offset - offset.floor(),
+        Extend::Reflect => {
+            let offset = offset.rem_euclid(2.0);
+            if offset > 1.0 { 2.0 - offset } else { offset }
+        }
+        _ => offset.clamp(0.0, 1.0),
+    }
+}
+
+/// Interpolates premultiplied colors of stops sorted by their offsets
+fn stop_color(stops: &[(f32, [f32; 4])], offset: f32) -> [f32; 4] {
+    match stops.iter().position(|(o, _)| *o >= offset) {
+        None => stops.last().map(|(_, c)| *c).unwrap_or_default(),
+        Some(0) => stops[0].1,
+        Some(i) => {
+            let (o0, c0) = stops[i - 1];
+            let (o1, c1) = stops[i];
+            let f = if o1 > o0 {
+                (offset - o0) / (o1 - o0)
+            } else {
+                1.0
+            };
+            [0, 1, 2, 3].map(|k| c0[k] + (c1[k] - c0[k]) * f)
+        }
+    }
+}
+
+fn premultiplied_color(color: [f32; 4]) -> tiny_skia::PremultipliedColorU8 {
+    let [r, g, b, a] = color.map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8);
+    tiny_skia::PremultipliedColorU8::from_rgba(r.min(a), g.min(a), b.min(a), a)
+        .unwrap_or(tiny_skia::PremultipliedColorU8::TRANSPARENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extend_offset() {
+        assert_eq!(extend_offset(1.25, Extend::Pad), 1.0);
+        assert_eq!(extend_offset(-0.5, Extend::Pad), 0.0);
+        assert_eq!(extend_offset(1.25, Extend::Repeat), 0.25);
+        assert_eq!(extend_offset(-0.25, Extend::Repeat), 0.75);
+        assert_eq!(extend_offset(1.25, Extend::Reflect), 0.75);
+        assert_eq!(extend_offset(-0.25, Extend::Reflect), 0.25);
+    }
+
+    #[test]
+    fn test_gradient_offset() {
+        let linear = Gradient::Linear {
+            p0: (10.0, 0.0),
+            p1: (20.0, 0.0),
+        };
+        assert_eq!(linear.offset(15.0, 7.0), Some(0.5));
+        assert_eq!(linear.offset(0.0, 0.0), Some(-1.0));
+
+        let radial = Gradient::Radial {
+            c0: (0.0, 0.0),
+            r0: 0.0,
+            c1: (0.0, 0.0),
+            r1: 10.0,
+        };
+        assert_eq!(radial.offset(0.0, 5.0), Some(0.5));
+        assert_eq!(radial.offset(-20.0, 0.0), Some(2.0));
+
+        let sweep = Gradient::Sweep {
+            c0: (0.0, 0.0),
+            start_angle: 0.0,
+            end_angle: 180.0,
+        };
+        assert_eq!(sweep.offset(0.0, -1.0), Some(0.5));
+        assert_eq!(sweep.offset(0.0, 1.0), Some(1.5));
+    }
+
+    #[test]
+    fn test_stop_color() {
+        let stops = [(0.0, [0.0, 0.0, 0.0, 1.0]), (0.5, [1.0, 0.5, 0.0, 1.0])];
+        assert_eq!(stop_color(&stops, 0.25), [0.5, 0.25, 0.0, 1.0]);
+        assert_eq!(stop_color(&stops, 0.75), [1.0, 0.5, 0.0, 1.0]);
+        assert_eq!(stop_color(&[], 0.5), [0.0; 4]);
+    }
+}
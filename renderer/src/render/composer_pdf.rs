@@ -7,12 +7,12 @@ use crate::render::text::RenderedText;
 use crate::{ContentId, InMemoryBinImage, InMemorySvgImage, Resources};
 use image::GenericImageView;
 use miniz_oxide::deflate::{CompressionLevel, compress_to_vec_zlib};
-use pdf_writer::{Chunk, Filter, Finish, Rect, Ref};
+use pdf_writer::{Chunk, Filter, Finish, Name, Rect, Ref};
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::ops::DerefMut;
-use std::sync::Mutex;
 use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Mutex};
 
 pub(crate) struct PdfComposer {
     chunks: Mutex<Vec<Chunk>>,
@@ -23,6 +23,7 @@ pub(crate) struct PdfComposer {
     page_refs: Vec<Ref>,
     compression_level: u8,
     ref_allocator: PdfRefAllocator,
+    glyph_images: GlyphImageRefs,
 }
 
 impl PdfComposer {
@@ -39,6 +40,7 @@ impl PdfComposer {
             content_to_ref: HashMap::new(),
             content_to_ref_builder: Mutex::new(HashMap::new()),
             ref_allocator: PdfRefAllocator::new(alloc_ref),
+            glyph_images: GlyphImageRefs::default(),
         }
     }
 
@@ -77,6 +79,7 @@ impl Composer for PdfComposer {
     ) -> crate::Result<()> {
         let page = canvas.into_pdf_page(
             &self.ref_allocator,
+            &self.glyph_images,
             self.page_refs[page_idx],
             self.page_tree_ref,
             self.compression_level,
@@ -101,6 +104,7 @@ impl Composer for PdfComposer {
                     width,
                     height,
                     &self.ref_allocator,
+                    &self.glyph_images,
                     self.compression_level,
                 )
             }
@@ -199,22 +203,43 @@ impl PdfRefAllocator {
     }
 }
 
+/// XObjects of glyph images (e.g. emojis) shared by all texts of a document, so each image is embedded once.
+/// Images are compared by their data, because every occurrence of a glyph has its own copy of the image.
+#[derive(Default)]
+pub(crate) struct GlyphImageRefs(Mutex<HashMap<Arc<Vec<u8>>, Ref>>);
+
+impl GlyphImageRefs {
+    /// Returns the XObject of the image; it is written into the chunk of `pdf_writer`
+    /// when the image is used for the first time
+    fn get_or_create(&self, image: &InMemoryBinImage, pdf_writer: &mut PdfWriter) -> Ref {
+        let data = match image {
+            InMemoryBinImage::Png(data) | InMemoryBinImage::Jpeg(data) => Arc::clone(&data.0),
+        };
+        if let Some(rf) = self.0.lock().unwrap().get(&data) {
+            return *rf;
+        }
+        let (chunk, rf) = create_image_xobject(image, pdf_writer.alloc_ref);
+        pdf_writer.chunk.extend(&chunk);
+        // If another thread created the image in the meantime, its XObject is used and this one stays unused
+        *self.0.lock().unwrap().entry(data).or_insert(rf)
+    }
+}
+
 fn create_text_xobject(
     text: &RenderedText,
     width: f32,
     height: f32,
     allocator: &PdfRefAllocator,
+    glyph_images: &GlyphImageRefs,
     compression_level: u8,
 ) -> (Chunk, Ref) {
     let obj_ref = allocator.bump();
-    let mut pdf_writer = PdfWriter::new(allocator);
+    let mut pdf_writer = PdfWriter::new(allocator, glyph_images);
     pdf_writer.content.save_state();
     pdf_writer
         .content
         .transform([1.0 / width, 0.0, 0.0, -1.0 / height, 0.0, 1.0]);
-    for path in text.paths() {
-        path_to_pdf(&mut pdf_writer, path)
-    }
+    text_to_pdf(&mut pdf_writer, text);
     pdf_writer.content.restore_state();
 
     let mut content_data = pdf_writer.content.finish();
@@ -226,10 +251,32 @@ fn create_text_xobject(
     if compression_level > 0 {
         x_obj.filter(Filter::FlateDecode);
     }
+    if !pdf_writer.xo_resources.is_empty() {
+        let mut resources = x_obj.resources();
+        let mut objects = resources.x_objects();
+        for (name, rf) in &pdf_writer.xo_resources {
+            objects.pair(Name(name.as_bytes()), *rf);
+        }
+        objects.finish();
+        resources.finish();
+    }
     x_obj.finish();
     (pdf_writer.chunk, obj_ref)
 }
 
+/// Draws paths and glyph images of a text in the coordinates of the text
+pub(crate) fn text_to_pdf(pdf_writer: &mut PdfWriter, text: &RenderedText) {
+    for path in text.paths() {
+        path_to_pdf(pdf_writer, path)
+    }
+    for glyph_image in text.images() {
+        let glyph_images = pdf_writer.glyph_images;
+        let rf = glyph_images.get_or_create(&glyph_image.image, pdf_writer);
+        let rect = &glyph_image.rect;
+        pdf_writer.put_x_object(rf, rect.clone(), rect.width, rect.height);
+    }
+}
+
 pub fn create_svg_xobject(
     resources: &Resources,
     svg_image: &InMemorySvgImage,
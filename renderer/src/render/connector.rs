@@ -0,0 +1,208 @@
+use crate::Rectangle;
+use crate::render::draw::{DrawPath, PathBuilder};
+use crate::render::geometry::ShapeGeometry;
+use crate::render::layout::{ComputedLayout, LayoutData};
+use crate::shapes::{Connector, ConnectorRouting, ConnectorSide, FillAndStroke};
+
+/// Distance of an orthogonal route from nodes when it leaves and enters them in the same direction
+const ROUTE_MARGIN: f32 = 20.0;
+
+type Point = (f32, f32);
+
+impl ConnectorSide {
+    fn direction(self) -> Point {
+        match self {
+            ConnectorSide::Top => (0.0, -1.0),
+            ConnectorSide::Right => (1.0, 0.0),
+            ConnectorSide::Bottom => (0.0, 1.0),
+            ConnectorSide::Left => (-1.0, 0.0),
+        }
+    }
+
+    fn is_horizontal(self) -> bool {
+        matches!(self, ConnectorSide::Left | ConnectorSide::Right)
+    }
+}
+
+fn center(rect: &Rectangle) -> Point {
+    (rect.x + rect.width / 2.0, rect.y + rect.height / 2.0)
+}
+
+fn side_point(rect: &Rectangle, side: ConnectorSide) -> Point {
+    let (cx, cy) = center(rect);
+    match side {
+        ConnectorSide::Top => (cx, rect.y),
+        ConnectorSide::Right => (rect.x + rect.width, cy),
+        ConnectorSide::Bottom => (cx, rect.y + rect.height),
+        ConnectorSide::Left => (rect.x, cy),
+    }
+}
+
+/// Chooses the side of `from` that faces `to`; a larger gap between nodes wins
+fn auto_side(from: &Rectangle, to: &Rectangle) -> ConnectorSide {
+    let (fx, fy) = center(from);
+    let (tx, ty) = center(to);
+    let h_gap = (to.x - (from.x + from.width)).max(from.x - (to.x + to.width));
+    let v_gap = (to.y - (from.y + from.height)).max(from.y - (to.y + to.height));
+    if h_gap >= v_gap {
+        if tx >= fx {
+            ConnectorSide::Right
+        } else {
+            ConnectorSide::Left
+        }
+    } else if ty >= fy {
+        ConnectorSide::Bottom
+    } else {
+        ConnectorSide::Top
+    }
+}
+
+/// A point where the line from the center of a node towards `target` crosses the node border
+fn border_point(layout: &LayoutData, target: Point) -> Point {
+    let rect = &layout.rect;
+    let outline = if layout.border_radius < 0.001 {
+        ShapeGeometry::from_rect(rect)
+    } else {
+        ShapeGeometry::from_path(&rect.rounded_path(FillAndStroke::default(), layout.border_radius))
+    };
+    let c = center(rect);
+    outline.exit_point(c, target).unwrap_or(c)
+}
+
+/// Corners of an orthogonal route between two points on sides of nodes
+fn elbow_points(p1: Point, side1: ConnectorSide, p2: Point, side2: ConnectorSide) -> Vec<Point> {
+    let (d1, d2) = (side1.direction(), side2.direction());
+    match (side1.is_horizontal(), side2.is_horizontal()) {
+        (true, true) => {
+            let x = if d1.0 != d2.0 {
+                (p1.0 + p2.0) / 2.0
+            } else if d1.0 > 0.0 {
+                p1.0.max(p2.0) + ROUTE_MARGIN
+            } else {
+                p1.0.min(p2.0) - ROUTE_MARGIN
+            };
+            vec![(x, p1.1), (x, p2.1)]
+        }
+        (false, false) => {
+            let y = if d1.1 != d2.1 {
+                (p1.1 + p2.1) / 2.0
+            } else if d1.1 > 0.0 {
+                p1.1.max(p2.1) + ROUTE_MARGIN
+            } else {
+                p1.1.min(p2.1) - ROUTE_MARGIN
+            };
+            vec![(p1.0, y), (p2.0, y)]
+        }
+        (true, false) => vec![(p2.0, p1.1)],
+        (false, true) => vec![(p1.0, p2.1)],
+    }
+}
+
+fn is_same_point(a: Point, b: Point) -> bool {
+    (a.0 - b.0).abs() < 0.001 && (a.1 - b.1).abs() < 0.001
+}
+
+pub(crate) fn route_connector(connector: &Connector, layout: &ComputedLayout) -> DrawPath {
+    let start = layout.node_layout(connector.start).unwrap();
+    let end = layout.node_layout(connector.end).unwrap();
+    let mut builder = PathBuilder::new(connector.fill_and_stroke.clone());
+    if connector.routing == ConnectorRouting::Straight {
+        let start_point = connector
+            .start_side
+            .map(|side| side_point(&start.rect, side));
+        let end_point = connector.end_side.map(|side| side_point(&end.rect, side));
+        let p1 = start_point
+            .unwrap_or_else(|| border_point(start, end_point.unwrap_or(center(&end.rect))));
+        let p2 = end_point
+            .unwrap_or_else(|| border_point(end, start_point.unwrap_or(center(&start.rect))));
+        builder.move_to(p1.0, p1.1);
+        builder.line_to(p2.0, p2.1);
+        return builder.build();
+    }
+
+    let side1 = connector
+        .start_side
+        .unwrap_or_else(|| auto_side(&start.rect, &end.rect));
+    let side2 = connector
+        .end_side
+        .unwrap_or_else(|| auto_side(&end.rect, &start.rect));
+    let p1 = side_point(&start.rect, side1);
+    let p2 = side_point(&end.rect, side2);
+    builder.move_to(p1.0, p1.1);
+    match connector.routing {
+        ConnectorRouting::Orthogonal => {
+            let mut last = p1;
+            for p in elbow_points(p1, side1, p2, side2)
+                .into_iter()
+                .chain(std::iter::once(p2))
+            {
+                if !is_same_point(last, p) {
+                    builder.line_to(p.0, p.1);
+                    last = p;
+                }
+            }
+        }
+        ConnectorRouting::Curved => {
+            let (d1, d2) = (side1.direction(), side2.direction());
+            let k = (p2.0 - p1.0).hypot(p2.1 - p1.1) / 2.0;
+            builder.cubic_to(
+                p1.0 + d1.0 * k,
+                p1.1 + d1.1 * k,
+                p2.0 + d2.0 * k,
+                p2.1 + d2.1 * k,
+                p2.0,
+                p2.1,
+            );
+        }
+        ConnectorRouting::Straight => unreachable!(),
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_side() {
+        let a = Rectangle::new(0.0, 0.0, 100.0, 100.0);
+        let b = Rectangle::new(300.0, 50.0, 100.0, 100.0);
+        assert_eq!(auto_side(&a, &b), ConnectorSide::Right);
+        assert_eq!(auto_side(&b, &a), ConnectorSide::Left);
+        let c = Rectangle::new(50.0, 300.0, 100.0, 100.0);
+        assert_eq!(auto_side(&a, &c), ConnectorSide::Bottom);
+        assert_eq!(auto_side(&c, &a), ConnectorSide::Top);
+    }
+
+    #[test]
+    fn test_elbow_points() {
+        use ConnectorSide::*;
+        assert_eq!(
+            elbow_points((100.0, 50.0), Right, (300.0, 150.0), Left),
+            vec![(200.0, 50.0), (200.0, 150.0)]
+        );
+        assert_eq!(
+            elbow_points((100.0, 50.0), Right, (300.0, 150.0), Right),
+            vec![(320.0, 50.0), (320.0, 150.0)]
+        );
+        assert_eq!(
+            elbow_points((50.0, 100.0), Bottom, (300.0, 150.0), Left),
+            vec![(50.0, 150.0)]
+        );
+    }
+
+    #[test]
+    fn test_border_point_rounded() {
+        let layout = LayoutData {
+            rect: Rectangle::new(0.0, 0.0, 100.0, 100.0),
+            border_radius: 50.0,
+            text: None,
+            padding: taffy::Rect::zero(),
+            margin: taffy::Rect::zero(),
+        };
+        let (x, y) = border_point(&layout, (150.0, -50.0));
+        assert!((x - 87.5).abs() < 0.1 && (y - 12.5).abs() < 0.1);
+        let (x, y) = border_point(&layout, (150.0, 50.0));
+        assert!((x - 100.0).abs() < 0.001 && (y - 50.0).abs() < 0.001);
+    }
+}
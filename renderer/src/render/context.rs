@@ -1,5 +1,8 @@
+use crate::Page;
 use crate::render::content::ContentMap;
 
 pub(crate) struct RenderContext<'a> {
     pub content_map: &'a ContentMap,
+    /// All pages of the document, used to name boxes from other pages in errors
+    pub pages: &'a [Page],
 }
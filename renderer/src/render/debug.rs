@@ -0,0 +1,232 @@
+use crate::node::Node;
+use crate::render::canvas::Canvas;
+use crate::render::draw::DrawItem;
+use crate::render::layout::{ComputedLayout, LayoutData, is_layout_managed};
+use crate::render::text::{TextContext, render_text};
+use crate::shapes::{FillAndStroke, Stroke};
+use crate::text::{Text, TextStyle};
+use crate::{Color, Length, NodeId, Rectangle, Resources};
+use resvg::usvg::PositiveF32;
+use std::sync::Arc;
+use taffy::prelude as tf;
+
+/// The overlay is drawn over all other items
+const DEBUG_LAYOUT_Z_LEVEL: i32 = 999999;
+const DEBUG_LAYOUT_FONT_SIZE: f32 = 8.0;
+/// Alpha of fills of gaps and inline anchors
+const DEBUG_FILL_ALPHA: u8 = 64;
+/// Alpha of frames of text lines
+const DEBUG_LINE_ALPHA: u8 = 128;
+
+fn stroke(color: Color, width: f32, dash_array: Option<Vec<f32>>) -> FillAndStroke {
+    FillAndStroke::new_stroke(Stroke {
+        color,
+        width,
+        dash_array,
+        dash_offset: 0.0,
+    })
+}
+
+/// Moves each side of the rectangle outwards by the given amount
+fn expand(rect: &Rectangle, sides: &tf::Rect<f32>) -> Rectangle {
+    Rectangle::new(
+        rect.x - sides.left,
+        rect.y - sides.top,
+        rect.width + sides.left + sides.right,
+        rect.height + sides.top + sides.bottom,
+    )
+}
+
+fn is_zero(sides: &tf::Rect<f32>) -> bool {
+    [sides.left, sides.right, sides.top, sides.bottom]
+        .iter()
+        .all(|v| v.abs() < 0.001)
+}
+
+/// Name of the box (or its id if it has no name) followed by its size
+fn debug_label(name: &str, node_id: NodeId, rect: &Rectangle) -> String {
+    let size = format!("[{}x{}]", format_size(rect.width), format_size(rect.height));
+    if name.is_empty() {
+        format!("#{} {size}", node_id.as_usize())
+    } else {
+        format!("{name} {size}")
+    }
+}
+
+/// Formats a size with at most two decimal places and without trailing zeros
+fn format_size(value: f32) -> String {
+    format!("{value:.2}")
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Gaps between neighbouring children placed by the flexbox layout of the node
+fn flex_gaps(node: &Node, data: &LayoutData, layout: &ComputedLayout) -> Vec<Rectangle> {
+    let padding = &data.padding;
+    let (gap, inner_size) = if node.row {
+        (
+            node.column_gap,
+            data.rect.width - padding.left - padding.right,
+        )
+    } else {
+        (
+            node.row_gap,
+            data.rect.height - padding.top - padding.bottom,
+        )
+    };
+    let gap = match gap {
+        Length::Points { value } => value,
+        Length::Fraction { value } => value * inner_size,
+    };
+    if node.is_grid() || gap <= 0.0 {
+        return Vec::new();
+    }
+    // Rectangles are handled as (main start, main size, cross start, cross size)
+    let to_axes = |r: &Rectangle| {
+        if node.row {
+            (r.x, r.width, r.y, r.height)
+        } else {
+            (r.y, r.height, r.x, r.width)
+        }
+    };
+    let mut children: Vec<_> = node
+        .child_nodes()
+        .filter(|child| is_layout_managed(child, Some(node)))
+        .map(|child| {
+            let data = layout.node_layout(child.node_id).unwrap();
+            to_axes(&expand(&data.rect, &data.margin))
+        })
+        .collect();
+    children.sort_by(|a, b| a.0.total_cmp(&b.0));
+    children
+        .windows(2)
+        .filter_map(|pair| {
+            let (main1, size1, cross1, cross_size1) = pair[0];
+            let (main2, _, cross2, cross_size2) = pair[1];
+            let start = main1 + size1;
+            // Children on different lines of a wrapped layout are not separated by a main axis gap
+            if main2 <= start {
+                return None;
+            }
+            let size = gap.min(main2 - start);
+            let cross = cross1.min(cross2);
+            let cross_size = (cross1 + cross_size1).max(cross2 + cross_size2) - cross;
+            Some(if node.row {
+                Rectangle::new(start, cross, size, cross_size)
+            } else {
+                Rectangle::new(cross, start, cross_size, size)
+            })
+        })
+        .collect()
+}
+
+fn render_node_overlay(
+    node: &Node,
+    color: Color,
+    resources: &Resources,
+    text_ctx: &mut TextContext,
+    layout: &ComputedLayout,
+    canvas: &mut Canvas,
+) -> crate::Result<()> {
+    let data = layout.node_layout(node.node_id).unwrap();
+    let rect = &data.rect;
+    let mut add = |item: DrawItem| canvas.add_draw_item(DEBUG_LAYOUT_Z_LEVEL, item);
+
+    if !is_zero(&data.margin) {
+        add(expand(rect, &data.margin).draw(stroke(color, 0.5, Some(vec![1.0, 2.0]))));
+    }
+    if !is_zero(&data.padding) {
+        let padding = tf::Rect {
+            left: -data.padding.left,
+            right: -data.padding.right,
+            top: -data.padding.top,
+            bottom: -data.padding.bottom,
+        };
+        add(expand(rect, &padding).draw(stroke(color, 0.5, Some(vec![2.0, 2.0]))));
+    }
+    for gap in flex_gaps(node, data, layout) {
+        add(gap.draw(FillAndStroke::new_fill(color.with_alpha(DEBUG_FILL_ALPHA))));
+    }
+    if let Some(text) = &data.text {
+        // A single line fills the whole text, so its rect is not drawn
+        if text.line_layouts().len() > 1 {
+            for line in text.line_layouts() {
+                let line =
+                    Rectangle::new(rect.x + line.x, rect.y + line.y, line.width, line.height);
+                add(line.draw(stroke(color.with_alpha(DEBUG_LINE_ALPHA), 0.5, None)));
+            }
+        }
+        for anchor in text.intext_rects().values() {
+            let anchor = Rectangle::new(
+                rect.x + anchor.x,
+                rect.y + anchor.y,
+                anchor.width,
+                anchor.height,
+            );
+            add(anchor.draw(FillAndStroke::new_fill(color.with_alpha(DEBUG_FILL_ALPHA))));
+        }
+    }
+
+    let frame = Rectangle::new(rect.x, rect.y, rect.width.max(1.0), rect.height.max(1.0));
+    add(frame.draw(stroke(color, 1.0, Some(vec![5.0, 2.0]))));
+
+    let label = Text {
+        text: debug_label(&node.name, node.node_id, rect),
+        style: TextStyle {
+            font: Some(Arc::new("monospace".to_string())),
+            color: Some(color),
+            size: PositiveF32::new(DEBUG_LAYOUT_FONT_SIZE),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let (label, _, _) = render_text(resources, text_ctx, &label)?;
+    for mut path in label.into_paths() {
+        path.transform_points(|x, y| (x + rect.x + 1.0, y + rect.y + 1.0));
+        add(DrawItem::Path(path));
+    }
+    Ok(())
+}
+
+/// Draws the debug overlay of nodes that have `debug_layout` set: frames of nodes and of their
+/// padding and margin boxes, flexbox gaps, rects of text lines and inline anchors, and labels
+/// with names and sizes of nodes
+pub(crate) fn render_debug_layout(
+    node: &Node,
+    resources: &Resources,
+    text_ctx: &mut TextContext,
+    layout: &ComputedLayout,
+    canvas: &mut Canvas,
+) -> crate::Result<()> {
+    if let Some(color) = node.debug_layout {
+        render_node_overlay(node, color, resources, text_ctx, layout, canvas)?;
+    }
+    for child in node.child_nodes() {
+        render_debug_layout(child, resources, text_ctx, layout, canvas)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(100.0), "100");
+        assert_eq!(format_size(0.0), "0");
+        assert_eq!(format_size(12.5), "12.5");
+        assert_eq!(format_size(1.0 / 3.0), "0.33");
+    }
+
+    #[test]
+    fn test_debug_label() {
+        let rect = Rectangle::new(10.0, 10.0, 100.0, 12.5);
+        assert_eq!(
+            debug_label("Box 1", NodeId::new(123), &rect),
+            "Box 1 [100x12.5]"
+        );
+        assert_eq!(debug_label("", NodeId::new(123), &rect), "#123 [100x12.5]");
+    }
+}
@@ -76,6 +76,32 @@ impl DrawPath {
     pub fn fill_and_stroke(&self) -> &FillAndStroke {
         &self.fill_and_stroke
     }
+
+    pub fn transform_points(&mut self, f: impl Fn(f32, f32) -> (f32, f32)) {
+        let apply = |x: &mut f32, y: &mut f32| (*x, *y) = f(*x, *y);
+        for part in &mut self.parts {
+            match part {
+                DrawPathPart::Move { x, y } | DrawPathPart::Line { x, y } => apply(x, y),
+                DrawPathPart::Quad { x1, y1, x, y } => {
+                    apply(x1, y1);
+                    apply(x, y);
+                }
+                DrawPathPart::Cubic {
+                    x1,
+                    y1,
+                    x2,
+                    y2,
+                    x,
+                    y,
+                } => {
+                    apply(x1, y1);
+                    apply(x2, y2);
+                    apply(x, y);
+                }
+                DrawPathPart::Close => {}
+            }
+        }
+    }
 }
 
 pub(crate) struct PathBuilder(DrawPath);
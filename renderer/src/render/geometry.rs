@@ -0,0 +1,269 @@
+use crate::Rectangle;
+use crate::render::draw::{DrawPath, DrawPathPart};
+
+const CURVE_SEGMENTS: usize = 32;
+const OVAL_SEGMENTS: usize = 64;
+
+type Point = (f32, f32);
+
+/// An outline of a shape flattened into line segments.
+/// It is used by layout expressions that refer to shapes.
+#[derive(Debug)]
+pub(crate) struct ShapeGeometry {
+    segments: Vec<(Point, Point)>,
+    bbox: Rectangle,
+}
+
+fn quad_point(p0: Point, p1: Point, p: Point, t: f32) -> Point {
+    let s = 1.0 - t;
+    (
+        s * s * p0.0 + 2.0 * s * t * p1.0 + t * t * p.0,
+        s * s * p0.1 + 2.0 * s * t * p1.1 + t * t * p.1,
+    )
+}
+
+fn cubic_point(p0: Point, p1: Point, p2: Point, p: Point, t: f32) -> Point {
+    let s = 1.0 - t;
+    (
+        s * s * s * p0.0 + 3.0 * s * s * t * p1.0 + 3.0 * s * t * t * p2.0 + t * t * t * p.0,
+        s * s * s * p0.1 + 3.0 * s * s * t * p1.1 + 3.0 * s * t * t * p2.1 + t * t * t * p.1,
+    )
+}
+
+fn distance(a: Point, b: Point) -> f32 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+impl ShapeGeometry {
+    fn new(segments: Vec<(Point, Point)>, points: impl Iterator<Item = Point>) -> Self {
+        let mut min = (f32::INFINITY, f32::INFINITY);
+        let mut max = (f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for (x, y) in points {
+            min = (min.0.min(x), min.1.min(y));
+            max = (max.0.max(x), max.1.max(y));
+        }
+        let bbox = if min.0 <= max.0 {
+            Rectangle::new(min.0, min.1, max.0 - min.0, max.1 - min.1)
+        } else {
+            Rectangle::new(0.0, 0.0, 0.0, 0.0)
+        };
+        ShapeGeometry { segments, bbox }
+    }
+
+    /// The outline starts in the top-left corner and goes clockwise
+    pub fn from_rect(rect: &Rectangle) -> Self {
+        let (x1, y1) = (
+            rect.x.min(rect.x + rect.width),
+            rect.y.min(rect.y + rect.height),
+        );
+        let (x2, y2) = (
+            rect.x.max(rect.x + rect.width),
+            rect.y.max(rect.y + rect.height),
+        );
+        let corners = [(x1, y1), (x2, y1), (x2, y2), (x1, y2)];
+        let segments = (0..4).map(|i| (corners[i], corners[(i + 1) % 4])).collect();
+        Self::new(segments, corners.into_iter())
+    }
+
+    /// The outline starts in the rightmost point and goes clockwise
+    pub fn from_oval(rect: &Rectangle) -> Self {
+        let (cx, cy) = (rect.x + rect.width / 2.0, rect.y + rect.height / 2.0);
+        let (rx, ry) = (rect.width.abs() / 2.0, rect.height.abs() / 2.0);
+        let points: Vec<Point> = (0..=OVAL_SEGMENTS)
+            .map(|i| {
+                let angle = i as f32 / OVAL_SEGMENTS as f32 * std::f32::consts::TAU;
+                (cx + rx * angle.cos(), cy + ry * angle.sin())
+            })
+            .collect();
+        let segments = points.windows(2).map(|w| (w[0], w[1])).collect();
+        Self::new(segments, points.into_iter())
+    }
+
+    pub fn from_path(path: &DrawPath) -> Self {
+        let mut segments = Vec::new();
+        let mut points = Vec::new();
+        let mut current = (0.0, 0.0);
+        let mut start = current;
+        let mut add_line = |from: &mut Point, to: Point, points: &mut Vec<Point>| {
+            segments.push((*from, to));
+            points.push(to);
+            *from = to;
+        };
+        for part in path.parts() {
+            match *part {
+                DrawPathPart::Move { x, y } => {
+                    current = (x, y);
+                    start = current;
+                    points.push(current);
+                }
+                DrawPathPart::Line { x, y } => add_line(&mut current, (x, y), &mut points),
+                DrawPathPart::Quad { x1, y1, x, y } => {
+                    let p0 = current;
+                    for i in 1..=CURVE_SEGMENTS {
+                        let t = i as f32 / CURVE_SEGMENTS as f32;
+                        add_line(
+                            &mut current,
+                            quad_point(p0, (x1, y1), (x, y), t),
+                            &mut points,
+                        );
+                    }
+                }
+                DrawPathPart::Cubic {
+                    x1,
+                    y1,
+                    x2,
+                    y2,
+                    x,
+                    y,
+                } => {
+                    let p0 = current;
+                    for i in 1..=CURVE_SEGMENTS {
+                        let t = i as f32 / CURVE_SEGMENTS as f32;
+                        add_line(
+                            &mut current,
+                            cubic_point(p0, (x1, y1), (x2, y2), (x, y), t),
+                            &mut points,
+                        );
+                    }
+                }
+                DrawPathPart::Close => add_line(&mut current, start, &mut points),
+            }
+        }
+        Self::new(segments, points.into_iter())
+    }
+
+    pub fn bbox(&self) -> &Rectangle {
+        &self.bbox
+    }
+
+    /// The last intersection of the line segment `from` -> `to` with the outline.
+    /// For a convex outline and `from` inside it, it is the point where the segment leaves the shape.
+    pub fn exit_point(&self, from: Point, to: Point) -> Option<Point> {
+        let r = (to.0 - from.0, to.1 - from.1);
+        let mut last: Option<f32> = None;
+        for (a, b) in &self.segments {
+            let s = (b.0 - a.0, b.1 - a.1);
+            let denom = r.0 * s.1 - r.1 * s.0;
+            if denom.abs() < 1e-9 {
+                continue;
+            }
+            let q = (a.0 - from.0, a.1 - from.1);
+            let t = (q.0 * s.1 - q.1 * s.0) / denom;
+            let u = (q.0 * r.1 - q.1 * r.0) / denom;
+            if (0.0..=1.0).contains(&t)
+                && (0.0..=1.0).contains(&u)
+                && last.is_none_or(|last| t > last)
+            {
+                last = Some(t);
+            }
+        }
+        last.map(|t| (from.0 + r.0 * t, from.1 + r.1 * t))
+    }
+
+    /// A point at the given fraction of the outline length
+    pub fn point_at(&self, fraction: f32) -> Point {
+        let Some(first) = self.segments.first() else {
+            return (self.bbox.x, self.bbox.y);
+        };
+        let length: f32 = self.segments.iter().map(|(a, b)| distance(*a, *b)).sum();
+        let mut remaining = length * fraction.clamp(0.0, 1.0);
+        for (a, b) in &self.segments {
+            let d = distance(*a, *b);
+            if remaining <= d {
+                if d == 0.0 {
+                    return *a;
+                }
+                let t = remaining / d;
+                return (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t);
+            }
+            remaining -= d;
+        }
+        self.segments.last().map(|(_, b)| *b).unwrap_or(first.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::FillAndStroke;
+
+    fn path(parts: Vec<DrawPathPart>) -> DrawPath {
+        DrawPath {
+            parts,
+            fill_and_stroke: FillAndStroke::default(),
+        }
+    }
+
+    #[test]
+    fn test_path_point_at() {
+        let g = ShapeGeometry::from_path(&path(vec![
+            DrawPathPart::Move { x: 10.0, y: 10.0 },
+            DrawPathPart::Line { x: 110.0, y: 10.0 },
+            DrawPathPart::Move { x: 0.0, y: 50.0 },
+            DrawPathPart::Line { x: 0.0, y: 150.0 },
+        ]));
+        assert_eq!(g.point_at(0.0), (10.0, 10.0));
+        assert_eq!(g.point_at(0.25), (60.0, 10.0));
+        assert_eq!(g.point_at(0.75), (0.0, 100.0));
+        assert_eq!(g.point_at(1.0), (0.0, 150.0));
+        assert_eq!(g.point_at(2.0), (0.0, 150.0));
+        let bbox = g.bbox();
+        assert_eq!(
+            (bbox.x, bbox.y, bbox.width, bbox.height),
+            (0.0, 10.0, 110.0, 140.0)
+        );
+    }
+
+    #[test]
+    fn test_curve_and_close() {
+        let g = ShapeGeometry::from_path(&path(vec![
+            DrawPathPart::Move { x: 0.0, y: 0.0 },
+            DrawPathPart::Quad {
+                x1: 50.0,
+                y1: 100.0,
+                x: 100.0,
+                y: 0.0,
+            },
+            DrawPathPart::Close,
+        ]));
+        let (x, y) = g.point_at(0.0);
+        assert_eq!((x, y), (0.0, 0.0));
+        // Apex of the symmetric curve
+        let curve_length: f32 = g.segments[..CURVE_SEGMENTS]
+            .iter()
+            .map(|(a, b)| distance(*a, *b))
+            .sum();
+        let total = curve_length + 100.0;
+        let (x, y) = g.point_at(curve_length / 2.0 / total);
+        assert!((x - 50.0).abs() < 0.01 && (y - 50.0).abs() < 0.01);
+        assert!((g.bbox().height - 50.0).abs() < 0.01);
+        assert_eq!(g.point_at(1.0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_rect_and_oval() {
+        let rect = Rectangle::new(100.0, 50.0, -100.0, 50.0);
+        let g = ShapeGeometry::from_rect(&rect);
+        assert_eq!(g.point_at(0.0), (0.0, 50.0));
+        assert_eq!(g.point_at(0.5), (100.0, 100.0));
+        assert_eq!(g.bbox().x, 0.0);
+
+        let g = ShapeGeometry::from_oval(&Rectangle::new(0.0, 0.0, 100.0, 50.0));
+        let (x, y) = g.point_at(0.0);
+        assert!((x - 100.0).abs() < 0.01 && (y - 25.0).abs() < 0.01);
+        let (x, y) = g.point_at(0.25);
+        assert!((x - 50.0).abs() < 0.5 && (y - 50.0).abs() < 0.5);
+        assert!((g.bbox().width - 100.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_exit_point() {
+        let g = ShapeGeometry::from_rect(&Rectangle::new(0.0, 0.0, 100.0, 50.0));
+        assert_eq!(
+            g.exit_point((50.0, 25.0), (250.0, 25.0)),
+            Some((100.0, 25.0))
+        );
+        assert_eq!(g.exit_point((50.0, 25.0), (50.0, -75.0)), Some((50.0, 0.0)));
+        assert_eq!(g.exit_point((50.0, 25.0), (60.0, 30.0)), None);
+    }
+}
@@ -1,8 +1,11 @@
-use crate::node::Node;
+use crate::node::{Node, NodeChild};
 use crate::render::context::RenderContext;
+use crate::render::geometry::ShapeGeometry;
+use crate::render::overflow::{NodeOverflow, detect_overflows};
 use crate::render::text::RenderedText;
+use crate::shapes::Shape;
 use crate::types::{LayoutExpr, Length, LengthOrAuto, LengthOrExpr};
-use crate::{NodeId, Page, Rectangle};
+use crate::{NodeId, Page, Rectangle, ShapeId};
 use itertools::Itertools;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -11,18 +14,30 @@ use taffy::{AlignItems, Display, JustifyContent, prelude as tf};
 #[derive(Debug)]
 pub(crate) struct LayoutData {
     pub(crate) rect: Rectangle,
+    pub(crate) border_radius: f32,
     pub(crate) text: Option<Arc<RenderedText>>,
+    /// Paddings and margins resolved by taffy
+    pub(crate) padding: tf::Rect<f32>,
+    pub(crate) margin: tf::Rect<f32>,
 }
 
 #[derive(Debug)]
 pub(crate) struct ComputedLayout {
     node_layout: HashMap<NodeId, LayoutData>,
+    shape_layout: HashMap<ShapeId, ShapeGeometry>,
+    overflows: Vec<NodeOverflow>,
+    page_width: f32,
+    page_height: f32,
 }
 
 impl ComputedLayout {
-    pub fn new(capacity: usize) -> Self {
+    pub fn new(capacity: usize, page_width: f32, page_height: f32) -> Self {
         ComputedLayout {
             node_layout: HashMap::with_capacity(capacity),
+            shape_layout: HashMap::new(),
+            overflows: Vec::new(),
+            page_width,
+            page_height,
         }
     }
 
@@ -30,15 +45,25 @@ impl ComputedLayout {
         self.node_layout.iter()
     }
 
+    pub fn overflows(&self) -> &[NodeOverflow] {
+        &self.overflows
+    }
+
     fn _layout(&self, node_id: NodeId) -> &LayoutData {
         self.node_layout(node_id)
-            .unwrap_or_else(|| panic!("Node {node_id:?} not found, ordering not correct?"))
+            .unwrap_or_else(|| panic!("Node {node_id:?} not found"))
     }
 
     fn _rect(&self, node_id: NodeId) -> &Rectangle {
         &self._layout(node_id).rect
     }
 
+    fn _shape(&self, shape_id: ShapeId) -> &ShapeGeometry {
+        self.shape_layout
+            .get(&shape_id)
+            .unwrap_or_else(|| panic!("Shape {shape_id:?} not found"))
+    }
+
     pub fn eval(&self, expr: &LayoutExpr, parent_node: NodeId) -> f32 {
         match expr {
             LayoutExpr::ConstValue { value } => *value,
@@ -46,6 +71,20 @@ impl ComputedLayout {
             LayoutExpr::Y { node_id } => self._rect(*node_id).y,
             LayoutExpr::Width { node_id, fraction } => self._rect(*node_id).width * fraction,
             LayoutExpr::Height { node_id, fraction } => self._rect(*node_id).height * fraction,
+            LayoutExpr::ShapeX { shape_id } => self._shape(*shape_id).bbox().x,
+            LayoutExpr::ShapeY { shape_id } => self._shape(*shape_id).bbox().y,
+            LayoutExpr::ShapeWidth { shape_id, fraction } => {
+                self._shape(*shape_id).bbox().width * fraction
+            }
+            LayoutExpr::ShapeHeight { shape_id, fraction } => {
+                self._shape(*shape_id).bbox().height * fraction
+            }
+            LayoutExpr::ShapePointX { shape_id, fraction } => {
+                self._shape(*shape_id).point_at(*fraction).0
+            }
+            LayoutExpr::ShapePointY { shape_id, fraction } => {
+                self._shape(*shape_id).point_at(*fraction).1
+            }
             LayoutExpr::Add { expressions } => {
                 self.eval(&expressions.0, parent_node) + self.eval(&expressions.1, parent_node)
             }
@@ -55,14 +94,43 @@ impl ComputedLayout {
             LayoutExpr::Mul { expressions } => {
                 self.eval(&expressions.0, parent_node) * self.eval(&expressions.1, parent_node)
             }
+            LayoutExpr::Div { expressions } => {
+                let divisor = self.eval(&expressions.1, parent_node);
+                if divisor == 0.0 {
+                    0.0
+                } else {
+                    self.eval(&expressions.0, parent_node) / divisor
+                }
+            }
+            LayoutExpr::Neg { expression } => -self.eval(expression, parent_node),
+            LayoutExpr::Abs { expression } => self.eval(expression, parent_node).abs(),
             LayoutExpr::Max { expressions } => expressions
                 .iter()
                 .map(|e| self.eval(e, parent_node))
-                .fold(0.0, |a, b| a.max(b)),
+                .reduce(f32::max)
+                .unwrap_or(0.0),
+            LayoutExpr::Min { expressions } => expressions
+                .iter()
+                .map(|e| self.eval(e, parent_node))
+                .reduce(f32::min)
+                .unwrap_or(0.0),
+            LayoutExpr::Clamp { expressions } => {
+                let (value, min, max) = expressions.as_ref();
+                self.eval(value, parent_node)
+                    .min(self.eval(max, parent_node))
+                    .max(self.eval(min, parent_node))
+            }
+            LayoutExpr::Lerp { expressions } => {
+                let (a, b, t) = expressions.as_ref();
+                let a = self.eval(a, parent_node);
+                a + (self.eval(b, parent_node) - a) * self.eval(t, parent_node)
+            }
             LayoutExpr::ParentX { shift } => self._rect(parent_node).x + shift,
             LayoutExpr::ParentY { shift } => self._rect(parent_node).y + shift,
             LayoutExpr::ParentWidth { fraction } => self._rect(parent_node).width * fraction,
             LayoutExpr::ParentHeight { fraction } => self._rect(parent_node).height * fraction,
+            LayoutExpr::PageWidth { fraction } => self.page_width * fraction,
+            LayoutExpr::PageHeight { fraction } => self.page_height * fraction,
             LayoutExpr::LineX { node_id, line_idx } => {
                 let layout = self._layout(*node_id);
                 layout
@@ -177,18 +245,51 @@ impl ComputedLayout {
     }
 }
 
-fn is_layout_managed(node: &Node, parent: Option<&Node>) -> bool {
+pub(crate) fn is_layout_managed(node: &Node, parent: Option<&Node>) -> bool {
     parent
-        .map(|p| {
-            node.main_axis_position(p.row).is_none()
-                && node
-                    .main_axis_size(p.row)
-                    .map(|v| !v.is_expr())
-                    .unwrap_or(true)
-        })
+        .map(|p| node.main_axis_position(p.row).is_none())
         .unwrap_or(true)
 }
 
+/// Maximal number of layout passes when sizes of nodes are defined by layout expressions
+const MAX_LAYOUT_PASSES: usize = 5;
+
+/// A node whose size is defined by a layout expression.
+/// Taffy gets the size resolved in the previous layout pass.
+struct ExprSizedNode<'a> {
+    node: &'a Node,
+    tf_node: tf::NodeId,
+    width: Option<f32>,
+    height: Option<f32>,
+}
+
+impl ExprSizedNode<'_> {
+    /// Sets sizes from the computed layout into taffy; returns true if any size has changed
+    fn update(&mut self, taffy: &mut tf::TaffyTree, layout: &ComputedLayout) -> bool {
+        let rect = layout._rect(self.node.node_id);
+        let mut style = taffy.style(self.tf_node).unwrap().clone();
+        let mut changed = false;
+        let mut update_size = |old: &mut Option<f32>, new: f32, dimension: &mut tf::Dimension| {
+            let new = new.max(0.0);
+            if old.is_none_or(|old| (old - new).abs() > 0.001) {
+                *old = Some(new);
+                *dimension = tf::Dimension::Length(new);
+                changed = true;
+            }
+        };
+        if self.node.width.as_ref().is_some_and(|w| w.is_expr()) {
+            update_size(&mut self.width, rect.width, &mut style.size.width);
+        }
+        if self.node.height.as_ref().is_some_and(|h| h.is_expr()) {
+            update_size(&mut self.height, rect.height, &mut style.size.height);
+        }
+        if changed {
+            taffy.set_style(self.tf_node, style).unwrap();
+        }
+        changed
+    }
+}
+
 impl From<Length> for tf::Dimension {
     fn from(value: Length) -> Self {
         match value {
@@ -221,6 +322,22 @@ impl From<LengthOrAuto> for tf::LengthPercentageAuto {
     }
 }
 
+impl From<LengthOrAuto> for tf::Dimension {
+    fn from(value: LengthOrAuto) -> Self {
+        match value {
+            LengthOrAuto::Length(length) => length.into(),
+            LengthOrAuto::Auto => tf::Dimension::Auto,
+        }
+    }
+}
+
+fn limit_size(width: Option<Length>, height: Option<Length>) -> tf::Size<tf::Dimension> {
+    tf::Size {
+        width: width.map(|v| v.into()).unwrap_or(tf::Dimension::Auto),
+        height: height.map(|v| v.into()).unwrap_or(tf::Dimension::Auto),
+    }
+}
+
 impl From<&LengthOrExpr> for tf::Dimension {
     fn from(value: &LengthOrExpr) -> Self {
         match value {
@@ -236,20 +353,14 @@ fn gather_taffy_layout<'b>(
     parent: Option<&Node>,
     taffy: &tf::TaffyTree,
     tf_node: tf::NodeId,
-    out: &mut HashMap<NodeId, (Option<NodeId>, &'b Node, Rectangle)>,
+    out: &mut HashMap<NodeId, (Option<NodeId>, &'b Node, tf::Layout)>,
 ) {
-    let layout_rect = taffy.layout(tf_node).unwrap();
     out.insert(
         node.node_id,
         (
             parent.map(|p| p.node_id),
             node,
-            Rectangle {
-                x: layout_rect.location.x,
-                y: layout_rect.location.y,
-                width: layout_rect.size.width,
-                height: layout_rect.size.height,
-            },
+            *taffy.layout(tf_node).unwrap(),
         ),
     );
     for (child, tf_child) in node.child_nodes().zip(taffy.children(tf_node).unwrap()) {
@@ -257,17 +368,27 @@ fn gather_taffy_layout<'b>(
     }
 }
 
-fn compute_layout_helper(
+fn compute_layout_helper<'a>(
     render_ctx: &mut RenderContext,
     taffy: &mut tf::TaffyTree,
-    node: &Node,
+    node: &'a Node,
     parent: Option<&Node>,
     node_id_order: &mut Vec<NodeId>,
+    expr_sized_nodes: &mut Vec<ExprSizedNode<'a>>,
 ) -> tf::NodeId {
     node_id_order.push(node.node_id);
     let tf_children: Vec<_> = node
         .child_nodes()
-        .map(|child| compute_layout_helper(render_ctx, taffy, child, Some(node), node_id_order))
+        .map(|child| {
+            compute_layout_helper(
+                render_ctx,
+                taffy,
+                child,
+                Some(node),
+                node_id_order,
+                expr_sized_nodes,
+            )
+        })
         .collect();
 
     let w = node.width.as_ref();
@@ -336,18 +457,7 @@ fn compute_layout_helper(
     dbg!(node.align_content.at_step(self.step));
     dbg!(node.justify_content.at_step(self.step));*/
 
-    let grid_template_rows = node
-        .grid_template_rows
-        .iter()
-        .map(|x| tf::TrackSizingFunction::Single(*x))
-        .collect_vec();
-    let grid_template_columns = node
-        .grid_template_columns
-        .iter()
-        .map(|x| tf::TrackSizingFunction::Single(*x))
-        .collect_vec();
-    let is_grid = !grid_template_rows.is_empty() || !grid_template_columns.is_empty();
-
+    let is_grid = node.is_grid();
     let style = tf::Style {
         display: if is_grid {
             Display::Grid
@@ -363,6 +473,9 @@ fn compute_layout_helper(
         flex_wrap: node.flex_wrap,
         flex_grow: node.flex_grow,
         flex_shrink: node.flex_shrink,
+        flex_basis: node.flex_basis.into(),
+        min_size: limit_size(node.min_width, node.min_height),
+        max_size: limit_size(node.max_width, node.max_height),
         align_items: node.align_items.or({
             if is_grid {
                 None
@@ -384,71 +497,465 @@ fn compute_layout_helper(
             width: node.column_gap.into(),
             height: node.row_gap.into(),
         },
-        grid_template_rows,
-        grid_template_columns,
+        grid_template_rows: node.grid_template_rows.clone(),
+        grid_template_columns: node.grid_template_columns.clone(),
+        grid_auto_rows: node.grid_auto_rows.clone(),
+        grid_auto_columns: node.grid_auto_columns.clone(),
+        grid_auto_flow: node.grid_auto_flow,
         grid_row: node.grid_row,
         grid_column: node.grid_column,
         ..Default::default()
     };
-    taffy.new_with_children(style, &tf_children).unwrap()
+    let tf_node = taffy.new_with_children(style, &tf_children).unwrap();
+    if w.is_some_and(|w| w.is_expr()) || h.is_some_and(|h| h.is_expr()) {
+        expr_sized_nodes.push(ExprSizedNode {
+            node,
+            tf_node,
+            width: None,
+            height: None,
+        });
+    }
+    tf_node
+}
+
+/// A value of a node layout that may be defined by a layout expression
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum LayoutValue {
+    X,
+    Y,
+    Width,
+    Height,
+}
+
+impl LayoutValue {
+    fn name(self) -> &'static str {
+        match self {
+            LayoutValue::X => "x",
+            LayoutValue::Y => "y",
+            LayoutValue::Width => "width",
+            LayoutValue::Height => "height",
+        }
+    }
 }
 
-pub fn compute_page_layout(render_ctx: &mut RenderContext, page: &Page) -> ComputedLayout {
+/// An item of a page layout that is evaluated by the resolver
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum LayoutItem {
+    Node(NodeId, LayoutValue),
+    Shape(ShapeId),
+}
+
+/// A value read by an expression.
+/// `Node(_, None)` means that only a text layout of the node is read; it is known before expressions are resolved.
+#[derive(Debug, Copy, Clone)]
+enum Dependency {
+    Node(NodeId, Option<LayoutValue>),
+    Shape(ShapeId),
+}
+
+fn expr_dependencies(expr: &LayoutExpr, parent_id: NodeId, out: &mut Vec<Dependency>) {
+    let mut node =
+        |node_id: NodeId, value: Option<LayoutValue>| out.push(Dependency::Node(node_id, value));
+    match expr {
+        LayoutExpr::ConstValue { .. }
+        | LayoutExpr::PageWidth { .. }
+        | LayoutExpr::PageHeight { .. } => {}
+        LayoutExpr::ParentX { .. } => node(parent_id, Some(LayoutValue::X)),
+        LayoutExpr::ParentY { .. } => node(parent_id, Some(LayoutValue::Y)),
+        LayoutExpr::ParentWidth { .. } => node(parent_id, Some(LayoutValue::Width)),
+        LayoutExpr::ParentHeight { .. } => node(parent_id, Some(LayoutValue::Height)),
+        LayoutExpr::X { node_id }
+        | LayoutExpr::LineX { node_id, .. }
+        | LayoutExpr::InlineX { node_id, .. } => node(*node_id, Some(LayoutValue::X)),
+        LayoutExpr::Y { node_id }
+        | LayoutExpr::LineY { node_id, .. }
+        | LayoutExpr::InlineY { node_id, .. } => node(*node_id, Some(LayoutValue::Y)),
+        LayoutExpr::Width { node_id, .. } => node(*node_id, Some(LayoutValue::Width)),
+        LayoutExpr::Height { node_id, .. } => node(*node_id, Some(LayoutValue::Height)),
+        LayoutExpr::LineWidth { node_id, .. }
+        | LayoutExpr::LineHeight { node_id, .. }
+        | LayoutExpr::InlineWidth { node_id, .. }
+        | LayoutExpr::InlineHeight { node_id, .. } => node(*node_id, None),
+        LayoutExpr::ShapeX { shape_id }
+        | LayoutExpr::ShapeY { shape_id }
+        | LayoutExpr::ShapeWidth { shape_id, .. }
+        | LayoutExpr::ShapeHeight { shape_id, .. }
+        | LayoutExpr::ShapePointX { shape_id, .. }
+        | LayoutExpr::ShapePointY { shape_id, .. } => out.push(Dependency::Shape(*shape_id)),
+        LayoutExpr::Add { expressions }
+        | LayoutExpr::Sub { expressions }
+        | LayoutExpr::Mul { expressions }
+        | LayoutExpr::Div { expressions } => {
+            expr_dependencies(&expressions.0, parent_id, out);
+            expr_dependencies(&expressions.1, parent_id, out);
+        }
+        LayoutExpr::Neg { expression } | LayoutExpr::Abs { expression } => {
+            expr_dependencies(expression, parent_id, out);
+        }
+        LayoutExpr::Max { expressions } | LayoutExpr::Min { expressions } => {
+            for e in expressions {
+                expr_dependencies(e, parent_id, out);
+            }
+        }
+        LayoutExpr::Clamp { expressions } | LayoutExpr::Lerp { expressions } => {
+            expr_dependencies(&expressions.0, parent_id, out);
+            expr_dependencies(&expressions.1, parent_id, out);
+            expr_dependencies(&expressions.2, parent_id, out);
+        }
+    }
+}
+
+fn shape_dependencies(shape: &Shape, parent_id: NodeId) -> Vec<Dependency> {
+    let mut dependencies = Vec::new();
+    if let Shape::Connector(connector) = shape {
+        for node_id in [connector.start, connector.end] {
+            for value in [
+                LayoutValue::X,
+                LayoutValue::Y,
+                LayoutValue::Width,
+                LayoutValue::Height,
+            ] {
+                dependencies.push(Dependency::Node(node_id, Some(value)));
+            }
+        }
+    }
+    for expr in shape.layout_exprs() {
+        expr_dependencies(expr, parent_id, &mut dependencies);
+    }
+    dependencies
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum ResolveState {
+    InProgress,
+    Done,
+}
+
+/// Evaluates layout values and shapes in the order of dependencies between them
+struct LayoutResolver<'a> {
+    entries: HashMap<NodeId, (Option<NodeId>, &'a Node, tf::Layout)>,
+    /// Shapes with ids and their parent nodes; the first occurrence of an id on the page is used
+    shapes: HashMap<ShapeId, (&'a Shape, NodeId)>,
+    states: HashMap<LayoutItem, ResolveState>,
+    stack: Vec<LayoutItem>,
+    layout: ComputedLayout,
+    /// All pages of the document
+    pages: &'a [Page],
+}
+
+impl LayoutResolver<'_> {
+    fn label(&self, item: LayoutItem) -> String {
+        match item {
+            LayoutItem::Node(node_id, value) => {
+                format!("{} of {}", value.name(), self.entries[&node_id].1.label())
+            }
+            LayoutItem::Shape(shape_id) => {
+                format!(
+                    "shape in {}",
+                    self.entries[&self.shapes[&shape_id].1].1.label()
+                )
+            }
+        }
+    }
+
+    /// Beginning of an error message about an expression of the item
+    fn context(&self, item: LayoutItem) -> String {
+        match item {
+            LayoutItem::Node(..) => format!("Layout expression for {}", self.label(item)),
+            LayoutItem::Shape(shape_id) => self.shape_context(self.shapes[&shape_id].1),
+        }
+    }
+
+    fn shape_context(&self, parent_id: NodeId) -> String {
+        format!("A shape in {}", self.entries[&parent_id].1.label())
+    }
+
+    fn dependencies(&self, item: LayoutItem) -> Vec<Dependency> {
+        let mut dependencies = Vec::new();
+        match item {
+            LayoutItem::Node(node_id, value) => {
+                let (parent, node, _) = &self.entries[&node_id];
+                let parent_id = parent.unwrap_or(NodeId::new(0));
+                if let Some(expr) = node_value_expr(node, value) {
+                    expr_dependencies(expr, parent_id, &mut dependencies);
+                } else if parent.is_some() && matches!(value, LayoutValue::X | LayoutValue::Y) {
+                    // Positions computed by taffy are relative to the parent
+                    dependencies.push(Dependency::Node(parent_id, Some(value)));
+                }
+            }
+            LayoutItem::Shape(shape_id) => {
+                let (shape, parent_id) = self.shapes[&shape_id];
+                dependencies = shape_dependencies(shape, parent_id);
+            }
+        }
+        dependencies
+    }
+
+    fn other_page_node_label(&self, node_id: NodeId) -> String {
+        self.pages
+            .iter()
+            .find_map(|page| find_node(&page.node, node_id))
+            .map(|node| node.label())
+            .unwrap_or_else(|| format!("box #{}", node_id.as_usize()))
+    }
+
+    fn other_page_shape_label(&self, shape_id: ShapeId) -> String {
+        self.pages
+            .iter()
+            .find_map(|page| find_shape_parent(&page.node, shape_id))
+            .map(|node| format!("a shape in {}", node.label()))
+            .unwrap_or_else(|| format!("shape #{}", shape_id.as_usize()))
+    }
+
+    fn resolve_dependencies(
+        &mut self,
+        dependencies: Vec<Dependency>,
+        context: impl Fn(&Self) -> String,
+    ) -> crate::Result<()> {
+        for dependency in dependencies {
+            match dependency {
+                Dependency::Node(node_id, value) => {
+                    if !self.entries.contains_key(&node_id) {
+                        return Err(crate::Error::generic_err(format!(
+                            "{} refers to {} that is not on the same page",
+                            context(self),
+                            self.other_page_node_label(node_id)
+                        )));
+                    }
+                    if let Some(value) = value {
+                        self.resolve(LayoutItem::Node(node_id, value))?;
+                    }
+                }
+                Dependency::Shape(shape_id) => {
+                    if !self.shapes.contains_key(&shape_id) {
+                        return Err(crate::Error::generic_err(format!(
+                            "{} refers to {} that is not on the same page",
+                            context(self),
+                            self.other_page_shape_label(shape_id)
+                        )));
+                    }
+                    self.resolve(LayoutItem::Shape(shape_id))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve(&mut self, item: LayoutItem) -> crate::Result<()> {
+        match self.states.get(&item) {
+            Some(ResolveState::Done) => return Ok(()),
+            Some(ResolveState::InProgress) => {
+                let start = self.stack.iter().position(|i| *i == item).unwrap();
+                let cycle = self.stack[start..]
+                    .iter()
+                    .chain(std::iter::once(&item))
+                    .map(|i| self.label(*i))
+                    .join(" -> ");
+                return Err(crate::Error::generic_err(format!(
+                    "Cycle in layout expressions: {cycle}"
+                )));
+            }
+            None => {}
+        }
+        let dependencies = self.dependencies(item);
+        self.states.insert(item, ResolveState::InProgress);
+        self.stack.push(item);
+        self.resolve_dependencies(dependencies, |r| r.context(item))?;
+        self.stack.pop();
+        self.states.insert(item, ResolveState::Done);
+        match item {
+            LayoutItem::Node(node_id, value) => self.eval_node_value(node_id, value),
+            LayoutItem::Shape(shape_id) => self.eval_shape(shape_id),
+        }
+        Ok(())
+    }
+
+    fn eval_node_value(&mut self, node_id: NodeId, value: LayoutValue) {
+        let (parent, node, taffy_layout) = &self.entries[&node_id];
+        let result = if let Some(expr) = node_value_expr(node, value) {
+            self.layout.eval(expr, parent.unwrap_or(NodeId::new(0)))
+        } else {
+            let parent_rect = parent.map(|parent_id| self.layout._rect(parent_id));
+            match value {
+                LayoutValue::X => parent_rect.map(|r| r.x).unwrap_or(0.0) + taffy_layout.location.x,
+                LayoutValue::Y => parent_rect.map(|r| r.y).unwrap_or(0.0) + taffy_layout.location.y,
+                LayoutValue::Width => taffy_layout.size.width,
+                LayoutValue::Height => taffy_layout.size.height,
+            }
+        };
+        let rect = &mut self.layout.node_layout.get_mut(&node_id).unwrap().rect;
+        match value {
+            LayoutValue::X => rect.x = result,
+            LayoutValue::Y => rect.y = result,
+            LayoutValue::Width => rect.width = result,
+            LayoutValue::Height => rect.height = result,
+        }
+    }
+
+    fn eval_shape(&mut self, shape_id: ShapeId) {
+        let (shape, parent_id) = self.shapes[&shape_id];
+        let geometry = match shape {
+            Shape::Rect(rect) => {
+                ShapeGeometry::from_rect(&rect.eval(&self.layout, parent_id).rectangle)
+            }
+            Shape::Oval(rect) => {
+                ShapeGeometry::from_oval(&rect.eval(&self.layout, parent_id).rectangle)
+            }
+            Shape::Path(path) => ShapeGeometry::from_path(&path.build(&self.layout, parent_id)),
+            Shape::Connector(connector) => ShapeGeometry::from_path(&connector.build(&self.layout)),
+        };
+        self.layout.shape_layout.insert(shape_id, geometry);
+    }
+
+    /// Shapes are evaluated when the page is drawn; only their dependencies are resolved.
+    /// Geometries are computed only for shapes referenced by layout expressions,
+    /// as they are resolved together with the expressions that refer to them.
+    fn resolve_shapes(&mut self, node_id_order: &[NodeId]) -> crate::Result<()> {
+        for node_id in node_id_order {
+            let node = self.entries[node_id].1;
+            for child in &node.children {
+                if let NodeChild::Shape(shape) = child {
+                    self.resolve_dependencies(shape_dependencies(shape, node.node_id), |r| {
+                        r.shape_context(node.node_id)
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn node_value_expr(node: &Node, value: LayoutValue) -> Option<&LayoutExpr> {
+    match value {
+        LayoutValue::X => node.x.as_ref(),
+        LayoutValue::Y => node.y.as_ref(),
+        LayoutValue::Width => node.width.as_ref().and_then(|v| v.as_expr()),
+        LayoutValue::Height => node.height.as_ref().and_then(|v| v.as_expr()),
+    }
+}
+
+/// Computes the layout of a page.
+/// Nodes with sizes defined by layout expressions are laid out by taffy as boxes with an automatic size at first;
+/// then the layout is recomputed with sizes resolved in the previous pass until sizes are stable.
+pub fn compute_page_layout(
+    render_ctx: &mut RenderContext,
+    page: &Page,
+) -> crate::Result<ComputedLayout> {
     let mut taffy = tf::TaffyTree::new();
     taffy.disable_rounding();
     let mut node_id_order = Vec::with_capacity(16);
-    let tf_node =
-        compute_layout_helper(render_ctx, &mut taffy, &page.node, None, &mut node_id_order);
+    let mut expr_sized_nodes = Vec::new();
+    let tf_node = compute_layout_helper(
+        render_ctx,
+        &mut taffy,
+        &page.node,
+        None,
+        &mut node_id_order,
+        &mut expr_sized_nodes,
+    );
     let size = tf::Size {
         width: tf::AvailableSpace::Definite(page.width),
         height: tf::AvailableSpace::Definite(page.height),
     };
-    taffy.compute_layout(tf_node, size).unwrap();
+    let mut pass = 1;
+    let mut layout = loop {
+        taffy.compute_layout(tf_node, size).unwrap();
+        let layout = resolve_layout(render_ctx, page, &taffy, tf_node, &node_id_order)?;
+        let mut changed = false;
+        for node in &mut expr_sized_nodes {
+            changed |= node.update(&mut taffy, &layout);
+        }
+        if !changed {
+            break layout;
+        }
+        if pass == MAX_LAYOUT_PASSES {
+            log::warn!(
+                "Sizes defined by layout expressions are not stable after {pass} layout passes"
+            );
+            break layout;
+        }
+        pass += 1;
+    };
+    layout.overflows = detect_overflows(render_ctx, page, &layout);
+    Ok(layout)
+}
+
+fn find_node(node: &Node, node_id: NodeId) -> Option<&Node> {
+    if node.node_id == node_id {
+        return Some(node);
+    }
+    node.child_nodes()
+        .find_map(|child| find_node(child, node_id))
+}
+
+/// Finds the node that draws the shape
+fn find_shape_parent(node: &Node, shape_id: ShapeId) -> Option<&Node> {
+    let has_shape = node.children.iter().any(
+        |child| matches!(child, NodeChild::Shape(shape) if shape.shape_id() == Some(shape_id)),
+    );
+    if has_shape {
+        return Some(node);
+    }
+    node.child_nodes()
+        .find_map(|child| find_shape_parent(child, shape_id))
+}
+
+fn resolve_layout(
+    render_ctx: &mut RenderContext,
+    page: &Page,
+    taffy: &tf::TaffyTree,
+    tf_node: tf::NodeId,
+    node_id_order: &[NodeId],
+) -> crate::Result<ComputedLayout> {
     let mut node_entries = HashMap::with_capacity(node_id_order.len());
-    gather_taffy_layout(&page.node, None, &taffy, tf_node, &mut node_entries);
-    let mut result = ComputedLayout::new(node_id_order.len());
-    for node_id in node_id_order {
-        let (parent_id, node, rect) = node_entries.get(&node_id).unwrap();
-        let (parent_x, parent_y) = parent_id
-            .map(|node_id| {
-                let r = &result.node_layout(node_id).unwrap().rect;
-                (r.x, r.y)
-            })
-            .unwrap_or((0.0, 0.0));
-        let parent_id = parent_id.unwrap_or(NodeId::new(0));
-        result.set_layout(
-            node.node_id,
+    gather_taffy_layout(&page.node, None, taffy, tf_node, &mut node_entries);
+    let mut layout = ComputedLayout::new(node_id_order.len(), page.width, page.height);
+    for (node_id, (_, node, taffy_layout)) in &node_entries {
+        layout.set_layout(
+            *node_id,
             LayoutData {
-                rect: Rectangle {
-                    x: node
-                        .x
-                        .as_ref()
-                        .map(|x| result.eval(x, parent_id))
-                        .unwrap_or_else(|| parent_x + rect.x),
-                    y: node
-                        .y
-                        .as_ref()
-                        .map(|y| result.eval(y, parent_id))
-                        .unwrap_or_else(|| parent_y + rect.y),
-                    width: node
-                        .width
-                        .as_ref()
-                        .and_then(|v| v.as_expr().map(|v| result.eval(v, parent_id)))
-                        .unwrap_or(rect.width),
-                    height: node
-                        .height
-                        .as_ref()
-                        .and_then(|v| v.as_expr().map(|v| result.eval(v, parent_id)))
-                        .unwrap_or(rect.height),
-                },
+                rect: Rectangle::new(0.0, 0.0, 0.0, 0.0),
+                border_radius: node.border_radius,
                 text: node.content.and_then(|content_id| {
                     render_ctx
                         .content_map
                         .get(&content_id)
                         .and_then(|c| c.as_text().cloned())
                 }),
+                padding: taffy_layout.padding,
+                margin: taffy_layout.margin,
             },
         );
     }
-    result
+    let mut shapes = HashMap::new();
+    for node_id in node_id_order {
+        for child in &node_entries[node_id].1.children {
+            if let NodeChild::Shape(shape) = child
+                && let Some(shape_id) = shape.shape_id()
+            {
+                shapes.entry(shape_id).or_insert((shape, *node_id));
+            }
+        }
+    }
+    let mut resolver = LayoutResolver {
+        entries: node_entries,
+        shapes,
+        states: HashMap::with_capacity(node_id_order.len() * 4),
+        stack: Vec::new(),
+        layout,
+        pages: render_ctx.pages,
+    };
+    for node_id in node_id_order {
+        let node_id = *node_id;
+        for value in [
+            LayoutValue::Width,
+            LayoutValue::Height,
+            LayoutValue::X,
+            LayoutValue::Y,
+        ] {
+            resolver.resolve(LayoutItem::Node(node_id, value))?;
+        }
+    }
+    resolver.resolve_shapes(node_id_order)?;
+    Ok(resolver.layout)
 }
@@ -0,0 +1,978 @@
+use crate::render::draw::DrawPath;
+use crate::render::text::{
+    GlyphImage, RenderedText, TextBrush, TextContext, draw_glyphs, rect_path,
+};
+use crate::text::{Math, TextStyle};
+use crate::textutils::math::{AtomClass, MathNode, MathVariant, parse_math};
+use crate::{Color, Rectangle};
+use parley::swash::NormalizedCoord;
+use parley::{Font, FontStack, FontStyle, FontWeight, Layout, PositionedLayoutItem, StyleProperty};
+use skrifa::instance::{LocationRef, Size};
+use skrifa::metrics::GlyphMetrics;
+use skrifa::raw::FontRef as ReadFontsRef;
+use skrifa::raw::types::Tag;
+use skrifa::{GlyphId, MetadataProvider};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Font settings of a formula, taken from a text style
+#[derive(Debug, Clone)]
+pub(crate) struct MathFont<'a> {
+    pub family: &'a str,
+    pub size: f32,
+    pub weight: f32,
+}
+
+impl<'a> MathFont<'a> {
+    pub fn new(style: &'a TextStyle) -> Self {
+        MathFont {
+            family: style
+                .font
+                .as_ref()
+                .map(|x| x.as_str())
+                .unwrap_or("sans-serif"),
+            size: style.size.map(|x| x.get()).unwrap_or(16.0),
+            weight: style.weight.unwrap_or(400) as f32,
+        }
+    }
+}
+
+/// Constants of a math font in em units (percentages as fractions).
+/// Default values are used when the font has no MATH table;
+/// they follow Latin Modern Math.
+#[derive(Debug, Clone)]
+struct MathConstants {
+    script_scale: f32,
+    script_script_scale: f32,
+    display_operator_min_height: f32,
+    axis_height: f32,
+    subscript_shift_down: f32,
+    subscript_top_max: f32,
+    subscript_baseline_drop_min: f32,
+    superscript_shift_up: f32,
+    superscript_shift_up_cramped: f32,
+    superscript_bottom_min: f32,
+    superscript_baseline_drop_max: f32,
+    sub_superscript_gap_min: f32,
+    space_after_script: f32,
+    upper_limit_gap_min: f32,
+    upper_limit_baseline_rise_min: f32,
+    lower_limit_gap_min: f32,
+    lower_limit_baseline_drop_min: f32,
+    fraction_numerator_shift_up: f32,
+    fraction_numerator_display_shift_up: f32,
+    fraction_denominator_shift_down: f32,
+    fraction_denominator_display_shift_down: f32,
+    fraction_numerator_gap_min: f32,
+    fraction_numerator_display_gap_min: f32,
+    fraction_rule_thickness: f32,
+    fraction_denominator_gap_min: f32,
+    fraction_denominator_display_gap_min: f32,
+    overbar_vertical_gap: f32,
+    overbar_rule_thickness: f32,
+    overbar_extra_ascender: f32,
+    underbar_vertical_gap: f32,
+    underbar_rule_thickness: f32,
+    underbar_extra_descender: f32,
+    radical_vertical_gap: f32,
+    radical_display_vertical_gap: f32,
+    radical_rule_thickness: f32,
+    radical_extra_ascender: f32,
+    radical_kern_before_degree: f32,
+    radical_kern_after_degree: f32,
+    radical_degree_bottom_raise: f32,
+}
+
+impl Default for MathConstants {
+    fn default() -> Self {
+        MathConstants {
+            script_scale: 0.7,
+            script_script_scale: 0.5,
+            display_operator_min_height: 1.3,
+            axis_height: 0.25,
+            subscript_shift_down: 0.247,
+            subscript_top_max: 0.344,
+            subscript_baseline_drop_min: 0.2,
+            superscript_shift_up: 0.363,
+            superscript_shift_up_cramped: 0.289,
+            superscript_bottom_min: 0.108,
+            superscript_baseline_drop_max: 0.25,
+            sub_superscript_gap_min: 0.16,
+            space_after_script: 0.056,
+            upper_limit_gap_min: 0.2,
+            upper_limit_baseline_rise_min: 0.111,
+            lower_limit_gap_min: 0.167,
+            lower_limit_baseline_drop_min: 0.6,
+            fraction_numerator_shift_up: 0.394,
+            fraction_numerator_display_shift_up: 0.677,
+            fraction_denominator_shift_down: 0.345,
+            fraction_denominator_display_shift_down: 0.686,
+            fraction_numerator_gap_min: 0.04,
+            fraction_numerator_display_gap_min: 0.12,
+            fraction_rule_thickness: 0.04,
+            fraction_denominator_gap_min: 0.04,
+            fraction_denominator_display_gap_min: 0.12,
+            overbar_vertical_gap: 0.12,
+            overbar_rule_thickness: 0.04,
+            overbar_extra_ascender: 0.04,
+            underbar_vertical_gap: 0.12,
+            underbar_rule_thickness: 0.04,
+            underbar_extra_descender: 0.04,
+            radical_vertical_gap: 0.05,
+            radical_display_vertical_gap: 0.148,
+            radical_rule_thickness: 0.04,
+            radical_extra_ascender: 0.04,
+            radical_kern_before_degree: 0.278,
+            radical_kern_after_degree: -0.556,
+            radical_degree_bottom_raise: 0.6,
+        }
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn read_i16(data: &[u8], offset: usize) -> Option<i16> {
+    read_u16(data, offset).map(|x| x as i16)
+}
+
+impl MathConstants {
+    /// Reads the MathConstants subtable of a MATH table.
+    /// Some text fonts contain an incomplete table, so zero values are replaced by defaults.
+    fn from_math_table(math: &[u8], units_per_em: f32) -> Option<Self> {
+        let base = read_u16(math, 4)? as usize;
+        let d = MathConstants::default();
+        let or_default = |v: f32, default: f32| if v == 0.0 { default } else { v };
+        let percent = |offset: usize, default: f32| {
+            read_i16(math, base + offset).map(|v| or_default(v as f32 / 100.0, default))
+        };
+        let unsigned = |offset: usize, default: f32| {
+            read_u16(math, base + offset).map(|v| or_default(v as f32 / units_per_em, default))
+        };
+        // MathValueRecord is a value followed by an offset to a device table
+        let value = |offset: usize, default: f32| {
+            read_i16(math, base + offset).map(|v| or_default(v as f32 / units_per_em, default))
+        };
+        Some(MathConstants {
+            script_scale: percent(0, d.script_scale)?,
+            script_script_scale: percent(2, d.script_script_scale)?,
+            display_operator_min_height: unsigned(6, d.display_operator_min_height)?,
+            axis_height: value(12, d.axis_height)?,
+            subscript_shift_down: value(24, d.subscript_shift_down)?,
+            subscript_top_max: value(28, d.subscript_top_max)?,
+            subscript_baseline_drop_min: value(32, d.subscript_baseline_drop_min)?,
+            superscript_shift_up: value(36, d.superscript_shift_up)?,
+            superscript_shift_up_cramped: value(40, d.superscript_shift_up_cramped)?,
+            superscript_bottom_min: value(44, d.superscript_bottom_min)?,
+            superscript_baseline_drop_max: value(48, d.superscript_baseline_drop_max)?,
+            sub_superscript_gap_min: value(52, d.sub_superscript_gap_min)?,
+            space_after_script: value(60, d.space_after_script)?,
+            upper_limit_gap_min: value(64, d.upper_limit_gap_min)?,
+            upper_limit_baseline_rise_min: value(68, d.upper_limit_baseline_rise_min)?,
+            lower_limit_gap_min: value(72, d.lower_limit_gap_min)?,
+            lower_limit_baseline_drop_min: value(76, d.lower_limit_baseline_drop_min)?,
+            fraction_numerator_shift_up: value(120, d.fraction_numerator_shift_up)?,
+            fraction_numerator_display_shift_up: value(124, d.fraction_numerator_display_shift_up)?,
+            fraction_denominator_shift_down: value(128, d.fraction_denominator_shift_down)?,
+            fraction_denominator_display_shift_down: value(
+                132,
+                d.fraction_denominator_display_shift_down,
+            )?,
+            fraction_numerator_gap_min: value(136, d.fraction_numerator_gap_min)?,
+            fraction_numerator_display_gap_min: value(140, d.fraction_numerator_display_gap_min)?,
+            fraction_rule_thickness: value(144, d.fraction_rule_thickness)?,
+            fraction_denominator_gap_min: value(148, d.fraction_denominator_gap_min)?,
+            fraction_denominator_display_gap_min: value(
+                152,
+                d.fraction_denominator_display_gap_min,
+            )?,
+            overbar_vertical_gap: value(164, d.overbar_vertical_gap)?,
+            overbar_rule_thickness: value(168, d.overbar_rule_thickness)?,
+            overbar_extra_ascender: value(172, d.overbar_extra_ascender)?,
+            underbar_vertical_gap: value(176, d.underbar_vertical_gap)?,
+            underbar_rule_thickness: value(180, d.underbar_rule_thickness)?,
+            underbar_extra_descender: value(184, d.underbar_extra_descender)?,
+            radical_vertical_gap: value(188, d.radical_vertical_gap)?,
+            radical_display_vertical_gap: value(192, d.radical_display_vertical_gap)?,
+            radical_rule_thickness: value(196, d.radical_rule_thickness)?,
+            radical_extra_ascender: value(200, d.radical_extra_ascender)?,
+            radical_kern_before_degree: value(204, d.radical_kern_before_degree)?,
+            radical_kern_after_degree: value(208, d.radical_kern_after_degree)?,
+            radical_degree_bottom_raise: percent(212, d.radical_degree_bottom_raise)?,
+        })
+    }
+}
+
+/// Returns the index of a glyph in an OpenType coverage table
+fn coverage_index(data: &[u8], offset: usize, glyph: u16) -> Option<usize> {
+    let count = read_u16(data, offset + 2)? as usize;
+    match read_u16(data, offset)? {
+        1 => (0..count).find(|i| read_u16(data, offset + 4 + 2 * i) == Some(glyph)),
+        2 => (0..count).find_map(|i| {
+            let record = offset + 4 + 6 * i;
+            let start = read_u16(data, record)?;
+            let end = read_u16(data, record + 2)?;
+            (start..=end).contains(&glyph).then(|| {
+                read_u16(data, record + 4).map(|index| index as usize + (glyph - start) as usize)
+            })?
+        }),
+        _ => None,
+    }
+}
+
+/// Returns vertical variants of a glyph from a MATH table as (glyph, height in font units)
+fn vertical_variants(math: &[u8], glyph: u16) -> Vec<(u16, u16)> {
+    let read = || -> Option<Vec<(u16, u16)>> {
+        let variants = read_u16(math, 8)? as usize;
+        if variants == 0 {
+            return None;
+        }
+        let coverage = variants + read_u16(math, variants + 2)? as usize;
+        let index = coverage_index(math, coverage, glyph)?;
+        if index >= read_u16(math, variants + 6)? as usize {
+            return None;
+        }
+        let construction = variants + read_u16(math, variants + 10 + 2 * index)? as usize;
+        let count = read_u16(math, construction + 2)? as usize;
+        (0..count)
+            .map(|i| {
+                let record = construction + 4 + 4 * i;
+                Some((read_u16(math, record)?, read_u16(math, record + 2)?))
+            })
+            .collect()
+    };
+    read().unwrap_or_default()
+}
+
+/// Characters of Mathematical Alphanumeric Symbols used by math fonts
+/// instead of italic and bold faces
+fn math_alphanumeric(c: char, variant: MathVariant) -> char {
+    let offset =
+        |base: u32, first: char| char::from_u32(base + c as u32 - first as u32).unwrap_or(c);
+    match (variant, c) {
+        (MathVariant::Italic, 'h') => 'ℎ',
+        (MathVariant::Italic, 'a'..='z') => offset(0x1D44E, 'a'),
+        (MathVariant::Italic, 'A'..='Z') => offset(0x1D434, 'A'),
+        (MathVariant::Italic, 'α'..='ω') => offset(0x1D6FC, 'α'),
+        (MathVariant::Bold, 'a'..='z') => offset(0x1D41A, 'a'),
+        (MathVariant::Bold, 'A'..='Z') => offset(0x1D400, 'A'),
+        (MathVariant::Bold, '0'..='9') => offset(0x1D7CE, '0'),
+        _ => c,
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MathRun {
+    font: Font,
+    size: f32,
+    coords: Vec<NormalizedCoord>,
+    /// Glyphs with their pen positions
+    glyphs: Vec<(f32, f32, GlyphId)>,
+    /// Vertical stretch of the glyphs around their baseline
+    y_scale: f32,
+}
+
+impl MathRun {
+    fn with_font_ref<T>(&self, f: impl FnOnce(&ReadFontsRef, LocationRef) -> T) -> T {
+        let font_ref = ReadFontsRef::from_index(self.font.data.as_ref(), self.font.index).unwrap();
+        let coords: Vec<_> = self
+            .coords
+            .iter()
+            .map(|c| skrifa::instance::NormalizedCoord::from_bits(*c))
+            .collect();
+        f(&font_ref, LocationRef::new(&coords))
+    }
+
+    /// Returns the extent of glyph ink above and below the baseline
+    fn ink_extents(&self) -> Option<(f32, f32)> {
+        self.with_font_ref(|font_ref, location| {
+            let metrics = GlyphMetrics::new(font_ref, Size::new(self.size), location);
+            self.glyphs
+                .iter()
+                .filter_map(|(_, y, glyph)| {
+                    let bounds = metrics.bounds(*glyph)?;
+                    Some((
+                        bounds.y_max * self.y_scale - y,
+                        y - bounds.y_min * self.y_scale,
+                    ))
+                })
+                .reduce(|(a1, d1), (a2, d2)| (a1.max(a2), d1.max(d2)))
+        })
+    }
+}
+
+/// Laid out part of a formula; positions are relative to the start of its baseline
+#[derive(Debug, Default, Clone)]
+pub(crate) struct MathBox {
+    pub width: f32,
+    /// Height above the baseline
+    pub ascent: f32,
+    /// Depth below the baseline
+    pub descent: f32,
+    runs: Vec<MathRun>,
+    rules: Vec<Rectangle>,
+}
+
+impl MathBox {
+    fn space(width: f32) -> Self {
+        MathBox {
+            width,
+            ..Default::default()
+        }
+    }
+
+    /// Moves the content right by `dx` and down by `dy`
+    fn shifted(mut self, dx: f32, dy: f32) -> Self {
+        for run in &mut self.runs {
+            for (x, y, _) in &mut run.glyphs {
+                *x += dx;
+                *y += dy;
+            }
+        }
+        for rule in &mut self.rules {
+            rule.x += dx;
+            rule.y += dy;
+        }
+        self.ascent -= dy;
+        self.descent += dy;
+        self
+    }
+
+    /// Places other box at the given position (moved right by `dx` and down by `dy`)
+    fn append(&mut self, other: MathBox, dx: f32, dy: f32) {
+        let other = other.shifted(dx, dy);
+        self.width = self.width.max(dx + other.width);
+        self.ascent = self.ascent.max(other.ascent);
+        self.descent = self.descent.max(other.descent);
+        self.runs.extend(other.runs);
+        self.rules.extend(other.rules);
+    }
+
+    /// Adds a horizontal rule; `top` is measured upwards from the baseline
+    fn push_rule(&mut self, x: f32, top: f32, width: f32, thickness: f32) {
+        self.rules.push(Rectangle::new(x, -top, width, thickness));
+        self.ascent = self.ascent.max(top);
+        self.descent = self.descent.max(thickness - top);
+    }
+
+    /// Moves the box vertically, so the middle of its ink is placed on the given height
+    fn centered_on(self, height: f32) -> Self {
+        let center = (self.ascent - self.descent) / 2.0;
+        self.shifted(0.0, center - height)
+    }
+
+    pub fn render(
+        &self,
+        x: f32,
+        baseline: f32,
+        color: Color,
+        out: &mut Vec<DrawPath>,
+        images: &mut Vec<GlyphImage>,
+    ) {
+        for run in &self.runs {
+            let glyphs = run
+                .glyphs
+                .iter()
+                .map(|(gx, gy, glyph)| (x + gx, baseline + gy, *glyph));
+            if run.y_scale == 1.0 {
+                draw_glyphs(&run.font, run.size, &run.coords, color, glyphs, out, images);
+                continue;
+            }
+            // Stretched glyphs are always placed in their own runs
+            let mut paths = Vec::new();
+            draw_glyphs(
+                &run.font,
+                run.size,
+                &run.coords,
+                color,
+                glyphs,
+                &mut paths,
+                images,
+            );
+            let pivot = baseline + run.glyphs.first().map(|g| g.1).unwrap_or(0.0);
+            for mut path in paths {
+                path.transform_points(|px, py| (px, pivot + (py - pivot) * run.y_scale));
+                out.push(path);
+            }
+        }
+        for rule in &self.rules {
+            out.push(rect_path(
+                &Rectangle::new(x + rule.x, baseline + rule.y, rule.width, rule.height),
+                color,
+            ));
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Level {
+    Display,
+    Text,
+    Script,
+    ScriptScript,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct MathStyle {
+    level: Level,
+    cramped: bool,
+}
+
+impl MathStyle {
+    fn script(self) -> Self {
+        MathStyle {
+            level: match self.level {
+                Level::Display | Level::Text => Level::Script,
+                Level::Script | Level::ScriptScript => Level::ScriptScript,
+            },
+            cramped: self.cramped,
+        }
+    }
+
+    fn cramped(self) -> Self {
+        MathStyle {
+            level: self.level,
+            cramped: true,
+        }
+    }
+
+    fn fraction(self) -> Self {
+        MathStyle {
+            level: match self.level {
+                Level::Display => Level::Text,
+                Level::Text => Level::Script,
+                Level::Script | Level::ScriptScript => Level::ScriptScript,
+            },
+            cramped: self.cramped,
+        }
+    }
+
+    fn is_script(self) -> bool {
+        matches!(self.level, Level::Script | Level::ScriptScript)
+    }
+}
+
+/// Spaces between atoms in mu (1/18 em); negative values are used only outside of scripts
+const ATOM_SPACING: [[i8; 8]; 8] = [
+    // Ord, Op, Bin, Rel, Open, Close, Punct, Inner
+    [0, 3, -4, -5, 0, 0, 0, -3],     // Ord
+    [3, 3, 0, -5, 0, 0, 0, -3],      // Op
+    [-4, -4, 0, 0, -4, 0, 0, -4],    // Bin
+    [-5, -5, 0, 0, -5, 0, 0, -5],    // Rel
+    [0, 0, 0, 0, 0, 0, 0, 0],        // Open
+    [0, 3, -4, -5, 0, 0, 0, -3],     // Close
+    [-3, -3, 0, -3, -3, -3, -3, -3], // Punct
+    [-3, 3, -4, -5, -3, 0, -3, -3],  // Inner
+];
+
+fn atom_spacing(left: AtomClass, right: AtomClass, style: MathStyle) -> f32 {
+    let space = ATOM_SPACING[left as usize][right as usize];
+    if space < 0 && style.is_script() {
+        0.0
+    } else {
+        space.abs() as f32 / 18.0
+    }
+}
+
+/// Binary operators without operands on both sides behave as ordinary symbols
+fn resolve_classes(items: &[MathNode]) -> Vec<AtomClass> {
+    let mut classes: Vec<AtomClass> = Vec::with_capacity(items.len());
+    for item in items {
+        let mut class = item.class();
+        if class == AtomClass::Bin
+            && classes.last().is_none_or(|c| {
+                matches!(
+                    c,
+                    AtomClass::Bin
+                        | AtomClass::Op
+                        | AtomClass::Rel
+                        | AtomClass::Open
+                        | AtomClass::Punct
+                )
+            })
+        {
+            class = AtomClass::Ord;
+        }
+        if matches!(class, AtomClass::Rel | AtomClass::Close | AtomClass::Punct)
+            && let Some(last @ AtomClass::Bin) = classes.last_mut()
+        {
+            *last = AtomClass::Ord;
+        }
+        classes.push(class);
+    }
+    if let Some(last @ AtomClass::Bin) = classes.last_mut() {
+        *last = AtomClass::Ord;
+    }
+    classes
+}
+
+struct MathLayouter<'a> {
+    text_ctx: &'a mut TextContext,
+    font: &'a MathFont<'a>,
+    constants: MathConstants,
+    /// The primary font and its MATH table
+    math_table: Option<(Font, Vec<u8>)>,
+}
+
+impl<'a> MathLayouter<'a> {
+    fn new(text_ctx: &'a mut TextContext, font: &'a MathFont<'a>) -> Self {
+        let mut layouter = MathLayouter {
+            text_ctx,
+            font,
+            constants: MathConstants::default(),
+            math_table: None,
+        };
+        let plus = layouter.shape("+", font.size, MathVariant::Normal);
+        let Some(run) = plus.runs.first() else {
+            return layouter;
+        };
+        let math_table = run.with_font_ref(|font_ref, location| {
+            let units_per_em = font_ref.metrics(Size::unscaled(), location).units_per_em as f32;
+            let data = font_ref.table_data(Tag::new(b"MATH"))?.as_bytes().to_vec();
+            let constants = MathConstants::from_math_table(&data, units_per_em)?;
+            Some((data, constants))
+        });
+        if let Some((data, constants)) = math_table {
+            layouter.constants = constants;
+            layouter.math_table = Some((run.font.clone(), data));
+        } else {
+            // Without a MATH table, the math axis goes through the middle of '+'
+            layouter.constants.axis_height = (plus.ascent - plus.descent) / 2.0 / font.size;
+        }
+        layouter
+    }
+
+    fn size(&self, style: MathStyle) -> f32 {
+        self.font.size
+            * match style.level {
+                Level::Display | Level::Text => 1.0,
+                Level::Script => self.constants.script_scale,
+                Level::ScriptScript => self.constants.script_script_scale,
+            }
+    }
+
+    fn shape(&mut self, text: &str, size: f32, variant: MathVariant) -> MathBox {
+        // Math fonts provide italic and bold letters as separate characters
+        let mapped: Option<String> = self.math_table.as_ref().and_then(|(font, _)| {
+            if variant == MathVariant::Normal {
+                return None;
+            }
+            let font_ref = ReadFontsRef::from_index(font.data.as_ref(), font.index).ok()?;
+            let charmap = font_ref.charmap();
+            text.chars()
+                .map(|c| {
+                    let m = math_alphanumeric(c, variant);
+                    (m == c || charmap.map(m).is_some()).then_some(m)
+                })
+                .collect()
+        });
+        let variant = if mapped.is_some() {
+            MathVariant::Normal
+        } else {
+            variant
+        };
+        let text = mapped.as_deref().unwrap_or(text);
+        let TextContext { layout_cx, font_cx } = &mut *self.text_ctx;
+        let mut builder = layout_cx.ranged_builder(font_cx, text, 1.0);
+        builder.push_default(StyleProperty::FontStack(FontStack::Source(Cow::Borrowed(
+            self.font.family,
+        ))));
+        builder.push_default(StyleProperty::FontSize(size));
+        builder.push_default(StyleProperty::Brush(TextBrush::default()));
+        builder.push_default(StyleProperty::FontWeight(FontWeight::new(
+            if variant == MathVariant::Bold {
+                700.0
+            } else {
+                self.font.weight
+            },
+        )));
+        if variant == MathVariant::Italic {
+            builder.push_default(StyleProperty::FontStyle(FontStyle::Italic));
+        }
+        let mut layout: Layout<TextBrush> = builder.build(text);
+        layout.break_all_lines(None);
+
+        let mut result = MathBox::space(layout.width());
+        let mut ink: Option<(f32, f32)> = None;
+        for line in layout.lines() {
+            for item in line.items() {
+                let PositionedLayoutItem::GlyphRun(glyph_run) = item else {
+                    continue;
+                };
+                let run = glyph_run.run();
+                let mut x = glyph_run.offset();
+                let glyphs = glyph_run
+                    .glyphs()
+                    .map(|glyph| {
+                        let position = (x + glyph.x, -glyph.y, GlyphId::from(glyph.id));
+                        x += glyph.advance;
+                        position
+                    })
+                    .collect();
+                let math_run = MathRun {
+                    font: run.font().clone(),
+                    size: run.font_size(),
+                    coords: run.normalized_coords().to_vec(),
+                    glyphs,
+                    y_scale: 1.0,
+                };
+                if let Some((a, d)) = math_run.ink_extents() {
+                    ink = Some(ink.map_or((a, d), |(a2, d2)| (a.max(a2), d.max(d2))));
+                }
+                result.runs.push(math_run);
+            }
+        }
+        // Ink may be entirely above or below the baseline (e.g. a minus sign)
+        (result.ascent, result.descent) = ink.unwrap_or((0.0, 0.0));
+        result
+    }
+
+    /// Shapes a single character that is stretched to the given height if it is smaller.
+    /// Vertical variants from the MATH table are preferred, the glyph is scaled otherwise.
+    fn stretched(&mut self, c: char, size: f32, height: f32) -> MathBox {
+        let mut result = self.shape(&c.to_string(), size, MathVariant::Normal);
+        if result.ascent + result.descent >= height || result.runs.len() != 1 {
+            return result;
+        }
+        let run = &mut result.runs[0];
+        if let Some((font, table)) = &self.math_table
+            && run.glyphs.len() == 1
+            && font.data.id() == run.font.data.id()
+            && font.index == run.font.index
+        {
+            let units_per_em = run.with_font_ref(|font_ref, location| {
+                font_ref.metrics(Size::unscaled(), location).units_per_em as f32
+            });
+            let glyph = run.glyphs[0].2.to_u32() as u16;
+            let variants = vertical_variants(table, glyph);
+            if let Some((variant, _)) = variants
+                .iter()
+                .find(|(_, advance)| *advance as f32 * size / units_per_em >= height)
+                .or(variants.last())
+            {
+                run.glyphs[0].2 = GlyphId::from(*variant);
+                result.width = run.with_font_ref(|font_ref, location| {
+                    GlyphMetrics::new(font_ref, Size::new(size), location)
+                        .advance_width(GlyphId::from(*variant))
+                        .unwrap_or(result.width)
+                });
+            }
+        }
+        let (mut ascent, mut descent) = run.ink_extents().unwrap_or((0.0, 0.0));
+        if ascent + descent < height && ascent + descent > 0.0 {
+            run.y_scale = height / (ascent + descent);
+            ascent *= run.y_scale;
+            descent *= run.y_scale;
+        }
+        result.ascent = ascent;
+        result.descent = descent;
+        result
+    }
+
+    fn layout(&mut self, node: &MathNode, style: MathStyle) -> MathBox {
+        let size = self.size(style);
+        let c = self.constants.clone();
+        match node {
+            MathNode::Symbol { text, variant, .. } => self.shape(text, size, *variant),
+            MathNode::Operator { text, .. } => {
+                if text.chars().count() != 1 {
+                    return self.shape(text, size, MathVariant::Normal);
+                }
+                let op = if style.level == Level::Display {
+                    self.stretched(
+                        text.chars().next().unwrap(),
+                        size,
+                        c.display_operator_min_height * size,
+                    )
+                } else {
+                    self.shape(text, size, MathVariant::Normal)
+                };
+                op.centered_on(c.axis_height * size)
+            }
+            MathNode::Row(items) => self.layout_row(items, style),
+            MathNode::Scripts { base, sup, sub } => {
+                self.layout_scripts(base, sup.as_deref(), sub.as_deref(), style)
+            }
+            MathNode::Fraction { num, den } => self.layout_fraction(num, den, style),
+            MathNode::Sqrt { index, body } => self.layout_sqrt(index.as_deref(), body, style),
+            MathNode::Delimited { left, right, body } => {
+                let body = self.layout(body, style);
+                let axis = c.axis_height * size;
+                let delta = (body.ascent - axis).max(body.descent + axis);
+                let height = (2.0 * delta * 0.901).max(2.0 * delta - 0.5 * size);
+                let mut result = MathBox::default();
+                for (delimiter, is_left) in [(*left, true), (*right, false)] {
+                    let delimiter = match delimiter {
+                        Some(d) => self.stretched(d, size, height).centered_on(axis),
+                        None => MathBox::space(0.12 * size),
+                    };
+                    result.append(delimiter, result.width, 0.0);
+                    if is_left {
+                        result.append(body.clone(), result.width, 0.0);
+                    }
+                }
+                result
+            }
+            MathNode::Overline(body) => {
+                let mut result = self.layout(body, style.cramped());
+                let top =
+                    result.ascent + (c.overbar_vertical_gap + c.overbar_rule_thickness) * size;
+                result.push_rule(0.0, top, result.width, c.overbar_rule_thickness * size);
+                result.ascent += c.overbar_extra_ascender * size;
+                result
+            }
+            MathNode::Underline(body) => {
+                let mut result = self.layout(body, style);
+                let top = -result.descent - c.underbar_vertical_gap * size;
+                result.push_rule(0.0, top, result.width, c.underbar_rule_thickness * size);
+                result.descent += c.underbar_extra_descender * size;
+                result
+            }
+            MathNode::Accent { accent, body } => {
+                let mut result = self.layout(body, style.cramped());
+                // The arrow of \vec is an ordinary arrow, so it is drawn smaller
+                let accent_size = if *accent == '→' { size * 0.7 } else { size };
+                let accent = self.shape(&accent.to_string(), accent_size, MathVariant::Normal);
+                let bottom = result.ascent + 0.08 * size;
+                let dx = (result.width - accent.width) / 2.0;
+                let dy = -accent.descent - bottom;
+                result.append(accent, dx.max(0.0), dy);
+                result
+            }
+            MathNode::Space(em) => MathBox::space(em * size),
+        }
+    }
+
+    fn layout_row(&mut self, items: &[MathNode], style: MathStyle) -> MathBox {
+        let size = self.size(style);
+        let classes = resolve_classes(items);
+        let mut result = MathBox::default();
+        let mut x = 0.0;
+        for (i, item) in items.iter().enumerate() {
+            if i > 0
+                && !matches!(item, MathNode::Space(_))
+                && !matches!(items[i - 1], MathNode::Space(_))
+            {
+                x += atom_spacing(classes[i - 1], classes[i], style) * size;
+            }
+            let b = self.layout(item, style);
+            let width = b.width;
+            result.append(b, x, 0.0);
+            x += width;
+        }
+        result.width = x;
+        result
+    }
+
+    fn layout_scripts(
+        &mut self,
+        base: &MathNode,
+        sup: Option<&MathNode>,
+        sub: Option<&MathNode>,
+        style: MathStyle,
+    ) -> MathBox {
+        let size = self.size(style);
+        let c = self.constants.clone();
+        let mut result = self.layout(base, style);
+        let sup = sup.map(|n| self.layout(n, style.script()));
+        let sub = sub.map(|n| self.layout(n, style.script().cramped()));
+
+        if style.level == Level::Display && matches!(base, MathNode::Operator { limits: true, .. })
+        {
+            let width = result
+                .width
+                .max(sup.as_ref().map_or(0.0, |b| b.width))
+                .max(sub.as_ref().map_or(0.0, |b| b.width));
+            let (base_ascent, base_descent) = (result.ascent, result.descent);
+            let base_width = result.width;
+            let mut limits = MathBox::default();
+            limits.append(result, (width - base_width) / 2.0, 0.0);
+            if let Some(sup) = sup {
+                let shift = base_ascent
+                    + (c.upper_limit_gap_min * size + sup.descent)
+                        .max(c.upper_limit_baseline_rise_min * size);
+                let dx = (width - sup.width) / 2.0;
+                limits.append(sup, dx, -shift);
+            }
+            if let Some(sub) = sub {
+                let shift = base_descent
+                    + (c.lower_limit_gap_min * size + sub.ascent)
+                        .max(c.lower_limit_baseline_drop_min * size);
+                let dx = (width - sub.width) / 2.0;
+                limits.append(sub, dx, shift);
+            }
+            limits.width = width;
+            return limits;
+        }
+
+        let mut sup_shift = sup.as_ref().map(|sup| {
+            let shift_up = if style.cramped {
+                c.superscript_shift_up_cramped
+            } else {
+                c.superscript_shift_up
+            };
+            (shift_up * size)
+                .max(result.ascent - c.superscript_baseline_drop_max * size)
+                .max(sup.descent + c.superscript_bottom_min * size)
+        });
+        let mut sub_shift = sub.as_ref().map(|sub| {
+            (c.subscript_shift_down * size)
+                .max(result.descent + c.subscript_baseline_drop_min * size)
+                .max(sub.ascent - c.subscript_top_max * size)
+        });
+        if let (Some(sup), Some(sub), Some(up), Some(down)) =
+            (&sup, &sub, &mut sup_shift, &mut sub_shift)
+        {
+            let gap = (*up - sup.descent) - (sub.ascent - *down);
+            if gap < c.sub_superscript_gap_min * size {
+                *down += c.sub_superscript_gap_min * size - gap;
+            }
+        }
+        let x = result.width;
+        let mut width = x;
+        if let (Some(sup), Some(shift)) = (sup, sup_shift) {
+            width = width.max(x + sup.width);
+            result.append(sup, x, -shift);
+        }
+        if let (Some(sub), Some(shift)) = (sub, sub_shift) {
+            width = width.max(x + sub.width);
+            result.append(sub, x, shift);
+        }
+        result.width = width + c.space_after_script * size;
+        result
+    }
+
+    fn layout_fraction(&mut self, num: &MathNode, den: &MathNode, style: MathStyle) -> MathBox {
+        let size = self.size(style);
+        let c = self.constants.clone();
+        let num = self.layout(num, style.fraction());
+        let den = self.layout(den, style.fraction().cramped());
+        let display = style.level == Level::Display;
+        let axis = c.axis_height * size;
+        let thickness = c.fraction_rule_thickness * size;
+        let (shift_up, gap_num, shift_down, gap_den) = if display {
+            (
+                c.fraction_numerator_display_shift_up,
+                c.fraction_numerator_display_gap_min,
+                c.fraction_denominator_display_shift_down,
+                c.fraction_denominator_display_gap_min,
+            )
+        } else {
+            (
+                c.fraction_numerator_shift_up,
+                c.fraction_numerator_gap_min,
+                c.fraction_denominator_shift_down,
+                c.fraction_denominator_gap_min,
+            )
+        };
+        let shift_up = (shift_up * size).max(gap_num * size + axis + thickness / 2.0 + num.descent);
+        let shift_down =
+            (shift_down * size).max(gap_den * size + den.ascent - axis + thickness / 2.0);
+        let padding = 0.1 * size;
+        let width = num.width.max(den.width);
+        let mut result = MathBox::default();
+        result.push_rule(padding, axis + thickness / 2.0, width, thickness);
+        let num_x = padding + (width - num.width) / 2.0;
+        let den_x = padding + (width - den.width) / 2.0;
+        result.append(num, num_x, -shift_up);
+        result.append(den, den_x, shift_down);
+        result.width = width + 2.0 * padding;
+        result
+    }
+
+    fn layout_sqrt(
+        &mut self,
+        index: Option<&MathNode>,
+        body: &MathNode,
+        style: MathStyle,
+    ) -> MathBox {
+        let size = self.size(style);
+        let c = self.constants.clone();
+        let body = self.layout(body, style.cramped());
+        let gap = if style.level == Level::Display {
+            c.radical_display_vertical_gap
+        } else {
+            c.radical_vertical_gap
+        } * size;
+        let thickness = c.radical_rule_thickness * size;
+        let required = body.ascent + body.descent + gap + thickness;
+        let surd = self.stretched('√', size, required);
+        let surd_height = surd.ascent + surd.descent;
+        let extra = ((surd_height - required) / 2.0).max(0.0);
+        let rule_top = body.ascent + gap + extra + thickness;
+        let surd_dy = surd.ascent - rule_top;
+
+        let mut result = MathBox::default();
+        let mut x = 0.0;
+        if let Some(index) = index {
+            let index = self.layout(
+                index,
+                MathStyle {
+                    level: Level::ScriptScript,
+                    cramped: true,
+                },
+            );
+            let surd_bottom = -(surd.descent + surd_dy);
+            let raise = surd_bottom + c.radical_degree_bottom_raise * surd_height + index.descent;
+            let index_x = c.radical_kern_before_degree * size;
+            x = (index_x + index.width + c.radical_kern_after_degree * size).max(0.0);
+            result.append(index, index_x, -raise);
+        }
+        let surd_width = surd.width;
+        result.append(surd, x, surd_dy);
+        x += surd_width;
+        result.push_rule(x, rule_top, body.width, thickness);
+        let body_width = body.width;
+        result.append(body, x, 0.0);
+        result.width = x + body_width;
+        result.ascent = result
+            .ascent
+            .max(rule_top + c.radical_extra_ascender * size);
+        result
+    }
+}
+
+/// Lays out a parsed formula
+pub(crate) fn layout_formula(
+    text_ctx: &mut TextContext,
+    node: &MathNode,
+    font: &MathFont,
+    display: bool,
+) -> MathBox {
+    let mut layouter = MathLayouter::new(text_ctx, font);
+    let level = if display { Level::Display } else { Level::Text };
+    layouter.layout(
+        node,
+        MathStyle {
+            level,
+            cramped: false,
+        },
+    )
+}
+
+pub(crate) fn render_math(
+    text_ctx: &mut TextContext,
+    math: &Math,
+) -> crate::Result<(RenderedText, f32, f32)> {
+    let node = parse_math(&math.formula)?;
+    let font = MathFont::new(&math.style);
+    let formula = layout_formula(text_ctx, &node, &font, math.display);
+    let color = math.style.color.unwrap_or_default();
+    let mut paths = Vec::new();
+    let mut images = Vec::new();
+    formula.render(0.0, formula.ascent, color, &mut paths, &mut images);
+    let width = formula.width;
+    let height = formula.ascent + formula.descent;
+    Ok((
+        RenderedText::new(
+            paths,
+            images,
+            vec![Rectangle::new(0.0, 0.0, width, height)],
+            HashMap::new(),
+        ),
+        width,
+        height,
+    ))
+}
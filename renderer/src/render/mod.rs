@@ -2,13 +2,19 @@ pub(crate) mod arrows;
 pub(crate) mod canvas;
 pub(crate) mod canvas_pdf;
 mod canvas_svg;
+mod colorglyphs;
 pub(crate) mod composer;
 pub(crate) mod composer_pdf;
+pub(crate) mod connector;
 pub(crate) mod content;
 pub(crate) mod context;
+pub(crate) mod debug;
 pub(crate) mod draw;
+pub(crate) mod geometry;
 pub(crate) mod layout;
+pub(crate) mod math;
 pub(crate) mod node;
+pub(crate) mod overflow;
 mod pdfdraw;
 mod svgpath;
 pub(crate) mod text;
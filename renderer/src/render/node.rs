@@ -1,9 +1,10 @@
 use crate::node::{Node, NodeChild};
 use crate::render::canvas::{Canvas, Link};
 use crate::render::draw::DrawItem;
+use crate::render::draw::DrawPath;
 use crate::render::layout::ComputedLayout;
 use crate::shapes::FillAndStroke;
-use crate::{NodeId, Path, Shape, ShapeRect};
+use crate::{NodeId, Rectangle, Shape, ShapeRect};
 
 pub(crate) fn render_node(node: &Node, layout: &ComputedLayout, canvas: &mut Canvas) {
     if !node.show {
@@ -25,13 +26,30 @@ pub(crate) fn render_node(node: &Node, layout: &ComputedLayout, canvas: &mut Can
         canvas.add_link(Link::new(rect.clone(), url.clone()));
     }
 
+    let node_layout = layout.node_layout(node.node_id).unwrap();
+    if let Some(text) = &node_layout.text {
+        let rect = &node_layout.rect;
+        for link in text.links() {
+            let r = link.rect();
+            canvas.add_link(Link::new(
+                Rectangle::new(rect.x + r.x, rect.y + r.y, r.width, r.height),
+                link.url().to_string(),
+            ));
+        }
+    }
+
     for child in &node.children {
         match child {
             NodeChild::Node(node) => render_node(node, layout, canvas),
             NodeChild::Shape(shape) => match shape {
                 Shape::Rect(rect) => render_rect(canvas, rect, layout, node.node_id),
                 Shape::Oval(rect) => render_oval(canvas, rect, layout, node.node_id),
-                Shape::Path(path) => render_path(canvas, path, layout, node.node_id),
+                Shape::Path(path) => {
+                    render_path(canvas, path.z_level, path.eval(layout, node.node_id))
+                }
+                Shape::Connector(connector) => {
+                    render_path(canvas, connector.z_level, connector.eval(layout))
+                }
             },
         }
     }
@@ -47,15 +65,13 @@ fn render_oval(canvas: &mut Canvas, rect: &ShapeRect, layout: &ComputedLayout, p
     canvas.add_draw_item(rect.z_level, DrawItem::Oval(draw_rect));
 }
 
-fn render_path(canvas: &mut Canvas, path: &Path, layout: &ComputedLayout, parent_id: NodeId) {
-    let (draw_path, arrow1, arrow2) = path.eval(layout, parent_id);
-    if let Some(draw_path) = draw_path {
-        canvas.add_draw_item(path.z_level, DrawItem::Path(draw_path));
-    }
-    if let Some(arrow) = arrow1 {
-        canvas.add_draw_item(path.z_level, DrawItem::Path(arrow));
-    }
-    if let Some(arrow) = arrow2 {
-        canvas.add_draw_item(path.z_level, DrawItem::Path(arrow));
+fn render_path(
+    canvas: &mut Canvas,
+    z_level: i32,
+    paths: (Option<DrawPath>, Option<DrawPath>, Option<DrawPath>),
+) {
+    let (draw_path, arrow1, arrow2) = paths;
+    for path in [draw_path, arrow1, arrow2].into_iter().flatten() {
+        canvas.add_draw_item(z_level, DrawItem::Path(path));
     }
 }
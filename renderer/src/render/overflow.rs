@@ -0,0 +1,156 @@
+use crate::layout_info::LayoutWarningKind;
+use crate::node::Node;
+use crate::render::content::ContentBody;
+use crate::render::context::RenderContext;
+use crate::render::layout::{ComputedLayout, is_layout_managed};
+use crate::{Length, NodeId, Page, Rectangle};
+
+/// Overflows smaller than this are caused by rounding and they are ignored
+const OVERFLOW_TOLERANCE: f32 = 0.5;
+
+#[derive(Debug, Clone)]
+pub(crate) struct NodeOverflow {
+    pub node_id: NodeId,
+    pub kind: LayoutWarningKind,
+    pub amount: f32,
+}
+
+/// The largest distance by which `inner` extends beyond `outer`; zero if it is inside
+fn overflow_amount(inner: &Rectangle, outer: &Rectangle) -> f32 {
+    [
+        outer.x - inner.x,
+        outer.y - inner.y,
+        (inner.x + inner.width) - (outer.x + outer.width),
+        (inner.y + inner.height) - (outer.y + outer.height),
+    ]
+    .into_iter()
+    .fold(0.0, f32::max)
+}
+
+fn resolve_padding(length: Length, parent_width: f32) -> f32 {
+    match length {
+        Length::Points { value } => value,
+        Length::Fraction { value } => value * parent_width,
+    }
+}
+
+struct OverflowChecker<'a> {
+    render_ctx: &'a RenderContext<'a>,
+    layout: &'a ComputedLayout,
+    page_rect: Rectangle,
+    overflows: Vec<NodeOverflow>,
+}
+
+impl OverflowChecker<'_> {
+    fn add(&mut self, node: &Node, kind: LayoutWarningKind, amount: f32) {
+        self.overflows.push(NodeOverflow {
+            node_id: node.node_id,
+            kind,
+            amount,
+        });
+    }
+
+    fn check_content(&mut self, node: &Node, rect: &Rectangle, parent_width: f32) {
+        let Some(content) = node
+            .content
+            .and_then(|content_id| self.render_ctx.content_map.get(&content_id))
+        else {
+            return;
+        };
+        let (content_width, content_height) = content.size();
+        if rect.width < OVERFLOW_TOLERANCE || rect.height < OVERFLOW_TOLERANCE {
+            // A content without an area (e.g. an empty text) is not lost in an empty node
+            if content_width >= OVERFLOW_TOLERANCE && content_height >= OVERFLOW_TOLERANCE {
+                self.add(node, LayoutWarningKind::EmptyNode, 0.0);
+            }
+            return;
+        }
+        // Images are scaled into their nodes, so only texts may overflow
+        if !matches!(content.body(), ContentBody::Text(_)) {
+            return;
+        }
+        let inner_width = rect.width
+            - resolve_padding(node.p_left, parent_width)
+            - resolve_padding(node.p_right, parent_width);
+        let inner_height = rect.height
+            - resolve_padding(node.p_top, parent_width)
+            - resolve_padding(node.p_bottom, parent_width);
+        let amount = (content_width - inner_width).max(content_height - inner_height);
+        if amount > OVERFLOW_TOLERANCE {
+            self.add(node, LayoutWarningKind::TextOverflow, amount);
+        }
+    }
+
+    /// `outside_page` is true when an ancestor already overflows the page;
+    /// its descendants are then not reported separately
+    fn check_node(&mut self, node: &Node, parent: Option<&Node>, outside_page: bool) {
+        if !node.show {
+            return;
+        }
+        let rect = &self.layout.node_layout(node.node_id).unwrap().rect;
+        let parent_rect = parent.map(|p| &self.layout.node_layout(p.node_id).unwrap().rect);
+
+        let page_amount = overflow_amount(rect, &self.page_rect);
+        let is_outside = page_amount > OVERFLOW_TOLERANCE;
+        if is_outside && !outside_page {
+            self.add(node, LayoutWarningKind::PageOverflow, page_amount);
+        }
+        if let Some(parent_rect) = parent_rect
+            && is_layout_managed(node, parent)
+        {
+            let amount = overflow_amount(rect, parent_rect);
+            if amount > OVERFLOW_TOLERANCE {
+                self.add(node, LayoutWarningKind::ParentOverflow, amount);
+            }
+        }
+        let parent_width = parent_rect.map(|r| r.width).unwrap_or(self.page_rect.width);
+        self.check_content(node, rect, parent_width);
+
+        for child in node.child_nodes() {
+            self.check_node(child, Some(node), outside_page || is_outside);
+        }
+    }
+}
+
+/// Finds visible nodes that extend beyond the page or beyond their parents,
+/// texts larger than their nodes, and nodes with a content that have no area
+pub(crate) fn detect_overflows(
+    render_ctx: &RenderContext,
+    page: &Page,
+    layout: &ComputedLayout,
+) -> Vec<NodeOverflow> {
+    let mut checker = OverflowChecker {
+        render_ctx,
+        layout,
+        page_rect: Rectangle::new(0.0, 0.0, page.width, page.height),
+        overflows: Vec::new(),
+    };
+    checker.check_node(&page.node, None, false);
+    checker.overflows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overflow_amount() {
+        let outer = Rectangle::new(0.0, 0.0, 100.0, 50.0);
+        assert_eq!(
+            overflow_amount(&Rectangle::new(10.0, 10.0, 20.0, 20.0), &outer),
+            0.0
+        );
+        assert_eq!(
+            overflow_amount(&Rectangle::new(90.0, 10.0, 30.0, 20.0), &outer),
+            20.0
+        );
+        assert_eq!(
+            overflow_amount(&Rectangle::new(-5.0, 40.0, 30.0, 20.0), &outer),
+            10.0
+        );
+        assert_eq!(
+            overflow_amount(&Rectangle::new(-10.0, -10.0, 120.0, 70.0), &outer),
+            10.0
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use crate::render::canvas::Link;
-use crate::render::composer_pdf::PdfRefAllocator;
+use crate::render::composer_pdf::{GlyphImageRefs, PdfRefAllocator};
 use crate::render::draw::{DrawItem, DrawPath, DrawPathPart, DrawRect, PathBuilder};
 use crate::shapes::FillAndStroke;
 use crate::{Color, Rectangle};
@@ -11,17 +11,19 @@ pub struct PdfWriter<'a> {
     pub(crate) content: Content,
     pub(crate) chunk: Chunk,
     pub(crate) alloc_ref: &'a PdfRefAllocator,
+    pub(crate) glyph_images: &'a GlyphImageRefs,
     res_name_counter: u32,
     pub(crate) xo_resources: Vec<(String, Ref)>,
     pub(crate) gs_resources: HashMap<(u8, u8), (String, Ref)>,
 }
 
 impl<'a> PdfWriter<'a> {
-    pub fn new(alloc_ref: &'a PdfRefAllocator) -> Self {
+    pub fn new(alloc_ref: &'a PdfRefAllocator, glyph_images: &'a GlyphImageRefs) -> Self {
         PdfWriter {
             content: Content::new(),
             chunk: Chunk::new(),
             alloc_ref,
+            glyph_images,
             res_name_counter: 0,
             xo_resources: Vec::new(),
             gs_resources: HashMap::new(),
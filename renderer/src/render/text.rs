@@ -1,11 +1,21 @@
+use crate::render::canvas::Link;
+use crate::render::colorglyphs::ColorGlyphs;
 use crate::render::draw::{DrawPath, PathBuilder};
+use crate::render::math::{MathBox, MathFont, layout_formula};
 use crate::shapes::{FillAndStroke, Stroke};
-use crate::text::{InlineId, Text, TextAlign, TextStyle};
-use crate::textutils::StyledText;
-use crate::{Color, Rectangle, Resources};
+use crate::text::{
+    DecorationStyle, DiffLine, InlineId, ScriptPosition, Text, TextAlign, TextDirection, TextStyle,
+    WritingMode,
+};
+use crate::textutils::math::parse_math;
+use crate::textutils::syntaxhl::{DEFAULT_LINE_HIGHLIGHT_COLOR, text_theme_colors};
+use crate::textutils::{MATH_PLACEHOLDER, StyledText};
+use crate::{Color, InMemoryBinImage, Rectangle, Resources};
+use parley::swash::text::{BidiClass, Codepoint};
 use parley::{
-    Alignment, AlignmentOptions, FontContext, FontStack, FontStyle, FontWeight, FontWidth,
-    GlyphRun, InlineBox, Layout, LayoutContext, PositionedLayoutItem, RangedBuilder, StyleProperty,
+    Alignment, AlignmentOptions, Cluster, Font, FontContext, FontSettings, FontStack, FontStyle,
+    FontWeight, FontWidth, GlyphRun, InlineBox, Layout, LayoutContext, Line, PositionedInlineBox,
+    PositionedLayoutItem, RangedBuilder, RunMetrics, Style, StyleProperty,
 };
 use resvg::usvg::FontStretch;
 use skrifa::instance::{LocationRef, NormalizedCoord, Size};
@@ -14,19 +24,50 @@ use skrifa::raw::FontRef as ReadFontsRef;
 use skrifa::{GlyphId, MetadataProvider};
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::ops::Range;
+use unicode_vo::{Orientation, char_orientation};
+
+/// Bitmap of a color glyph (e.g. an emoji) that is drawn over the paths of a text
+#[derive(Debug)]
+pub(crate) struct GlyphImage {
+    pub rect: Rectangle,
+    pub image: InMemoryBinImage,
+}
 
 #[derive(Debug)]
 pub(crate) struct RenderedText {
     paths: Vec<DrawPath>,
+    images: Vec<GlyphImage>,
     line_rects: Vec<Rectangle>,
     inline_rects: HashMap<InlineId, Rectangle>,
+    /// Links inside the text, relative to the text origin
+    links: Vec<Link>,
 }
 
 impl RenderedText {
+    pub fn new(
+        paths: Vec<DrawPath>,
+        images: Vec<GlyphImage>,
+        line_rects: Vec<Rectangle>,
+        inline_rects: HashMap<InlineId, Rectangle>,
+    ) -> Self {
+        RenderedText {
+            paths,
+            images,
+            line_rects,
+            inline_rects,
+            links: Vec::new(),
+        }
+    }
+
     pub fn paths(&self) -> &[DrawPath] {
         &self.paths
     }
 
+    pub fn images(&self) -> &[GlyphImage] {
+        &self.images
+    }
+
     pub fn line_layouts(&self) -> &[Rectangle] {
         &self.line_rects
     }
@@ -34,23 +75,276 @@ impl RenderedText {
     pub fn intext_rects(&self) -> &HashMap<InlineId, Rectangle> {
         &self.inline_rects
     }
+
+    pub fn links(&self) -> &[Link] {
+        &self.links
+    }
+
+    pub fn into_paths(self) -> Vec<DrawPath> {
+        self.paths
+    }
 }
 
 pub(crate) struct TextContext {
-    pub layout_cx: LayoutContext<Color>,
+    pub layout_cx: LayoutContext<TextBrush>,
     pub font_cx: FontContext,
 }
 
+impl TextContext {
+    pub fn new(resources: &Resources) -> Self {
+        TextContext {
+            layout_cx: Default::default(),
+            font_cx: FontContext {
+                collection: resources.font_context.collection.clone(),
+                source_cache: resources.font_context.source_cache.clone(),
+            },
+        }
+    }
+}
+
+/// Brush used for parley layouts; carries everything that is painted per glyph run
+/// and that parley does not know about by itself (background, overline, decoration look).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct TextBrush {
+    pub color: Color,
+    pub bg_color: Option<Color>,
+    pub overline: bool,
+    pub decoration_color: Option<Color>,
+    pub decoration_width: Option<f32>,
+    pub decoration_style: DecorationStyle,
+    /// Shift of the baseline upwards (in points)
+    pub baseline_shift: f32,
+}
+
+impl TextBrush {
+    fn update(&mut self, style: &TextStyle) {
+        if let Some(color) = style.color {
+            self.color = color;
+        }
+        if let Some(bg_color) = style.bg_color {
+            self.bg_color = Some(bg_color);
+        }
+        if let Some(overline) = style.overline {
+            self.overline = overline;
+        }
+        if let Some(decoration_color) = style.decoration_color {
+            self.decoration_color = Some(decoration_color);
+        }
+        if let Some(decoration_width) = style.decoration_width {
+            self.decoration_width = Some(decoration_width.get());
+        }
+        if let Some(decoration_style) = style.decoration_style {
+            self.decoration_style = decoration_style;
+        }
+    }
+}
+
+const SCRIPT_SIZE_FACTOR: f32 = 0.65;
+const SUPERSCRIPT_SHIFT: f32 = 0.35;
+const SUBSCRIPT_SHIFT: f32 = -0.2;
+
+/// Style properties that has to be resolved before they are passed into parley
+#[derive(Debug, Clone)]
+struct ResolvedStyle {
+    brush: TextBrush,
+    size: f32,
+    script: ScriptPosition,
+    baseline_shift: f32,
+}
+
+impl ResolvedStyle {
+    fn new(style: &TextStyle) -> Self {
+        let mut result = ResolvedStyle {
+            brush: TextBrush::default(),
+            size: 16.0,
+            script: ScriptPosition::Normal,
+            baseline_shift: 0.0,
+        };
+        result.update(style);
+        result
+    }
+
+    fn update(&mut self, style: &TextStyle) {
+        self.brush.update(style);
+        if let Some(size) = style.size {
+            self.size = size.get();
+        }
+        if let Some(script) = style.script {
+            self.script = script;
+        }
+        if let Some(baseline_shift) = style.baseline_shift {
+            self.baseline_shift = baseline_shift.get();
+        }
+    }
+
+    /// Returns brush with the final baseline shift and the final font size
+    fn finish(mut self) -> (TextBrush, f32) {
+        let (size_factor, script_shift) = match self.script {
+            ScriptPosition::Normal => (1.0, 0.0),
+            ScriptPosition::Super => (SCRIPT_SIZE_FACTOR, SUPERSCRIPT_SHIFT),
+            ScriptPosition::Sub => (SCRIPT_SIZE_FACTOR, SUBSCRIPT_SHIFT),
+        };
+        self.brush.baseline_shift = self.baseline_shift + script_shift * self.size;
+        (self.brush, self.size * size_factor)
+    }
+}
+
+/// Returns how much shifted runs (e.g. superscripts) and inline formulas overflow
+/// the line metrics computed by parley (above and below the line).
+fn shifted_runs_overflow(line: &Line<TextBrush>, math: &[(MathBox, TextBrush)]) -> (f32, f32) {
+    let metrics = line.metrics();
+    let mut above: f32 = 0.0;
+    let mut below: f32 = 0.0;
+    for item in line.items() {
+        match item {
+            PositionedLayoutItem::GlyphRun(glyph_run) => {
+                let shift = glyph_run.style().brush.baseline_shift;
+                if shift != 0.0 {
+                    let run_metrics = glyph_run.run().metrics();
+                    above = above.max(run_metrics.ascent + shift);
+                    below = below.max(run_metrics.descent - shift);
+                }
+            }
+            PositionedLayoutItem::InlineBox(inline_box) => {
+                // Parley places the bottom of a box on the baseline, so only the depth is missing
+                if let Some((formula, brush)) = inline_math(&inline_box, math) {
+                    below = below.max(formula.descent - brush.baseline_shift);
+                }
+            }
+        }
+    }
+    (
+        (above - (metrics.baseline - metrics.min_coord)).max(0.0),
+        (below - (metrics.max_coord - metrics.baseline)).max(0.0),
+    )
+}
+
+/// Ids of inline boxes with formulas start here, lower ids belong to anchors
+const MATH_BOX_ID: u64 = 1 << 40;
+
+fn inline_math<'a>(
+    inline_box: &PositionedInlineBox,
+    math: &'a [(MathBox, TextBrush)],
+) -> Option<&'a (MathBox, TextBrush)> {
+    inline_box
+        .id
+        .checked_sub(MATH_BOX_ID)
+        .and_then(|idx| math.get(idx as usize))
+}
+
+/// Lays out inline formulas in the font, size, and color of the text around them
+fn layout_inline_math(
+    text_ctx: &mut TextContext,
+    styled_text: &StyledText,
+) -> crate::Result<Vec<(MathBox, TextBrush)>> {
+    styled_text
+        .math
+        .iter()
+        .map(|math| {
+            let main_style = &styled_text.main_style;
+            let mut resolved = ResolvedStyle::new(main_style);
+            let mut font = main_style.font.as_ref();
+            let mut weight = main_style.weight;
+            for style in &styled_text.styles {
+                if style.start <= math.pos && math.pos < style.end {
+                    resolved.update(&style.style);
+                    font = style.style.font.as_ref().or(font);
+                    weight = style.style.weight.or(weight);
+                }
+            }
+            let (brush, size) = resolved.finish();
+            let math_font = MathFont {
+                family: font.map(|f| f.as_str()).unwrap_or("sans-serif"),
+                size,
+                weight: weight.unwrap_or(400) as f32,
+            };
+            let node = parse_math(&math.formula)?;
+            Ok((layout_formula(text_ctx, &node, &math_font, false), brush))
+        })
+        .collect()
+}
+
 pub fn render_text(
     resources: &Resources,
     text_ctx: &mut TextContext,
     text: &Text,
 ) -> crate::Result<(RenderedText, f32, f32)> {
     let styled_text = StyledText::from(resources, text)?;
+    let (mut rendered_text, width, height) = render_styled_text(text_ctx, text, &styled_text)?;
+    let mut highlights: Vec<(u32, u32, Color)> = Vec::new();
+    if let Some(diff) = &text.diff {
+        for (idx, line) in diff.lines.iter().enumerate() {
+            let color = match line {
+                DiffLine::Added => diff.added_color,
+                DiffLine::Removed => diff.removed_color,
+                DiffLine::Unchanged => continue,
+            };
+            highlights.push((idx as u32, idx as u32 + 1, color));
+        }
+    }
+    if !text.line_highlights.is_empty() {
+        let theme_colors = text_theme_colors(resources, text)?;
+        let default_color = theme_colors
+            .selection
+            .or(theme_colors.line_highlight)
+            .unwrap_or(DEFAULT_LINE_HIGHLIGHT_COLOR);
+        highlights.extend(
+            text.line_highlights
+                .iter()
+                .map(|h| (h.start, h.end, h.color.unwrap_or(default_color))),
+        );
+    }
+    if !highlights.is_empty() {
+        let vertical = text.writing_mode == WritingMode::Vertical;
+        let paths: Vec<DrawPath> = highlights
+            .into_iter()
+            .flat_map(|(start, end, color)| {
+                rendered_text
+                    .line_rects
+                    .iter()
+                    .take(end as usize)
+                    .skip(start as usize)
+                    .map(move |line| {
+                        let rect = if vertical {
+                            Rectangle::new(line.x, 0.0, line.width, height)
+                        } else {
+                            Rectangle::new(0.0, line.y, width, line.height)
+                        };
+                        rect_path(&rect, color)
+                    })
+            })
+            .collect();
+        rendered_text.paths.splice(0..0, paths);
+    }
+    Ok((rendered_text, width, height))
+}
+
+fn render_styled_text(
+    text_ctx: &mut TextContext,
+    text: &Text,
+    styled_text: &StyledText,
+) -> crate::Result<(RenderedText, f32, f32)> {
+    let mark = direction_mark(text.direction, &styled_text.text);
+    let vertical = text.writing_mode == WritingMode::Vertical;
+    if vertical && !styled_text.math.is_empty() {
+        return Err(crate::Error::generic_err(
+            "Math formulas are not supported in vertical text",
+        ));
+    }
+    let math = layout_inline_math(text_ctx, styled_text)?;
 
-    let mut layout = styled_text_to_parley(text_ctx, &styled_text);
+    let mut layout = styled_text_to_parley(text_ctx, styled_text, &math, mark, vertical);
 
     layout.break_all_lines(None);
+    if vertical {
+        return Ok(render_vertical_layout(
+            &layout,
+            &styled_text.text,
+            mark.len(),
+            text.text_align,
+            &styled_text.links,
+        ));
+    }
     layout.align(
         None,
         match text.text_align {
@@ -61,32 +355,65 @@ pub fn render_text(
         AlignmentOptions::default(),
     );
 
-    let mut inline_rects = HashMap::new();
+    let mut inline_rects: HashMap<InlineId, Rectangle> = HashMap::new();
+    let mut box_positions: HashMap<u64, (usize, f32)> = HashMap::new();
+    let mut backgrounds = Vec::new();
     let mut paths = Vec::new();
+    let mut images = Vec::new();
     let mut line_rects = Vec::with_capacity(layout.len());
+    let mut y_offset = 0.0;
     for line in layout.lines() {
         let mut min_x: f32 = f32::INFINITY;
         let mut max_x: f32 = 0.0;
         let metrics = line.metrics();
-        let line_y = metrics.min_coord;
-        let line_height = metrics.max_coord - metrics.min_coord;
+        let (extra_above, extra_below) = shifted_runs_overflow(&line, &math);
+        let line_y = metrics.min_coord + y_offset;
+        let line_height = metrics.max_coord - metrics.min_coord + extra_above + extra_below;
+        let baseline_offset = y_offset + extra_above;
+        y_offset += extra_above + extra_below;
         for item in line.items() {
             match item {
                 PositionedLayoutItem::GlyphRun(glyph_run) => {
-                    render_glyph_run(&glyph_run, &mut paths);
+                    if let Some(bg_color) = glyph_run.style().brush.bg_color {
+                        backgrounds.push(rect_path(
+                            &Rectangle::new(
+                                glyph_run.offset(),
+                                line_y,
+                                glyph_run.advance(),
+                                line_height,
+                            ),
+                            bg_color,
+                        ));
+                    }
+                    render_glyph_run(&glyph_run, baseline_offset, &mut paths, &mut images);
                     min_x = min_x.min(glyph_run.offset());
                     max_x = max_x.max(glyph_run.offset() + glyph_run.advance());
                 }
                 PositionedLayoutItem::InlineBox(inline_box) => {
-                    let id = InlineId::new((inline_box.id / 2) as u32);
-                    if inline_box.id % 2 == 0 {
-                        inline_rects.insert(
-                            id,
-                            Rectangle::new(inline_box.x, metrics.min_coord, 0.0, line_height),
+                    if let Some((formula, brush)) = inline_math(&inline_box, &math) {
+                        let baseline = inline_box.y + inline_box.height + baseline_offset;
+                        formula.render(
+                            inline_box.x,
+                            baseline - brush.baseline_shift,
+                            brush.color,
+                            &mut paths,
+                            &mut images,
                         );
+                        min_x = min_x.min(inline_box.x);
+                        max_x = max_x.max(inline_box.x + inline_box.width);
+                        continue;
+                    }
+                    box_positions.insert(inline_box.id, (line_rects.len(), inline_box.x));
+                    // Boxes come in visual order, so in RTL runs the end of an anchor
+                    // is visited before its start
+                    let id = InlineId::new((inline_box.id / 2) as u32);
+                    if let Some(r) = inline_rects.get_mut(&id) {
+                        let x1 = (r.x + r.width).max(inline_box.x);
+                        r.x = r.x.min(inline_box.x);
+                        r.width = x1 - r.x;
                     } else {
-                        let r = inline_rects.get_mut(&id).unwrap();
-                        r.width = inline_box.x - r.x;
+                        inline_rects
+                            .insert(id, Rectangle::new(inline_box.x, line_y, 0.0, line_height));
                     }
                 }
             };
@@ -97,44 +424,153 @@ pub fn render_text(
         }
         line_rects.push(Rectangle::new(min_x, line_y, max_x - min_x, line_height));
     }
+    if !backgrounds.is_empty() {
+        backgrounds.append(&mut paths);
+        paths = backgrounds;
+    }
 
+    let links = link_rects(&styled_text.links, &box_positions, &line_rects, false);
     Ok((
         RenderedText {
             paths,
+            images,
             line_rects,
             inline_rects,
+            links,
         },
         layout.width(),
-        layout.height(),
+        layout.height() + y_offset,
     ))
 }
 
+pub(crate) fn rect_path(rect: &Rectangle, color: Color) -> DrawPath {
+    let mut builder = PathBuilder::new(FillAndStroke::new_fill(color));
+    builder.move_to(rect.x, rect.y);
+    builder.line_to(rect.x + rect.width, rect.y);
+    builder.line_to(rect.x + rect.width, rect.y + rect.height);
+    builder.line_to(rect.x, rect.y + rect.height);
+    builder.close();
+    builder.build()
+}
+
+/// Splits text into segments with constant resolved style properties.
+/// These properties cannot be pushed into parley per style range directly, because a nested range
+/// would replace the whole brush (e.g. a background of the outer range would be lost) and
+/// the size of super/subscripts depends on the size of the surrounding text.
+fn resolve_ranges(
+    styled_text: &StyledText,
+    base: &ResolvedStyle,
+) -> Vec<(Range<usize>, TextBrush, f32)> {
+    let base_result = base.clone().finish();
+    let styles = &styled_text.styles;
+    let mut bounds: Vec<u32> = styles.iter().flat_map(|s| [s.start, s.end]).collect();
+    bounds.push(0);
+    bounds.push(styled_text.text.len() as u32);
+    bounds.sort_unstable();
+    bounds.dedup();
+
+    let mut by_start: Vec<usize> = (0..styles.len()).collect();
+    by_start.sort_by_key(|idx| styles[*idx].start);
+    let mut next = 0;
+    let mut active: Vec<usize> = Vec::new();
+    let mut result = Vec::new();
+    for w in bounds.windows(2) {
+        let (start, end) = (w[0], w[1]);
+        active.retain(|idx| styles[*idx].end > start);
+        while next < by_start.len() && styles[by_start[next]].start <= start {
+            if styles[by_start[next]].end > start {
+                active.push(by_start[next]);
+            }
+            next += 1;
+        }
+        // Styles are applied in the same order as they are pushed into parley
+        active.sort_unstable();
+        let mut resolved = base.clone();
+        for idx in &active {
+            resolved.update(&styles[*idx].style);
+        }
+        let (brush, size) = resolved.finish();
+        if brush != base_result.0 || size != base_result.1 {
+            result.push((start as usize..end as usize, brush, size));
+        }
+    }
+    result
+}
+
+/// Returns true if the first strong character (outside of isolates) is right-to-left
+fn starts_with_rtl(text: &str) -> bool {
+    let mut isolates = 0u32;
+    for ch in text.chars() {
+        match ch.bidi_class() {
+            BidiClass::RLI | BidiClass::LRI | BidiClass::FSI => isolates += 1,
+            BidiClass::PDI => isolates = isolates.saturating_sub(1),
+            BidiClass::L if isolates == 0 => return false,
+            BidiClass::R | BidiClass::AL if isolates == 0 => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Parley always detects the base direction from the first strong character,
+/// so an explicit direction is forced by prepending an invisible directional mark.
+/// The mark is also used for detected RTL paragraphs, otherwise an inline box at
+/// the very beginning of the text would get the LTR level.
+fn direction_mark(direction: TextDirection, text: &str) -> &'static str {
+    match direction {
+        TextDirection::Auto if starts_with_rtl(text) => "\u{200F}",
+        TextDirection::Auto => "",
+        TextDirection::Ltr => "\u{200E}",
+        TextDirection::Rtl => "\u{200F}",
+    }
+}
+
 fn styled_text_to_parley(
     text_context: &mut TextContext,
     styled_text: &StyledText,
-) -> Layout<Color> {
-    let mut builder =
-        text_context
-            .layout_cx
-            .ranged_builder(&mut text_context.font_cx, &styled_text.text, 1.0);
+    math: &[(MathBox, TextBrush)],
+    mark: &str,
+    vertical: bool,
+) -> Layout<TextBrush> {
+    let text: Cow<str> = if mark.is_empty() {
+        Cow::Borrowed(&styled_text.text)
+    } else {
+        Cow::Owned(format!("{mark}{}", styled_text.text))
+    };
+    let mark_len = mark.len();
+    let mut builder = text_context
+        .layout_cx
+        .ranged_builder(&mut text_context.font_cx, &text, 1.0);
     let TextStyle {
         font,
-        color,
-        size,
+        color: _,
+        size: _,
         line_spacing,
         italic,
         stretch,
         weight,
         underline,
+        overline: _,
         line_through,
+        bg_color: _,
+        decoration_color: _,
+        decoration_width: _,
+        decoration_style: _,
+        baseline_shift: _,
+        script: _,
     } = &styled_text.main_style;
     builder.push_default(StyleProperty::FontStack(FontStack::Source(Cow::Borrowed(
         font.as_ref().map(|x| x.as_str()).unwrap_or("sans-serif"),
     ))));
-    builder.push_default(StyleProperty::Brush((*color).unwrap_or_default()));
-    builder.push_default(StyleProperty::FontSize(
-        (*size).map(|x| x.get()).unwrap_or(16.0),
-    ));
+    let base_style = ResolvedStyle::new(&styled_text.main_style);
+    for (range, brush, size) in resolve_ranges(styled_text, &base_style) {
+        let range = range.start + mark_len..range.end + mark_len;
+        builder.push(StyleProperty::Brush(brush), range.clone());
+        builder.push(StyleProperty::FontSize(size), range);
+    }
+    let (base_brush, base_size) = base_style.finish();
+    builder.push_default(StyleProperty::Brush(base_brush));
+    builder.push_default(StyleProperty::FontSize(base_size));
     builder.push_default(StyleProperty::LineHeight(
         (*line_spacing).map(|x| x.get()).unwrap_or(1.0),
     ));
@@ -153,60 +589,161 @@ fn styled_text_to_parley(
     if *italic == Some(true) {
         builder.push_default(StyleProperty::FontStyle(FontStyle::Italic));
     }
+    if vertical {
+        // Vertical alternates of punctuation, brackets, etc.
+        builder.push_default(StyleProperty::FontFeatures(FontSettings::Source(
+            Cow::Borrowed("\"vert\" on"),
+        )));
+    }
     for style in &styled_text.styles {
         set_text_style_to_parley(
             &mut builder,
             &style.style,
-            style.start as usize,
-            style.end as usize,
+            style.start as usize + mark_len,
+            style.end as usize + mark_len,
         );
     }
+    // Parley (0.3) compares the index of an inline box with a char index, not a byte offset
+    let char_index = |byte_index: u32| text[..byte_index as usize + mark_len].chars().count();
     for (anchor_id, anchor) in &styled_text.anchors {
         let id = anchor_id.as_u32() as u64 * 2;
         builder.push_inline_box(InlineBox {
             id,
-            index: anchor.start as usize,
+            index: char_index(anchor.start),
             width: 0.0,
             height: 0.0,
         });
         builder.push_inline_box(InlineBox {
             id: id + 1,
-            index: anchor.end as usize,
+            index: char_index(anchor.end),
             width: 0.0,
             height: 0.0,
         });
     }
-    builder.build(&styled_text.text)
+    // Formulas are pushed after anchors, so an anchor that ends right before
+    // a formula does not contain it
+    for (idx, (inline, (formula, brush))) in styled_text.math.iter().zip(math).enumerate() {
+        builder.push_inline_box(InlineBox {
+            id: MATH_BOX_ID + idx as u64,
+            index: char_index(inline.pos),
+            width: formula.width,
+            height: (formula.ascent + brush.baseline_shift).max(0.0),
+        });
+        // The placeholder itself is not visible
+        let pos = inline.pos as usize + mark_len;
+        builder.push(
+            StyleProperty::FontSize(0.0),
+            pos..pos + MATH_PLACEHOLDER.len_utf8(),
+        );
+    }
+    builder.build(&text)
 }
 
 fn render_decoration(
-    glyph_run: &GlyphRun<Color>,
-    color: Color,
+    x1: f32,
+    x2: f32,
+    baseline: f32,
+    brush: &TextBrush,
     offset: f32,
     width: f32,
-) -> DrawPath {
-    let y = glyph_run.baseline() - offset + width / 2.;
-    let mut builder = PathBuilder::new(FillAndStroke::new_stroke(Stroke {
-        color,
-        width,
-        dash_array: None,
-        dash_offset: 0.0,
-    }));
-    builder.move_to(glyph_run.offset(), y);
-    builder.line_to(glyph_run.offset() + glyph_run.advance(), y);
-    builder.build()
+    out: &mut Vec<DrawPath>,
+) {
+    let color = brush.decoration_color.unwrap_or(brush.color);
+    let width = brush.decoration_width.unwrap_or(width);
+    let y = baseline - offset + width / 2.;
+    let stroke = |dash_array| {
+        FillAndStroke::new_stroke(Stroke {
+            color,
+            width,
+            dash_array,
+            dash_offset: 0.0,
+        })
+    };
+    match brush.decoration_style {
+        DecorationStyle::Solid | DecorationStyle::Dotted | DecorationStyle::Dashed => {
+            let dash_array = match brush.decoration_style {
+                DecorationStyle::Dotted => Some(vec![width, width]),
+                DecorationStyle::Dashed => Some(vec![width * 3.0, width * 2.0]),
+                _ => None,
+            };
+            let mut builder = PathBuilder::new(stroke(dash_array));
+            builder.move_to(x1, y);
+            builder.line_to(x2, y);
+            out.push(builder.build());
+        }
+        DecorationStyle::Double => {
+            let mut builder = PathBuilder::new(stroke(None));
+            builder.move_to(x1, y - width);
+            builder.line_to(x2, y - width);
+            builder.move_to(x1, y + width);
+            builder.line_to(x2, y + width);
+            out.push(builder.build());
+        }
+        DecorationStyle::Wavy => {
+            let half_wave = (width * 2.0).max(1.0);
+            let amplitude = width * 1.5;
+            let mut builder = PathBuilder::new(stroke(None));
+            builder.move_to(x1, y);
+            let mut x = x1;
+            let mut sign = -1.0;
+            while x < x2 {
+                let next_x = (x + half_wave).min(x2);
+                builder.quad_to((x + next_x) / 2.0, y + sign * amplitude, next_x, y);
+                sign = -sign;
+                x = next_x;
+            }
+            out.push(builder.build());
+        }
+    }
 }
 
-fn render_glyph_run(glyph_run: &GlyphRun<Color>, out: &mut Vec<DrawPath>) {
-    let mut run_x = glyph_run.offset();
-    let run_y = glyph_run.baseline();
+fn render_glyph_run(
+    glyph_run: &GlyphRun<TextBrush>,
+    baseline_offset: f32,
+    out: &mut Vec<DrawPath>,
+    images: &mut Vec<GlyphImage>,
+) {
     let style = glyph_run.style();
-    let color = style.brush;
+    let mut run_x = glyph_run.offset();
+    let run_y = glyph_run.baseline() + baseline_offset - style.brush.baseline_shift;
+    let glyphs = glyph_run.glyphs().map(|glyph| {
+        let x = run_x + glyph.x;
+        run_x += glyph.advance;
+        (x, run_y - glyph.y, GlyphId::from(glyph.id))
+    });
     let run = glyph_run.run();
-    let font = run.font();
-    let font_size = run.font_size();
-    let normalized_coords = run
-        .normalized_coords()
+    draw_glyphs(
+        run.font(),
+        run.font_size(),
+        run.normalized_coords(),
+        style.brush.color,
+        glyphs,
+        out,
+        images,
+    );
+    render_run_decorations(
+        style,
+        glyph_run.run().metrics(),
+        glyph_run.offset(),
+        glyph_run.offset() + glyph_run.advance(),
+        run_y,
+        out,
+    );
+}
+
+/// Draws glyphs of a run, each glyph is given together with its pen position.
+/// Outlines of all glyphs are put into a single path, color glyphs
+/// are drawn after it as separate paths or images.
+pub(crate) fn draw_glyphs(
+    font: &Font,
+    font_size: f32,
+    coords: &[i16],
+    color: Color,
+    glyphs: impl Iterator<Item = (f32, f32, GlyphId)>,
+    out: &mut Vec<DrawPath>,
+    images: &mut Vec<GlyphImage>,
+) {
+    let normalized_coords = coords
         .iter()
         .map(|coord| NormalizedCoord::from_bits(*coord))
         .collect::<Vec<_>>();
@@ -214,42 +751,322 @@ fn render_glyph_run(glyph_run: &GlyphRun<Color>, out: &mut Vec<DrawPath>) {
     let font_collection_ref = font.data.as_ref();
     let font_ref = ReadFontsRef::from_index(font_collection_ref, font.index).unwrap();
     let outlines = font_ref.outline_glyphs();
+    let location_ref = LocationRef::new(&normalized_coords);
+    let color_glyphs = ColorGlyphs::new(font_ref.clone(), location_ref, font_size, color);
 
     let mut pen = NelsiePathPen {
         path_builder: PathBuilder::new(FillAndStroke::new_fill(color)),
         x: 0.0,
         y: 0.0,
     };
-    let location_ref = LocationRef::new(&normalized_coords);
-    for glyph in glyph_run.glyphs() {
-        pen.x = run_x + glyph.x;
-        pen.y = run_y - glyph.y;
-        run_x += glyph.advance;
-
-        let glyph_id = GlyphId::from(glyph.id);
+    let mut color_paths = Vec::new();
+    for (x, y, glyph_id) in glyphs {
+        if color_glyphs
+            .as_ref()
+            .is_some_and(|c| c.draw(glyph_id, x, y, &mut color_paths, images))
+        {
+            continue;
+        }
+        pen.x = x;
+        pen.y = y;
         let glyph_outline = outlines.get(glyph_id).unwrap();
 
         let settings = DrawSettings::unhinted(Size::new(font_size), location_ref);
         glyph_outline.draw(settings, &mut pen).unwrap();
     }
     out.push(pen.path_builder.build());
+    out.append(&mut color_paths);
+}
 
-    let style = glyph_run.style();
-    let run_metrics = run.metrics();
+fn render_run_decorations(
+    style: &Style<TextBrush>,
+    run_metrics: &RunMetrics,
+    x1: f32,
+    x2: f32,
+    baseline: f32,
+    out: &mut Vec<DrawPath>,
+) {
     if let Some(decoration) = &style.underline {
         let offset = decoration.offset.unwrap_or(run_metrics.underline_offset);
         let size = decoration.size.unwrap_or(run_metrics.underline_size);
-        out.push(render_decoration(glyph_run, decoration.brush, offset, size));
+        render_decoration(x1, x2, baseline, &decoration.brush, offset, size, out);
     }
     if let Some(decoration) = &style.strikethrough {
         let offset = decoration
             .offset
             .unwrap_or(run_metrics.strikethrough_offset);
         let size = decoration.size.unwrap_or(run_metrics.strikethrough_size);
-        out.push(render_decoration(glyph_run, decoration.brush, offset, size));
+        render_decoration(x1, x2, baseline, &decoration.brush, offset, size, out);
+    }
+    if style.brush.overline {
+        render_decoration(
+            x1,
+            x2,
+            baseline,
+            &style.brush,
+            run_metrics.ascent,
+            run_metrics.underline_size,
+            out,
+        );
+    }
+}
+
+/// Returns true if the cluster is placed upright in vertical text (e.g. CJK characters);
+/// other clusters are rotated by 90 degrees clockwise
+fn is_upright_cluster(cluster: &Cluster<TextBrush>, text: &str, mark_len: usize) -> bool {
+    let start = cluster.text_range().start;
+    start >= mark_len
+        && text[start - mark_len..].chars().next().is_some_and(|ch| {
+            matches!(
+                char_orientation(ch),
+                Orientation::Upright | Orientation::TransformedOrUpright
+            )
+        })
+}
+
+/// Advance of a cluster in vertical text; upright clusters occupy an em box
+fn vertical_advance(cluster: &Cluster<TextBrush>, upright: bool, font_size: f32) -> f32 {
+    if upright && cluster.advance() > 0.0 {
+        font_size
+    } else {
+        cluster.advance()
     }
 }
 
+/// Creates link rectangles for ranges of link anchors, one rectangle for each line
+/// (or column in vertical text) covered by the anchor.
+/// `box_positions` maps ids of inline boxes to their line and position in the line.
+fn link_rects(
+    links: &[(InlineId, String)],
+    box_positions: &HashMap<u64, (usize, f32)>,
+    line_rects: &[Rectangle],
+    vertical: bool,
+) -> Vec<Link> {
+    let mut result = Vec::new();
+    for (anchor_id, url) in links {
+        let id = anchor_id.as_u32() as u64 * 2;
+        let (Some(&(first_line, start_pos)), Some(&(last_line, end_pos))) =
+            (box_positions.get(&id), box_positions.get(&(id + 1)))
+        else {
+            continue;
+        };
+        for (line_idx, line) in line_rects
+            .iter()
+            .enumerate()
+            .take(last_line + 1)
+            .skip(first_line)
+        {
+            let (line_start, line_end) = if vertical {
+                (line.y, line.y + line.height)
+            } else {
+                (line.x, line.x + line.width)
+            };
+            let start = if line_idx == first_line {
+                start_pos
+            } else {
+                line_start
+            };
+            let end = if line_idx == last_line {
+                end_pos
+            } else {
+                line_end
+            };
+            let (start, end) = (start.min(end), start.max(end));
+            if end <= start {
+                continue;
+            }
+            let rect = if vertical {
+                Rectangle::new(line.x, start, line.width, end - start)
+            } else {
+                Rectangle::new(start, line.y, end - start, line.height)
+            };
+            result.push(Link::new(rect, url.clone()));
+        }
+    }
+    result
+}
+
+/// Renders lines of a layout as columns placed from right to left.
+/// Rotated glyphs, decorations and backgrounds are created as in a horizontal line
+/// and then rotated; upright glyphs are centered in the column.
+fn render_vertical_layout(
+    layout: &Layout<TextBrush>,
+    text: &str,
+    mark_len: usize,
+    text_align: TextAlign,
+    links: &[(InlineId, String)],
+) -> (RenderedText, f32, f32) {
+    let lengths: Vec<f32> = layout
+        .lines()
+        .map(|line| {
+            line.runs()
+                .flat_map(|run| {
+                    let font_size = run.font_size();
+                    run.visual_clusters()
+                        .map(|cluster| {
+                            let upright = is_upright_cluster(&cluster, text, mark_len);
+                            vertical_advance(&cluster, upright, font_size)
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .sum()
+        })
+        .collect();
+    let height = lengths.iter().copied().fold(0.0, f32::max);
+    let width: f32 = layout
+        .lines()
+        .map(|line| line.metrics().max_coord - line.metrics().min_coord)
+        .sum();
+
+    let mut inline_rects: HashMap<InlineId, Rectangle> = HashMap::new();
+    let mut box_positions: HashMap<u64, (usize, f32)> = HashMap::new();
+    let mut backgrounds = Vec::new();
+    let mut paths = Vec::new();
+    let mut images = Vec::new();
+    let mut line_rects = Vec::with_capacity(layout.len());
+    let mut column_end = width;
+    for (line, length) in layout.lines().zip(lengths) {
+        let metrics = line.metrics();
+        let column_width = metrics.max_coord - metrics.min_coord;
+        let column_x = column_end - column_width;
+        // The top of the horizontal line box becomes the right side of the column
+        let baseline_x = column_end - (metrics.baseline - metrics.min_coord);
+        let center_x = column_x + column_width / 2.0;
+        column_end = column_x;
+        let top = match text_align {
+            TextAlign::Start => 0.0,
+            TextAlign::Center => (height - length) / 2.0,
+            TextAlign::End => height - length,
+        };
+        let rotate = |a: f32, y: f32| (baseline_x - y, a);
+
+        let mut pos = top;
+        let mut current_run = None;
+        let mut run_glyph_offset = 0;
+        for item in line.items() {
+            match item {
+                PositionedLayoutItem::GlyphRun(glyph_run) => {
+                    let run = glyph_run.run();
+                    if current_run != Some(run.index()) {
+                        current_run = Some(run.index());
+                        run_glyph_offset = 0;
+                    }
+                    let glyph_range =
+                        run_glyph_offset..run_glyph_offset + glyph_run.glyphs().count();
+                    run_glyph_offset = glyph_range.end;
+
+                    let style = glyph_run.style();
+                    let shift = style.brush.baseline_shift;
+                    let font_size = run.font_size();
+                    let run_metrics = run.metrics();
+                    let em_baseline =
+                        font_size * run_metrics.ascent / (run_metrics.ascent + run_metrics.descent);
+                    let start = pos;
+                    let mut upright_glyphs = Vec::new();
+                    let mut rotated_glyphs = Vec::new();
+                    let mut glyph_index = 0;
+                    for cluster in run.visual_clusters() {
+                        let first_glyph = glyph_index;
+                        glyph_index += cluster.glyphs().count();
+                        if !glyph_range.contains(&first_glyph) {
+                            continue;
+                        }
+                        let upright = is_upright_cluster(&cluster, text, mark_len);
+                        let mut x = 0.0;
+                        for glyph in cluster.glyphs() {
+                            if upright {
+                                upright_glyphs.push((
+                                    center_x - cluster.advance() / 2.0 + x + glyph.x + shift,
+                                    pos + em_baseline - glyph.y,
+                                    GlyphId::from(glyph.id),
+                                ));
+                            } else {
+                                rotated_glyphs.push((
+                                    pos + x + glyph.x,
+                                    -shift - glyph.y,
+                                    GlyphId::from(glyph.id),
+                                ));
+                            }
+                            x += glyph.advance;
+                        }
+                        pos += vertical_advance(&cluster, upright, font_size);
+                    }
+
+                    if let Some(bg_color) = style.brush.bg_color {
+                        backgrounds.push(rect_path(
+                            &Rectangle::new(column_x, start, column_width, pos - start),
+                            bg_color,
+                        ));
+                    }
+                    let color = style.brush.color;
+                    if !upright_glyphs.is_empty() {
+                        draw_glyphs(
+                            run.font(),
+                            font_size,
+                            run.normalized_coords(),
+                            color,
+                            upright_glyphs.into_iter(),
+                            &mut paths,
+                            &mut images,
+                        );
+                    }
+                    let first_rotated = paths.len();
+                    let first_rotated_image = images.len();
+                    if !rotated_glyphs.is_empty() {
+                        draw_glyphs(
+                            run.font(),
+                            font_size,
+                            run.normalized_coords(),
+                            color,
+                            rotated_glyphs.into_iter(),
+                            &mut paths,
+                            &mut images,
+                        );
+                    }
+                    render_run_decorations(style, run_metrics, start, pos, -shift, &mut paths);
+                    for path in &mut paths[first_rotated..] {
+                        path.transform_points(rotate);
+                    }
+                    // Images are not rotated, they are only moved into the column
+                    for image in &mut images[first_rotated_image..] {
+                        let r = &image.rect;
+                        let (x, y) = rotate(r.x, r.y + r.height);
+                        image.rect = Rectangle::new(x, y, r.width, r.height);
+                    }
+                }
+                PositionedLayoutItem::InlineBox(inline_box) => {
+                    box_positions.insert(inline_box.id, (line_rects.len(), pos));
+                    let id = InlineId::new((inline_box.id / 2) as u32);
+                    if let Some(r) = inline_rects.get_mut(&id) {
+                        let y1 = (r.y + r.height).max(pos);
+                        r.y = r.y.min(pos);
+                        r.height = y1 - r.y;
+                    } else {
+                        inline_rects.insert(id, Rectangle::new(column_x, pos, column_width, 0.0));
+                    }
+                }
+            }
+        }
+        line_rects.push(Rectangle::new(column_x, top, column_width, length));
+    }
+    if !backgrounds.is_empty() {
+        backgrounds.append(&mut paths);
+        paths = backgrounds;
+    }
+    let links = link_rects(links, &box_positions, &line_rects, true);
+    (
+        RenderedText {
+            paths,
+            images,
+            line_rects,
+            inline_rects,
+            links,
+        },
+        width,
+        height,
+    )
+}
+
 struct NelsiePathPen {
     path_builder: PathBuilder,
     x: f32,
@@ -301,21 +1118,29 @@ fn font_stretch_to_parley(stretch: FontStretch) -> FontWidth {
 }
 
 fn set_text_style_to_parley(
-    builder: &mut RangedBuilder<Color>,
+    builder: &mut RangedBuilder<TextBrush>,
     text_style: &TextStyle,
     start: usize,
     end: usize,
 ) {
+    // Color, size and other brush properties are resolved in `resolve_ranges`
     let TextStyle {
         font,
-        color,
-        size,
+        color: _,
+        size: _,
         line_spacing,
         italic,
         stretch,
         weight,
         underline,
+        overline: _,
         line_through,
+        bg_color: _,
+        decoration_color: _,
+        decoration_width: _,
+        decoration_style: _,
+        baseline_shift: _,
+        script: _,
     } = text_style;
 
     if let Some(font) = font {
@@ -325,14 +1150,6 @@ fn set_text_style_to_parley(
         );
     }
 
-    if let Some(color) = *color {
-        builder.push(StyleProperty::Brush(color), start..end);
-    }
-
-    if let Some(size) = size {
-        builder.push(StyleProperty::FontSize(size.get()), start..end);
-    }
-
     if let Some(line_spacing) = line_spacing {
         builder.push_default(StyleProperty::LineHeight(line_spacing.get()));
     }
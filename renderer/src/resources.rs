@@ -1,10 +1,22 @@
+use crate::Color;
 use parley::fontique::{Collection, CollectionOptions, SourceCache};
 use parley::{FontContext, GenericFamily};
 use resvg::usvg::fontdb;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use syntect::highlighting::ThemeSet;
-use syntect::parsing::SyntaxSet;
+use syntect::parsing::{SyntaxDefinition, SyntaxSet};
+
+/// Colors defined by a code theme for the whole editor (not for particular tokens)
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ThemeColors {
+    pub background: Option<Color>,
+    pub foreground: Option<Color>,
+    pub selection: Option<Color>,
+    pub line_highlight: Option<Color>,
+    pub gutter: Option<Color>,
+    pub gutter_foreground: Option<Color>,
+}
 
 pub struct Resources {
     // // FontContext is needed for parley (normal text rendering)
@@ -15,6 +27,27 @@ pub struct Resources {
     // pub image_manager: ImageManager,
     pub syntax_set: SyntaxSet,
     pub theme_set: ThemeSet,
+    /// Files of syntaxes and themes from loaded directories that failed to load (path, error)
+    pub code_load_failures: Vec<(String, String)>,
+}
+
+/// Files with the given extension in a directory and its subdirectories
+fn find_files(dir: &Path, extension: &str, output: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_files(&path, extension, output)?;
+        } else if path.extension().and_then(|ext| ext.to_str()) == Some(extension) {
+            output.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn file_stem(path: &Path) -> crate::Result<&str> {
+    path.file_stem()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| crate::Error::Generic(format!("Invalid file name: {}", path.display())))
 }
 
 impl Resources {
@@ -51,6 +84,7 @@ impl Resources {
             font_db: Some(Arc::new(font_db)),
             syntax_set,
             theme_set,
+            code_load_failures: Vec::new(),
         }
     }
 
@@ -68,22 +102,132 @@ impl Resources {
         Ok(())
     }
 
-    pub fn load_code_syntax_dir(&mut self, path: &Path) -> crate::Result<()> {
-        log::debug!("Adding code syntax directory {}", path.display());
+    pub fn theme_colors(&self, theme_name: &str) -> crate::Result<ThemeColors> {
+        let settings = &self
+            .theme_set
+            .themes
+            .get(theme_name)
+            .ok_or_else(|| crate::Error::generic_err(format!("Theme '{theme_name}' not found")))?
+            .settings;
+        Ok(ThemeColors {
+            background: settings.background.map(Color::from),
+            foreground: settings.foreground.map(Color::from),
+            selection: settings.selection.map(Color::from),
+            line_highlight: settings.line_highlight.map(Color::from),
+            gutter: settings.gutter.map(Color::from),
+            gutter_foreground: settings.gutter_foreground.map(Color::from),
+        })
+    }
+
+    /// Languages supported by the tree-sitter syntax highlighting backend
+    pub fn tree_sitter_languages(&self) -> Vec<&'static str> {
+        #[cfg(feature = "tree-sitter")]
+        return crate::textutils::treesitter::tree_sitter_languages().collect();
+        #[cfg(not(feature = "tree-sitter"))]
+        Vec::new()
+    }
+
+    fn add_syntaxes(&mut self, syntaxes: Vec<SyntaxDefinition>) {
         let syntax_set = std::mem::take(&mut self.syntax_set);
         let mut builder = syntax_set.into_builder();
-        builder
-            .add_from_folder(path, false)
-            .map_err(|e| crate::Error::Generic(format!("Adding syntax failed: {}", e)))?;
+        for syntax in syntaxes {
+            builder.add(syntax);
+        }
         self.syntax_set = builder.build();
+    }
+
+    fn parse_syntax(source: &str, fallback_name: Option<&str>) -> crate::Result<SyntaxDefinition> {
+        // Default syntaxes are loaded in "nonewlines" mode, so the same is used for added syntaxes
+        SyntaxDefinition::load_from_str(source, false, fallback_name)
+            .map_err(|e| crate::Error::Generic(format!("Adding syntax failed: {}", e)))
+    }
+
+    fn read_syntax_file(path: &Path) -> crate::Result<SyntaxDefinition> {
+        Self::parse_syntax(&std::fs::read_to_string(path)?, Some(file_stem(path)?))
+    }
+
+    /// Loads all .sublime-syntax files in the directory (and subdirectories);
+    /// files that cannot be loaded are recorded into `code_load_failures`
+    pub fn load_code_syntax_dir(&mut self, path: &Path) -> crate::Result<()> {
+        log::debug!("Adding code syntax directory {}", path.display());
+        let mut paths = Vec::new();
+        find_files(path, "sublime-syntax", &mut paths)?;
+        paths.sort();
+        let mut syntaxes = Vec::new();
+        for path in paths {
+            match Self::read_syntax_file(&path) {
+                Ok(syntax) => syntaxes.push(syntax),
+                Err(e) => {
+                    log::warn!("Syntax {} cannot be loaded: {}", path.display(), e);
+                    self.code_load_failures
+                        .push((path.display().to_string(), e.to_string()));
+                }
+            }
+        }
+        self.add_syntaxes(syntaxes);
+        Ok(())
+    }
+
+    pub fn load_code_syntax_file(&mut self, path: &Path) -> crate::Result<()> {
+        log::debug!("Adding code syntax {}", path.display());
+        let syntax = Self::read_syntax_file(path)?;
+        self.add_syntaxes(vec![syntax]);
+        Ok(())
+    }
+
+    /// Adds a syntax from a content of a .sublime-syntax file
+    pub fn load_code_syntax_str(&mut self, source: &str) -> crate::Result<()> {
+        let syntax = Self::parse_syntax(source, None)?;
+        self.add_syntaxes(vec![syntax]);
         Ok(())
     }
 
+    /// Stores the current syntaxes in a compiled binary form
+    pub fn dump_code_syntaxes(&self, path: &Path) -> crate::Result<()> {
+        syntect::dumps::dump_to_file(&self.syntax_set, path)
+            .map_err(|e| crate::Error::Generic(format!("Dumping syntaxes failed: {}", e)))
+    }
+
+    /// Replaces the current syntaxes by syntaxes stored by `dump_code_syntaxes`
+    pub fn load_code_syntax_dump(&mut self, path: &Path) -> crate::Result<()> {
+        log::debug!("Loading code syntax dump {}", path.display());
+        self.syntax_set = syntect::dumps::from_dump_file(path)
+            .map_err(|e| crate::Error::Generic(format!("Loading syntax dump failed: {}", e)))?;
+        Ok(())
+    }
+
+    /// Loads all .tmTheme files in the directory (and subdirectories), a theme is named by its file name;
+    /// files that cannot be loaded are recorded into `code_load_failures`
     pub fn load_code_theme_dir(&mut self, path: &Path) -> crate::Result<()> {
         log::debug!("Adding code theme directory {}", path.display());
+        let paths = ThemeSet::discover_theme_paths(path)
+            .map_err(|e| crate::Error::Generic(format!("Adding theme failed: {}", e)))?;
+        for path in paths {
+            if let Err(e) = self.load_code_theme_file(&path) {
+                log::warn!("Theme {} cannot be loaded: {}", path.display(), e);
+                self.code_load_failures
+                    .push((path.display().to_string(), e.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a theme named by the file name
+    pub fn load_code_theme_file(&mut self, path: &Path) -> crate::Result<()> {
+        log::debug!("Adding code theme {}", path.display());
+        let theme = ThemeSet::get_theme(path)
+            .map_err(|e| crate::Error::Generic(format!("Adding theme failed: {}", e)))?;
         self.theme_set
-            .add_from_folder(path)
+            .themes
+            .insert(file_stem(path)?.to_string(), theme);
+        Ok(())
+    }
+
+    /// Adds a theme from a content of a .tmTheme file
+    pub fn load_code_theme_str(&mut self, name: &str, source: &str) -> crate::Result<()> {
+        let theme = ThemeSet::load_from_reader(&mut std::io::Cursor::new(source.as_bytes()))
             .map_err(|e| crate::Error::Generic(format!("Adding theme failed: {}", e)))?;
+        self.theme_set.themes.insert(name.to_string(), theme);
         Ok(())
     }
 
@@ -1,8 +1,9 @@
 use crate::render::arrows::create_arrow;
+use crate::render::connector::route_connector;
 use crate::render::draw::{DrawPath, DrawRect, PathBuilder};
 use crate::render::layout::ComputedLayout;
 use crate::types::LayoutExpr;
-use crate::{Color, NodeId, Rectangle};
+use crate::{Color, NodeId, Rectangle, ShapeId};
 //use crate::render::arrows::{create_arrow, move_point_for_arrow};
 
 #[derive(Clone, Debug, PartialEq)]
@@ -42,6 +43,8 @@ pub struct ShapeRect {
     pub y2: LayoutExpr,
     pub z_level: i32,
     pub fill_and_stroke: FillAndStroke,
+    /// Layout expressions may refer to the shape through this id
+    pub shape_id: Option<ShapeId>,
 }
 
 impl ShapeRect {
@@ -60,6 +63,7 @@ impl ShapeRect {
             y2,
             z_level,
             fill_and_stroke,
+            shape_id: None,
         }
     }
 
@@ -85,6 +89,41 @@ pub enum Shape {
     Rect(ShapeRect),
     Oval(ShapeRect),
     Path(Path),
+    Connector(Connector),
+}
+
+impl Shape {
+    pub fn shape_id(&self) -> Option<ShapeId> {
+        match self {
+            Shape::Rect(rect) | Shape::Oval(rect) => rect.shape_id,
+            Shape::Path(path) => path.shape_id,
+            Shape::Connector(connector) => connector.shape_id,
+        }
+    }
+
+    pub(crate) fn layout_exprs(&self) -> Vec<&LayoutExpr> {
+        match self {
+            Shape::Rect(rect) | Shape::Oval(rect) => vec![&rect.x1, &rect.y1, &rect.x2, &rect.y2],
+            Shape::Path(path) => path
+                .parts
+                .iter()
+                .flat_map(|part| match part {
+                    PathPart::Move { x, y } | PathPart::Line { x, y } => vec![x, y],
+                    PathPart::Quad { x1, y1, x, y } => vec![x1, y1, x, y],
+                    PathPart::Cubic {
+                        x1,
+                        y1,
+                        x2,
+                        y2,
+                        x,
+                        y,
+                    } => vec![x1, y1, x2, y2, x, y],
+                    PathPart::Close => vec![],
+                })
+                .collect(),
+            Shape::Connector(_) => vec![],
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -142,14 +181,13 @@ pub struct Path {
     pub arrow_start: Option<Arrow>,
     pub arrow_end: Option<Arrow>,
     pub z_level: i32,
+    /// Layout expressions may refer to the path through this id
+    pub shape_id: Option<ShapeId>,
 }
 
 impl Path {
-    pub(crate) fn eval(
-        &self,
-        layout: &ComputedLayout,
-        parent_id: NodeId,
-    ) -> (Option<DrawPath>, Option<DrawPath>, Option<DrawPath>) {
+    /// Evaluates the path without arrows
+    pub(crate) fn build(&self, layout: &ComputedLayout, parent_id: NodeId) -> DrawPath {
         let mut builder = PathBuilder::new(self.fill_and_stroke.clone());
         for part in &self.parts {
             match part {
@@ -183,36 +221,100 @@ impl Path {
                 PathPart::Close => builder.close(),
             }
         }
-        let mut path = builder.build();
-        if path.parts.is_empty() {
-            return (None, None, None);
-        }
-        let arrow1 = self.arrow_start.as_ref().and_then(|a| {
-            let mut i = path.parts.iter_mut();
-            create_arrow(
-                a,
-                i.next().unwrap(),
-                i.next().as_deref(),
-                path.fill_and_stroke.stroke.as_ref().map(|s| s.color),
-            )
-        });
-        let arrow2 = self.arrow_end.as_ref().and_then(|a| {
-            let mut i = path.parts.iter_mut().rev();
-            create_arrow(
-                a,
-                i.next().unwrap(),
-                i.next().as_deref(),
-                path.fill_and_stroke.stroke.as_ref().map(|s| s.color),
-            )
-        });
-        (Some(path), arrow1, arrow2)
-        // let arrow_start = create_arrow(self, layout, parent_id, true);
-        // let arrow_end = create_arrow(self, layout, parent_id, true);
-        //
-        // (builder.build(),
-        //     create_arrow(path, layout, parent_id, true);
-        //     create_arrow(path, layout, parent_id, false);
-        // )
-        // }
+        builder.build()
+    }
+
+    pub(crate) fn eval(
+        &self,
+        layout: &ComputedLayout,
+        parent_id: NodeId,
+    ) -> (Option<DrawPath>, Option<DrawPath>, Option<DrawPath>) {
+        attach_arrows(
+            self.build(layout, parent_id),
+            self.arrow_start.as_ref(),
+            self.arrow_end.as_ref(),
+        )
+    }
+}
+
+/// Shortens the path ends by arrows; returns the path and paths of arrows
+fn attach_arrows(
+    mut path: DrawPath,
+    arrow_start: Option<&Arrow>,
+    arrow_end: Option<&Arrow>,
+) -> (Option<DrawPath>, Option<DrawPath>, Option<DrawPath>) {
+    if path.parts.is_empty() {
+        return (None, None, None);
+    }
+    let arrow1 = arrow_start.and_then(|a| {
+        let mut i = path.parts.iter_mut();
+        create_arrow(
+            a,
+            i.next().unwrap(),
+            i.next().as_deref(),
+            path.fill_and_stroke.stroke.as_ref().map(|s| s.color),
+        )
+    });
+    let arrow2 = arrow_end.and_then(|a| {
+        let mut i = path.parts.iter_mut().rev();
+        create_arrow(
+            a,
+            i.next().unwrap(),
+            i.next().as_deref(),
+            path.fill_and_stroke.stroke.as_ref().map(|s| s.color),
+        )
+    });
+    (Some(path), arrow1, arrow2)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ConnectorSide {
+    Top,
+    Right,
+    Bottom,
+    Left,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ConnectorRouting {
+    /// A straight line; ends without a given side are placed on the line between centers of nodes
+    Straight,
+    /// Horizontal and vertical line segments
+    Orthogonal,
+    /// A cubic curve leaving and entering nodes perpendicularly to their sides
+    Curved,
+}
+
+/// A path between two nodes that starts and ends on their borders
+#[derive(Debug)]
+pub struct Connector {
+    pub start: NodeId,
+    pub end: NodeId,
+    /// `None` chooses a side by positions of nodes
+    pub start_side: Option<ConnectorSide>,
+    pub end_side: Option<ConnectorSide>,
+    pub routing: ConnectorRouting,
+    pub fill_and_stroke: FillAndStroke,
+    pub arrow_start: Option<Arrow>,
+    pub arrow_end: Option<Arrow>,
+    pub z_level: i32,
+    pub shape_id: Option<ShapeId>,
+}
+
+impl Connector {
+    /// Evaluates the connector without arrows
+    pub(crate) fn build(&self, layout: &ComputedLayout) -> DrawPath {
+        route_connector(self, layout)
+    }
+
+    pub(crate) fn eval(
+        &self,
+        layout: &ComputedLayout,
+    ) -> (Option<DrawPath>, Option<DrawPath>, Option<DrawPath>) {
+        attach_arrows(
+            self.build(layout),
+            self.arrow_start.as_ref(),
+            self.arrow_end.as_ref(),
+        )
     }
 }
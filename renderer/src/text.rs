@@ -1,5 +1,6 @@
 use crate::Color;
 use resvg::usvg::PositiveF32;
+use resvg::usvg::strict_num::FiniteF32;
 use std::num::ParseIntError;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -36,13 +37,47 @@ pub struct TextStyle {
     pub stretch: Option<FontStretch>,
     pub weight: Option<u16>,
     pub underline: Option<bool>,
+    pub overline: Option<bool>,
     pub line_through: Option<bool>,
+    pub bg_color: Option<Color>,
+    pub decoration_color: Option<Color>,
+    pub decoration_width: Option<PositiveF32>,
+    pub decoration_style: Option<DecorationStyle>,
+    pub baseline_shift: Option<FiniteF32>,
+    pub script: Option<ScriptPosition>,
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Default)]
+pub enum ScriptPosition {
+    #[default]
+    Normal,
+    Super,
+    Sub,
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Default)]
+pub enum DecorationStyle {
+    #[default]
+    Solid,
+    Double,
+    Dotted,
+    Dashed,
+    Wavy,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SyntaxHighlightBackend {
+    #[default]
+    Syntect,
+    /// Needs feature "tree-sitter"; only bundled grammars are available
+    TreeSitter,
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct SyntaxHighlightSettings {
     pub language: String,
     pub theme: String,
+    pub backend: SyntaxHighlightBackend,
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
@@ -66,11 +101,113 @@ pub enum TextAlign {
     End,
 }
 
+/// Base direction of a paragraph
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Default)]
+pub enum TextDirection {
+    /// Direction is detected from the first strong character
+    #[default]
+    Auto,
+    Ltr,
+    Rtl,
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Default)]
+pub enum WritingMode {
+    #[default]
+    Horizontal,
+    /// Lines are top-to-bottom columns placed from right to left
+    Vertical,
+}
+
+/// Numbers placed in front of lines of a text; when the style has no color,
+/// the gutter foreground of the syntax highlighting theme is used
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct LineNumbers {
+    /// Number of the first line
+    pub start: u32,
+    pub style: TextStyle,
+    /// Text between a number and a line
+    pub separator: String,
+}
+
+/// Background of lines `start..end` (indices of lines, starting from 0)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LineHighlight {
+    pub start: u32,
+    pub end: u32,
+    /// If `None`, the selection (or line highlight) color of the syntax highlighting theme is used
+    pub color: Option<Color>,
+}
+
+/// Range of a text that is found without a markup in the text
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AnnotationTarget {
+    /// Line and columns (in characters) counted from 0; the end column is exclusive
+    Position {
+        line: u32,
+        start_column: u32,
+        end_column: u32,
+    },
+    /// The first match of a regular expression, or of its first group if it has any
+    Regex(String),
+}
+
+/// Creates an inline anchor over a range of a text (if the target is found)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TextAnnotation {
+    pub anchor_id: InlineId,
+    pub target: AnnotationTarget,
+    /// Style applied on the range over the other styles
+    pub style: Option<TextStyle>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum DiffLine {
+    Unchanged,
+    Added,
+    Removed,
+}
+
+/// Text shown as a diff; each line gets a marker and added and removed lines get a background
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TextDiff {
+    /// Kind of each line of the text
+    pub lines: Vec<DiffLine>,
+    /// Background of added lines; its opaque variant is used for markers
+    pub added_color: Color,
+    /// Background of removed lines; its opaque variant is used for markers
+    pub removed_color: Color,
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct Text {
     pub text: String,
     pub style: TextStyle,
     pub styling: Option<TextStyling>,
     pub text_align: TextAlign,
+    pub direction: TextDirection,
+    pub writing_mode: WritingMode,
     pub syntax_highlight: Option<SyntaxHighlightSettings>,
+    /// Formulas between '$' are typeset as math
+    pub parse_math: bool,
+    /// Text is parsed as Markdown instead of the style markup; spans are styled
+    /// by named styles "emph", "strong", "code", "link" and "h1" - "h6"
+    pub markdown: bool,
+    /// Urls attached to ranges of inline anchors
+    pub links: Vec<(InlineId, String)>,
+    pub annotations: Vec<TextAnnotation>,
+    /// Removed lines of a diff are not numbered
+    pub line_numbers: Option<LineNumbers>,
+    pub diff: Option<TextDiff>,
+    /// Highlights are drawn behind glyphs, in the given order
+    pub line_highlights: Vec<LineHighlight>,
+}
+
+/// Math formula written in a subset of TeX
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Math {
+    pub formula: String,
+    pub style: TextStyle,
+    /// Display style (e.g. limits of sums are placed above and below)
+    pub display: bool,
 }
@@ -0,0 +1,104 @@
+use crate::text::{AnnotationTarget, TextAnnotation};
+use crate::textutils::styling::{InlineAnchor, StyledRange, StyledText};
+use regex::Regex;
+
+/// Byte range of a target; `None` if the target is not found
+fn find_target(text: &str, target: &AnnotationTarget) -> crate::Result<Option<(usize, usize)>> {
+    Ok(match target {
+        AnnotationTarget::Position {
+            line,
+            start_column,
+            end_column,
+        } => {
+            let Some((line_start, line)) = text
+                .split('\n')
+                .scan(0, |pos, line| {
+                    let start = *pos;
+                    *pos += line.len() + 1;
+                    Some((start, line))
+                })
+                .nth(*line as usize)
+            else {
+                return Ok(None);
+            };
+            let column_offset = |column: u32| {
+                line.char_indices()
+                    .map(|(idx, _)| idx)
+                    .chain(std::iter::once(line.len()))
+                    .nth(column as usize)
+                    .map(|idx| line_start + idx)
+            };
+            column_offset(*start_column)
+                .zip(column_offset(*end_column))
+                .filter(|(start, end)| start <= end)
+        }
+        AnnotationTarget::Regex(pattern) => {
+            let regex = Regex::new(pattern).map_err(|e| {
+                crate::Error::parsing_err(format!("Invalid pattern '{pattern}': {e}"))
+            })?;
+            regex.captures(text).map(|captures| {
+                let m = captures.get(1).unwrap_or_else(|| captures.get(0).unwrap());
+                (m.start(), m.end())
+            })
+        }
+    })
+}
+
+/// Creates anchors (and styles) of annotations; targets that are not found are skipped
+pub(crate) fn apply_annotations(
+    styled_text: &mut StyledText,
+    annotations: &[TextAnnotation],
+) -> crate::Result<()> {
+    for annotation in annotations {
+        let Some((start, end)) = find_target(&styled_text.text, &annotation.target)? else {
+            continue;
+        };
+        let (start, end) = (start as u32, end as u32);
+        styled_text
+            .anchors
+            .push((annotation.anchor_id, InlineAnchor { start, end }));
+        if let Some(style) = &annotation.style {
+            styled_text.styles.push(StyledRange {
+                start,
+                end,
+                style: style.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(line: u32, start_column: u32, end_column: u32) -> AnnotationTarget {
+        AnnotationTarget::Position {
+            line,
+            start_column,
+            end_column,
+        }
+    }
+
+    #[test]
+    fn test_find_position() {
+        let text = "ab\nčdef\n";
+        assert_eq!(find_target(text, &position(0, 0, 2)).unwrap(), Some((0, 2)));
+        assert_eq!(find_target(text, &position(1, 1, 3)).unwrap(), Some((5, 7)));
+        assert_eq!(find_target(text, &position(1, 0, 4)).unwrap(), Some((3, 8)));
+        assert_eq!(find_target(text, &position(2, 0, 0)).unwrap(), Some((9, 9)));
+        assert_eq!(find_target(text, &position(1, 0, 5)).unwrap(), None);
+        assert_eq!(find_target(text, &position(1, 2, 1)).unwrap(), None);
+        assert_eq!(find_target(text, &position(3, 0, 0)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_find_regex() {
+        let text = "let x = 1;\nlet y = x;";
+        let regex = |s: &str| AnnotationTarget::Regex(s.to_string());
+        assert_eq!(find_target(text, &regex("x")).unwrap(), Some((4, 5)));
+        assert_eq!(find_target(text, &regex(r"= (x)")).unwrap(), Some((19, 20)));
+        assert_eq!(find_target(text, &regex("z")).unwrap(), None);
+        assert!(find_target(text, &regex("(")).is_err());
+    }
+}
@@ -0,0 +1,642 @@
+use crate::text::{InlineId, TextStyle};
+use crate::textutils::styling::{InlineAnchor, InlineMath, MATH_PLACEHOLDER, StyledRange};
+use std::borrow::Cow;
+use std::cmp::Reverse;
+
+/// Links get anchors with ids starting here, so they do not collide with user anchors
+pub(crate) const LINK_ANCHOR_BASE: u32 = 1 << 31;
+
+#[derive(Debug, Clone, PartialEq)]
+enum SpanKind {
+    Emph,
+    Strong,
+    Code,
+    Link(String),
+    Heading(u8),
+}
+
+impl SpanKind {
+    fn style_name(&self) -> Cow<'static, str> {
+        match self {
+            SpanKind::Emph => Cow::Borrowed("emph"),
+            SpanKind::Strong => Cow::Borrowed("strong"),
+            SpanKind::Code => Cow::Borrowed("code"),
+            SpanKind::Link(_) => Cow::Borrowed("link"),
+            SpanKind::Heading(level) => Cow::Owned(format!("h{level}")),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Span {
+    kind: SpanKind,
+    start: u32,
+    end: u32,
+}
+
+/// Run of '*' or '_' characters that may open or close emphasis
+#[derive(Debug)]
+struct Delimiter {
+    ch: char,
+    count: usize,
+    orig_count: usize,
+    can_open: bool,
+    can_close: bool,
+    opens: Vec<usize>,
+    closes: Vec<usize>,
+}
+
+#[derive(Debug)]
+enum Token {
+    Text(String),
+    Delimiter(Delimiter),
+    /// '[' that may become the start of a link
+    Bracket,
+    Start(usize),
+    End(usize),
+    /// Placeholder of the formula with the given index
+    Math(usize),
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Markdown {
+    pub text: String,
+    pub styles: Vec<StyledRange>,
+    pub anchors: Vec<(InlineId, InlineAnchor)>,
+    pub links: Vec<(InlineId, String)>,
+    pub math: Vec<InlineMath>,
+}
+
+fn is_punctuation(c: char) -> bool {
+    !c.is_alphanumeric() && !c.is_whitespace()
+}
+
+fn push_text(tokens: &mut Vec<Token>, text: &str) {
+    if let Some(Token::Text(s)) = tokens.last_mut() {
+        s.push_str(text);
+    } else {
+        tokens.push(Token::Text(text.to_string()));
+    }
+}
+
+fn push_char(tokens: &mut Vec<Token>, c: char) {
+    if let Some(Token::Text(s)) = tokens.last_mut() {
+        s.push(c);
+    } else {
+        tokens.push(Token::Text(c.to_string()));
+    }
+}
+
+/// Pushes text that starts at `offset` of the whole input; placeholders of formulas
+/// become math tokens, other characters are kept as they are
+fn push_text_with_math(tokens: &mut Vec<Token>, text: &str, offset: usize, math: &[InlineMath]) {
+    for (idx, c) in text.char_indices() {
+        let formula = (c == MATH_PLACEHOLDER)
+            .then(|| math.iter().position(|m| m.pos as usize == offset + idx))
+            .flatten();
+        if let Some(formula) = formula {
+            tokens.push(Token::Math(formula));
+        } else {
+            push_char(tokens, c);
+        }
+    }
+}
+
+/// Parses "(destination "title")" that follows a link text; returns the destination
+/// and the rest of the input
+fn parse_link_destination(input: &str) -> Option<(String, &str)> {
+    let input = input.strip_prefix('(')?.trim_start();
+    let (url, rest) = if let Some(rest) = input.strip_prefix('<') {
+        let end = rest.find(['>', '\n'])?;
+        if !rest[end..].starts_with('>') {
+            return None;
+        }
+        (rest[..end].to_string(), &rest[end + 1..])
+    } else {
+        let mut depth = 0;
+        let end = input
+            .char_indices()
+            .find(|(_, c)| match c {
+                '(' => {
+                    depth += 1;
+                    false
+                }
+                ')' if depth == 0 => true,
+                ')' => {
+                    depth -= 1;
+                    false
+                }
+                c => c.is_whitespace(),
+            })
+            .map(|(i, _)| i)?;
+        (input[..end].to_string(), &input[end..])
+    };
+    let mut rest = rest.trim_start();
+    if let Some(quote) = rest.chars().next().filter(|c| matches!(c, '"' | '\'')) {
+        let end = rest[1..].find(quote)?;
+        rest = rest[end + 2..].trim_start();
+    }
+    Some((url, rest.strip_prefix(')')?))
+}
+
+/// Parses "<scheme:...>" or "<user@host>"; returns the url and the rest of the input
+fn parse_autolink(input: &str) -> Option<(String, &str)> {
+    let end = input.find('>')?;
+    let content = &input[1..end];
+    if content.is_empty() || content.contains(|c: char| c.is_whitespace() || c == '<') {
+        return None;
+    }
+    let rest = &input[end + 1..];
+    if let Some((scheme, _)) = content.split_once(':') {
+        let mut chars = scheme.chars();
+        if (2..=32).contains(&scheme.len())
+            && chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+            && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '.' | '-'))
+        {
+            return Some((content.to_string(), rest));
+        }
+        None
+    } else if content.contains('@') {
+        Some((format!("mailto:{content}"), rest))
+    } else {
+        None
+    }
+}
+
+/// Resolves emphasis between delimiters in `tokens` (the "process emphasis"
+/// procedure from CommonMark)
+fn process_emphasis(tokens: &mut [Token], spans: &mut Vec<Span>) {
+    for closer_idx in 0..tokens.len() {
+        while let Token::Delimiter(closer) = &tokens[closer_idx] {
+            if !closer.can_close || closer.count == 0 {
+                break;
+            }
+            let opener_idx = (0..closer_idx).rev().find(|idx| {
+                let Token::Delimiter(opener) = &tokens[*idx] else {
+                    return false;
+                };
+                opener.ch == closer.ch
+                    && opener.can_open
+                    && opener.count > 0
+                    && !((opener.can_close || closer.can_open)
+                        && (opener.orig_count + closer.orig_count) % 3 == 0
+                        && !(opener.orig_count % 3 == 0 && closer.orig_count % 3 == 0))
+            });
+            let Some(opener_idx) = opener_idx else {
+                if let Token::Delimiter(closer) = &mut tokens[closer_idx]
+                    && !closer.can_open
+                {
+                    closer.can_close = false;
+                }
+                break;
+            };
+            let (left, right) = tokens.split_at_mut(closer_idx);
+            let (Token::Delimiter(opener), Token::Delimiter(closer)) =
+                (&mut left[opener_idx], &mut right[0])
+            else {
+                unreachable!()
+            };
+            let (used, kind) = if opener.count >= 2 && closer.count >= 2 {
+                (2, SpanKind::Strong)
+            } else {
+                (1, SpanKind::Emph)
+            };
+            opener.count -= used;
+            closer.count -= used;
+            opener.opens.push(spans.len());
+            closer.closes.push(spans.len());
+            spans.push(Span {
+                kind,
+                start: 0,
+                end: 0,
+            });
+            for token in &mut left[opener_idx + 1..] {
+                if let Token::Delimiter(d) = token {
+                    d.can_open = false;
+                    d.can_close = false;
+                }
+            }
+        }
+    }
+    for token in tokens.iter_mut() {
+        if let Token::Delimiter(d) = token {
+            d.can_open = false;
+            d.can_close = false;
+        }
+    }
+}
+
+/// `offset` is the position of `input` in the whole input, positions of formulas refer to it
+fn parse_inline(
+    input: &str,
+    offset: usize,
+    math: &[InlineMath],
+    tokens: &mut Vec<Token>,
+    spans: &mut Vec<Span>,
+) {
+    let bottom = tokens.len();
+    let position = |rest: &str| offset + input.len() - rest.len();
+    // Token indices of '[' that may start a link
+    let mut brackets: Vec<(usize, bool)> = Vec::new();
+    let mut prev_char: Option<char> = None;
+    let mut rest = input;
+    while let Some(c) = rest.chars().next() {
+        let mut last_char = c;
+        match c {
+            '\\' if rest[1..].starts_with(|c: char| c.is_ascii_punctuation()) => {
+                last_char = rest[1..].chars().next().unwrap();
+                push_char(tokens, last_char);
+                rest = &rest[2..];
+            }
+            '`' => {
+                let n = rest.len() - rest.trim_start_matches('`').len();
+                let after = &rest[n..];
+                let mut search = 0;
+                let mut closing = None;
+                while let Some(idx) = after[search..].find('`') {
+                    let start = search + idx;
+                    let len = after[start..].len() - after[start..].trim_start_matches('`').len();
+                    if len == n {
+                        closing = Some(start);
+                        break;
+                    }
+                    search = start + len;
+                }
+                if let Some(end) = closing {
+                    let mut code = after[..end].replace('\n', " ");
+                    let mut code_offset = position(after);
+                    if code.len() >= 2
+                        && code.starts_with(' ')
+                        && code.ends_with(' ')
+                        && !code.chars().all(|c| c == ' ')
+                    {
+                        code = code[1..code.len() - 1].to_string();
+                        code_offset += 1;
+                    }
+                    tokens.push(Token::Start(spans.len()));
+                    push_text_with_math(tokens, &code, code_offset, math);
+                    tokens.push(Token::End(spans.len()));
+                    spans.push(Span {
+                        kind: SpanKind::Code,
+                        start: 0,
+                        end: 0,
+                    });
+                    rest = &after[end + n..];
+                } else {
+                    push_text(tokens, &rest[..n]);
+                    rest = after;
+                }
+            }
+            '*' | '_' => {
+                let count = rest.len() - rest.trim_start_matches(c).len();
+                let next_char = rest[count..].chars().next();
+                let before_space = prev_char.is_none_or(char::is_whitespace);
+                let after_space = next_char.is_none_or(char::is_whitespace);
+                let before_punct = prev_char.is_some_and(is_punctuation);
+                let after_punct = next_char.is_some_and(is_punctuation);
+                let left_flanking = !after_space && (!after_punct || before_space || before_punct);
+                let right_flanking = !before_space && (!before_punct || after_space || after_punct);
+                let (can_open, can_close) = if c == '*' {
+                    (left_flanking, right_flanking)
+                } else {
+                    (
+                        left_flanking && (!right_flanking || before_punct),
+                        right_flanking && (!left_flanking || after_punct),
+                    )
+                };
+                tokens.push(Token::Delimiter(Delimiter {
+                    ch: c,
+                    count,
+                    orig_count: count,
+                    can_open,
+                    can_close,
+                    opens: Vec::new(),
+                    closes: Vec::new(),
+                }));
+                rest = &rest[count..];
+            }
+            '[' => {
+                brackets.push((tokens.len(), true));
+                tokens.push(Token::Bracket);
+                rest = &rest[1..];
+            }
+            ']' => {
+                let destination = brackets
+                    .last()
+                    .filter(|(_, active)| *active)
+                    .and_then(|_| parse_link_destination(&rest[1..]));
+                if let Some((url, after)) = destination {
+                    let (opener_idx, _) = brackets.pop().unwrap();
+                    process_emphasis(&mut tokens[opener_idx + 1..], spans);
+                    tokens[opener_idx] = Token::Start(spans.len());
+                    tokens.push(Token::End(spans.len()));
+                    spans.push(Span {
+                        kind: SpanKind::Link(url),
+                        start: 0,
+                        end: 0,
+                    });
+                    // Links cannot contain other links
+                    brackets.iter_mut().for_each(|b| b.1 = false);
+                    last_char = ')';
+                    rest = after;
+                } else {
+                    brackets.pop();
+                    push_char(tokens, ']');
+                    rest = &rest[1..];
+                }
+            }
+            '<' => {
+                if let Some((url, after)) = parse_autolink(rest) {
+                    let text = &rest[1..rest.len() - after.len() - 1];
+                    tokens.push(Token::Start(spans.len()));
+                    push_text_with_math(tokens, text, position(rest) + 1, math);
+                    tokens.push(Token::End(spans.len()));
+                    spans.push(Span {
+                        kind: SpanKind::Link(url),
+                        start: 0,
+                        end: 0,
+                    });
+                    last_char = '>';
+                    rest = after;
+                } else {
+                    push_char(tokens, '<');
+                    rest = &rest[1..];
+                }
+            }
+            c => {
+                let len = c.len_utf8();
+                push_text_with_math(tokens, &rest[..len], position(rest), math);
+                rest = &rest[len..];
+            }
+        }
+        prev_char = Some(last_char);
+    }
+    process_emphasis(&mut tokens[bottom..], spans);
+}
+
+/// Returns the level and the content of an ATX heading ("# Title")
+fn parse_heading(line: &str) -> Option<(u8, &str)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let level = trimmed.len() - trimmed.trim_start_matches('#').len();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let content = &trimmed[level..];
+    if !content.is_empty() && !content.starts_with([' ', '\t']) {
+        return None;
+    }
+    let content = content.trim();
+    // Optional closing sequence of '#'
+    let without_closing = content.trim_end_matches('#');
+    let content = if without_closing.is_empty() {
+        without_closing
+    } else if without_closing.ends_with([' ', '\t']) {
+        without_closing.trim_end()
+    } else {
+        content
+    };
+    Some((level as u8, content))
+}
+
+/// Parses inline Markdown (emphasis, strong emphasis, code spans, links)
+/// and ATX headings. Line breaks are kept as they are in the input.
+/// `math` are formulas whose placeholders are in the input; they are moved to positions
+/// of the placeholders in the resulting text.
+pub(crate) fn parse_markdown(
+    input: &str,
+    named_styles: &[(String, TextStyle)],
+    math: Vec<InlineMath>,
+) -> Markdown {
+    let mut tokens = Vec::new();
+    let mut spans = Vec::new();
+    let mut paragraph = String::new();
+    let mut paragraph_offset = 0;
+    let offset_of = |s: &str| s.as_ptr() as usize - input.as_ptr() as usize;
+    for (i, line) in input.split('\n').enumerate() {
+        if let Some((level, content)) = parse_heading(line) {
+            if !paragraph.is_empty() {
+                parse_inline(&paragraph, paragraph_offset, &math, &mut tokens, &mut spans);
+                paragraph.clear();
+            }
+            if i > 0 {
+                push_char(&mut tokens, '\n');
+            }
+            tokens.push(Token::Start(spans.len()));
+            let span_id = spans.len();
+            spans.push(Span {
+                kind: SpanKind::Heading(level),
+                start: 0,
+                end: 0,
+            });
+            parse_inline(content, offset_of(content), &math, &mut tokens, &mut spans);
+            tokens.push(Token::End(span_id));
+        } else {
+            if paragraph.is_empty() {
+                // The paragraph starts with the line break before the line
+                paragraph_offset = offset_of(line) - usize::from(i > 0);
+            }
+            if i > 0 {
+                paragraph.push('\n');
+            }
+            paragraph.push_str(line);
+        }
+    }
+    if !paragraph.is_empty() {
+        parse_inline(&paragraph, paragraph_offset, &math, &mut tokens, &mut spans);
+    }
+
+    let mut text = String::with_capacity(input.len());
+    let mut math_positions = vec![None; math.len()];
+    for token in tokens {
+        match token {
+            Token::Text(s) => text.push_str(&s),
+            Token::Delimiter(d) => {
+                for idx in d.closes {
+                    spans[idx].end = text.len() as u32;
+                }
+                text.extend(std::iter::repeat_n(d.ch, d.count));
+                for idx in d.opens {
+                    spans[idx].start = text.len() as u32;
+                }
+            }
+            Token::Bracket => text.push('['),
+            Token::Start(idx) => spans[idx].start = text.len() as u32,
+            Token::End(idx) => spans[idx].end = text.len() as u32,
+            Token::Math(idx) => {
+                math_positions[idx] = Some(text.len() as u32);
+                text.push(MATH_PLACEHOLDER);
+            }
+        }
+    }
+
+    let mut markdown = Markdown {
+        text,
+        // Formulas inside link destinations are dropped together with the destinations
+        math: math
+            .into_iter()
+            .zip(math_positions)
+            .filter_map(|(m, pos)| {
+                Some(InlineMath {
+                    pos: pos?,
+                    formula: m.formula,
+                })
+            })
+            .collect(),
+        ..Default::default()
+    };
+    for span in spans {
+        let name = span.kind.style_name();
+        if let Some((_, style)) = named_styles.iter().find(|(n, _)| *n == name) {
+            markdown.styles.push(StyledRange {
+                start: span.start,
+                end: span.end,
+                style: style.clone(),
+            });
+        }
+        if let SpanKind::Link(url) = span.kind {
+            let anchor_id = InlineId::new(LINK_ANCHOR_BASE + markdown.links.len() as u32);
+            markdown.anchors.push((
+                anchor_id,
+                InlineAnchor {
+                    start: span.start,
+                    end: span.end,
+                },
+            ));
+            markdown.links.push((anchor_id, url));
+        }
+    }
+    // Outer spans go first, so inner ones override them
+    markdown
+        .styles
+        .sort_by_key(|s: &StyledRange| (s.start, Reverse(s.end)));
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(input: &str) -> (String, Vec<(String, u32, u32)>) {
+        let names = ["emph", "strong", "code", "link", "h1", "h2"];
+        let styles: Vec<_> = names
+            .iter()
+            .map(|n| {
+                (
+                    n.to_string(),
+                    TextStyle {
+                        font: Some(std::sync::Arc::new(n.to_string())),
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect();
+        let md = parse_markdown(input, &styles, Vec::new());
+        let ranges = md
+            .styles
+            .iter()
+            .map(|s| (s.style.font.as_ref().unwrap().to_string(), s.start, s.end))
+            .collect();
+        (md.text, ranges)
+    }
+
+    #[test]
+    fn test_markdown_emphasis() {
+        assert_eq!(
+            spans("a *b* **c** _d_"),
+            (
+                "a b c d".to_string(),
+                vec![
+                    ("emph".to_string(), 2, 3),
+                    ("strong".to_string(), 4, 5),
+                    ("emph".to_string(), 6, 7)
+                ]
+            )
+        );
+        assert_eq!(
+            spans("***x*** snake_case_name 2 * 3 * 4"),
+            (
+                "x snake_case_name 2 * 3 * 4".to_string(),
+                vec![("strong".to_string(), 0, 1), ("emph".to_string(), 0, 1)]
+            )
+        );
+        assert_eq!(
+            spans("*a **b** c* \\*d\\*"),
+            (
+                "a b c *d*".to_string(),
+                vec![("emph".to_string(), 0, 5), ("strong".to_string(), 2, 3)]
+            )
+        );
+        assert_eq!(spans("*a"), ("*a".to_string(), vec![]));
+    }
+
+    #[test]
+    fn test_markdown_code_and_headings() {
+        assert_eq!(
+            spans("# Title #\nuse `*x*` and `` a`b ``\n##No"),
+            (
+                "Title\nuse *x* and a`b\n##No".to_string(),
+                vec![
+                    ("h1".to_string(), 0, 5),
+                    ("code".to_string(), 10, 13),
+                    ("code".to_string(), 18, 21)
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn test_markdown_links() {
+        let md = parse_markdown(
+            "See [the *docs*](https://a.org/x_(y) \"Title\") or <https://b.org>, [no link]",
+            &[],
+            Vec::new(),
+        );
+        assert_eq!(md.text, "See the docs or https://b.org, [no link]");
+        assert_eq!(
+            md.links,
+            vec![
+                (
+                    InlineId::new(LINK_ANCHOR_BASE),
+                    "https://a.org/x_(y)".to_string()
+                ),
+                (
+                    InlineId::new(LINK_ANCHOR_BASE + 1),
+                    "https://b.org".to_string()
+                )
+            ]
+        );
+        assert_eq!(md.anchors[0].1, InlineAnchor { start: 4, end: 12 });
+        assert_eq!(md.anchors[1].1, InlineAnchor { start: 16, end: 29 });
+    }
+
+    #[test]
+    fn test_markdown_math_positions() {
+        // The first and the last placeholder characters are a part of the text, not formulas
+        let input = "# \u{2060}*\u{2060}*\n`\u{2060}` **\u{2060}**";
+        let math = [6, 12]
+            .into_iter()
+            .map(|pos| InlineMath {
+                pos,
+                formula: format!("f{pos}"),
+            })
+            .collect();
+        let md = parse_markdown(input, &[], math);
+        assert_eq!(md.text, "\u{2060}\u{2060}\n\u{2060} \u{2060}");
+        assert_eq!(
+            md.math,
+            vec![
+                InlineMath {
+                    pos: 3,
+                    formula: "f6".to_string()
+                },
+                InlineMath {
+                    pos: 7,
+                    formula: "f12".to_string()
+                }
+            ]
+        );
+    }
+}
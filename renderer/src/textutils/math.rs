@@ -0,0 +1,645 @@
+/// Classes of atoms; they determine spacing between neighbouring atoms
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum AtomClass {
+    Ord,
+    Op,
+    Bin,
+    Rel,
+    Open,
+    Close,
+    Punct,
+    Inner,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum MathVariant {
+    Normal,
+    Italic,
+    Bold,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum MathNode {
+    Symbol {
+        text: String,
+        class: AtomClass,
+        variant: MathVariant,
+    },
+    /// Big operator, `limits` places scripts above and below in display style
+    Operator {
+        text: String,
+        limits: bool,
+    },
+    Row(Vec<MathNode>),
+    Scripts {
+        base: Box<MathNode>,
+        sup: Option<Box<MathNode>>,
+        sub: Option<Box<MathNode>>,
+    },
+    Fraction {
+        num: Box<MathNode>,
+        den: Box<MathNode>,
+    },
+    Sqrt {
+        index: Option<Box<MathNode>>,
+        body: Box<MathNode>,
+    },
+    Delimited {
+        left: Option<char>,
+        right: Option<char>,
+        body: Box<MathNode>,
+    },
+    Overline(Box<MathNode>),
+    Underline(Box<MathNode>),
+    Accent {
+        accent: char,
+        body: Box<MathNode>,
+    },
+    /// Space in em units
+    Space(f32),
+}
+
+impl MathNode {
+    pub fn class(&self) -> AtomClass {
+        match self {
+            MathNode::Symbol { class, .. } => *class,
+            MathNode::Operator { .. } => AtomClass::Op,
+            MathNode::Scripts { base, .. } => base.class(),
+            MathNode::Fraction { .. } | MathNode::Delimited { .. } => AtomClass::Inner,
+            _ => AtomClass::Ord,
+        }
+    }
+}
+
+const SYMBOLS: &[(&str, char, AtomClass)] = &[
+    ("alpha", 'α', AtomClass::Ord),
+    ("beta", 'β', AtomClass::Ord),
+    ("gamma", 'γ', AtomClass::Ord),
+    ("delta", 'δ', AtomClass::Ord),
+    ("epsilon", 'ϵ', AtomClass::Ord),
+    ("varepsilon", 'ε', AtomClass::Ord),
+    ("zeta", 'ζ', AtomClass::Ord),
+    ("eta", 'η', AtomClass::Ord),
+    ("theta", 'θ', AtomClass::Ord),
+    ("vartheta", 'ϑ', AtomClass::Ord),
+    ("iota", 'ι', AtomClass::Ord),
+    ("kappa", 'κ', AtomClass::Ord),
+    ("lambda", 'λ', AtomClass::Ord),
+    ("mu", 'μ', AtomClass::Ord),
+    ("nu", 'ν', AtomClass::Ord),
+    ("xi", 'ξ', AtomClass::Ord),
+    ("pi", 'π', AtomClass::Ord),
+    ("varpi", 'ϖ', AtomClass::Ord),
+    ("rho", 'ρ', AtomClass::Ord),
+    ("varrho", 'ϱ', AtomClass::Ord),
+    ("sigma", 'σ', AtomClass::Ord),
+    ("varsigma", 'ς', AtomClass::Ord),
+    ("tau", 'τ', AtomClass::Ord),
+    ("upsilon", 'υ', AtomClass::Ord),
+    ("phi", 'ϕ', AtomClass::Ord),
+    ("varphi", 'φ', AtomClass::Ord),
+    ("chi", 'χ', AtomClass::Ord),
+    ("psi", 'ψ', AtomClass::Ord),
+    ("omega", 'ω', AtomClass::Ord),
+    ("Gamma", 'Γ', AtomClass::Ord),
+    ("Delta", 'Δ', AtomClass::Ord),
+    ("Theta", 'Θ', AtomClass::Ord),
+    ("Lambda", 'Λ', AtomClass::Ord),
+    ("Xi", 'Ξ', AtomClass::Ord),
+    ("Pi", 'Π', AtomClass::Ord),
+    ("Sigma", 'Σ', AtomClass::Ord),
+    ("Upsilon", 'Υ', AtomClass::Ord),
+    ("Phi", 'Φ', AtomClass::Ord),
+    ("Psi", 'Ψ', AtomClass::Ord),
+    ("Omega", 'Ω', AtomClass::Ord),
+    ("infty", '∞', AtomClass::Ord),
+    ("partial", '∂', AtomClass::Ord),
+    ("nabla", '∇', AtomClass::Ord),
+    ("forall", '∀', AtomClass::Ord),
+    ("exists", '∃', AtomClass::Ord),
+    ("emptyset", '∅', AtomClass::Ord),
+    ("hbar", 'ℏ', AtomClass::Ord),
+    ("ell", 'ℓ', AtomClass::Ord),
+    ("prime", '′', AtomClass::Ord),
+    ("neg", '¬', AtomClass::Ord),
+    ("ldots", '…', AtomClass::Inner),
+    ("dots", '…', AtomClass::Inner),
+    ("cdots", '⋯', AtomClass::Inner),
+    ("vdots", '⋮', AtomClass::Ord),
+    ("ddots", '⋱', AtomClass::Inner),
+    ("pm", '±', AtomClass::Bin),
+    ("mp", '∓', AtomClass::Bin),
+    ("times", '×', AtomClass::Bin),
+    ("div", '÷', AtomClass::Bin),
+    ("cdot", '⋅', AtomClass::Bin),
+    ("ast", '∗', AtomClass::Bin),
+    ("circ", '∘', AtomClass::Bin),
+    ("bullet", '∙', AtomClass::Bin),
+    ("oplus", '⊕', AtomClass::Bin),
+    ("otimes", '⊗', AtomClass::Bin),
+    ("cup", '∪', AtomClass::Bin),
+    ("cap", '∩', AtomClass::Bin),
+    ("setminus", '∖', AtomClass::Bin),
+    ("wedge", '∧', AtomClass::Bin),
+    ("land", '∧', AtomClass::Bin),
+    ("vee", '∨', AtomClass::Bin),
+    ("lor", '∨', AtomClass::Bin),
+    ("leq", '≤', AtomClass::Rel),
+    ("le", '≤', AtomClass::Rel),
+    ("geq", '≥', AtomClass::Rel),
+    ("ge", '≥', AtomClass::Rel),
+    ("neq", '≠', AtomClass::Rel),
+    ("ne", '≠', AtomClass::Rel),
+    ("approx", '≈', AtomClass::Rel),
+    ("equiv", '≡', AtomClass::Rel),
+    ("sim", '∼', AtomClass::Rel),
+    ("simeq", '≃', AtomClass::Rel),
+    ("cong", '≅', AtomClass::Rel),
+    ("propto", '∝', AtomClass::Rel),
+    ("ll", '≪', AtomClass::Rel),
+    ("gg", '≫', AtomClass::Rel),
+    ("in", '∈', AtomClass::Rel),
+    ("notin", '∉', AtomClass::Rel),
+    ("ni", '∋', AtomClass::Rel),
+    ("subset", '⊂', AtomClass::Rel),
+    ("supset", '⊃', AtomClass::Rel),
+    ("subseteq", '⊆', AtomClass::Rel),
+    ("supseteq", '⊇', AtomClass::Rel),
+    ("mid", '∣', AtomClass::Rel),
+    ("parallel", '∥', AtomClass::Rel),
+    ("perp", '⊥', AtomClass::Rel),
+    ("to", '→', AtomClass::Rel),
+    ("rightarrow", '→', AtomClass::Rel),
+    ("leftarrow", '←', AtomClass::Rel),
+    ("gets", '←', AtomClass::Rel),
+    ("leftrightarrow", '↔', AtomClass::Rel),
+    ("Rightarrow", '⇒', AtomClass::Rel),
+    ("Leftarrow", '⇐', AtomClass::Rel),
+    ("Leftrightarrow", '⇔', AtomClass::Rel),
+    ("implies", '⟹', AtomClass::Rel),
+    ("iff", '⟺', AtomClass::Rel),
+    ("mapsto", '↦', AtomClass::Rel),
+    ("uparrow", '↑', AtomClass::Rel),
+    ("downarrow", '↓', AtomClass::Rel),
+    ("langle", '⟨', AtomClass::Open),
+    ("rangle", '⟩', AtomClass::Close),
+    ("lfloor", '⌊', AtomClass::Open),
+    ("rfloor", '⌋', AtomClass::Close),
+    ("lceil", '⌈', AtomClass::Open),
+    ("rceil", '⌉', AtomClass::Close),
+    ("lbrace", '{', AtomClass::Open),
+    ("rbrace", '}', AtomClass::Close),
+    ("vert", '|', AtomClass::Ord),
+    ("Vert", '‖', AtomClass::Ord),
+];
+
+/// Big operators; the flag tells if they take limits in display style
+const OPERATORS: &[(&str, char, bool)] = &[
+    ("sum", '∑', true),
+    ("prod", '∏', true),
+    ("coprod", '∐', true),
+    ("bigcup", '⋃', true),
+    ("bigcap", '⋂', true),
+    ("bigvee", '⋁', true),
+    ("bigwedge", '⋀', true),
+    ("int", '∫', false),
+    ("iint", '∬', false),
+    ("iiint", '∭', false),
+    ("oint", '∮', false),
+];
+
+/// Named functions (e.g. `\sin`); the flag tells if they take limits in display style
+const FUNCTIONS: &[(&str, bool)] = &[
+    ("sin", false),
+    ("cos", false),
+    ("tan", false),
+    ("cot", false),
+    ("sec", false),
+    ("csc", false),
+    ("arcsin", false),
+    ("arccos", false),
+    ("arctan", false),
+    ("sinh", false),
+    ("cosh", false),
+    ("tanh", false),
+    ("log", false),
+    ("ln", false),
+    ("lg", false),
+    ("exp", false),
+    ("deg", false),
+    ("dim", false),
+    ("ker", false),
+    ("arg", false),
+    ("lim", true),
+    ("liminf", true),
+    ("limsup", true),
+    ("max", true),
+    ("min", true),
+    ("sup", true),
+    ("inf", true),
+    ("det", true),
+    ("gcd", true),
+    ("Pr", true),
+];
+
+const ACCENTS: &[(&str, char)] = &[
+    ("hat", 'ˆ'),
+    ("widehat", 'ˆ'),
+    ("bar", '¯'),
+    ("tilde", '˜'),
+    ("widetilde", '˜'),
+    ("dot", '˙'),
+    ("ddot", '¨'),
+    ("vec", '→'),
+    ("check", 'ˇ'),
+    ("breve", '˘'),
+    ("acute", '´'),
+    ("grave", '`'),
+];
+
+const SPACES: &[(&str, f32)] = &[
+    (",", 3.0 / 18.0),
+    (":", 4.0 / 18.0),
+    (">", 4.0 / 18.0),
+    (";", 5.0 / 18.0),
+    ("!", -3.0 / 18.0),
+    (" ", 0.25),
+    ("quad", 1.0),
+    ("qquad", 2.0),
+];
+
+/// What ends a row of atoms
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum RowEnd {
+    Input,
+    Brace,
+    Bracket,
+    Right,
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    variant: Option<MathVariant>,
+}
+
+fn err(message: impl Into<String>) -> crate::Error {
+    crate::Error::parsing_err(message)
+}
+
+fn symbol(c: char, class: AtomClass, variant: MathVariant) -> MathNode {
+    MathNode::Symbol {
+        text: c.to_string(),
+        class,
+        variant,
+    }
+}
+
+fn double_struck(c: char) -> char {
+    match c {
+        'C' => 'ℂ',
+        'H' => 'ℍ',
+        'N' => 'ℕ',
+        'P' => 'ℙ',
+        'Q' => 'ℚ',
+        'R' => 'ℝ',
+        'Z' => 'ℤ',
+        'A'..='Z' => char::from_u32(0x1D538 + (c as u32 - 'A' as u32)).unwrap_or(c),
+        _ => c,
+    }
+}
+
+fn attach_scripts(
+    base: MathNode,
+    sup: Option<MathNode>,
+    sub: Option<MathNode>,
+) -> crate::Result<MathNode> {
+    let (base, old_sup, old_sub) = match base {
+        MathNode::Scripts { base, sup, sub } => (*base, sup, sub),
+        base => (base, None, None),
+    };
+    if old_sup.is_some() && sup.is_some() {
+        return Err(err("Double superscript in formula"));
+    }
+    if old_sub.is_some() && sub.is_some() {
+        return Err(err("Double subscript in formula"));
+    }
+    Ok(MathNode::Scripts {
+        base: Box::new(base),
+        sup: old_sup.or(sup.map(Box::new)),
+        sub: old_sub.or(sub.map(Box::new)),
+    })
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.next_char();
+        }
+    }
+
+    fn at_right(&self) -> bool {
+        let rest = &self.input[self.pos..];
+        rest.starts_with("\\right")
+            && !rest[6..]
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic())
+    }
+
+    fn parse_row(&mut self, end: RowEnd) -> crate::Result<MathNode> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if end == RowEnd::Right && self.at_right() {
+                self.pos += "\\right".len();
+                break;
+            }
+            match (self.peek(), end) {
+                (None, RowEnd::Input) => break,
+                (None, RowEnd::Right) => return Err(err("Missing '\\right' in formula")),
+                (None, _) => return Err(err("Unclosed group in formula")),
+                (Some('}'), RowEnd::Brace) | (Some(']'), RowEnd::Bracket) => {
+                    self.next_char();
+                    break;
+                }
+                (Some('}'), _) => return Err(err("Unexpected '}' in formula")),
+                (Some(c @ ('^' | '_')), _) => {
+                    self.next_char();
+                    let base = items.pop().unwrap_or(MathNode::Row(Vec::new()));
+                    let arg = self.parse_argument()?;
+                    let (sup, sub) = if c == '^' {
+                        (Some(arg), None)
+                    } else {
+                        (None, Some(arg))
+                    };
+                    items.push(attach_scripts(base, sup, sub)?);
+                }
+                _ => items.push(self.parse_atom()?),
+            }
+        }
+        Ok(MathNode::Row(items))
+    }
+
+    fn parse_argument(&mut self) -> crate::Result<MathNode> {
+        self.skip_whitespace();
+        match self.peek() {
+            None | Some('}') => Err(err("Missing argument in formula")),
+            _ => self.parse_atom(),
+        }
+    }
+
+    fn parse_group_raw(&mut self) -> crate::Result<&'a str> {
+        self.skip_whitespace();
+        if self.next_char() != Some('{') {
+            return Err(err("Expected '{' in formula"));
+        }
+        let start = self.pos;
+        let mut depth = 0;
+        while let Some(c) = self.next_char() {
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 0 => return Ok(&self.input[start..self.pos - 1]),
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        Err(err("Unclosed group in formula"))
+    }
+
+    fn parse_delimiter(&mut self) -> crate::Result<Option<char>> {
+        self.skip_whitespace();
+        Ok(Some(match self.next_char() {
+            Some('.') => return Ok(None),
+            Some(c @ ('(' | ')' | '[' | ']' | '|' | '/')) => c,
+            Some('\\') => match self.parse_command_name() {
+                "{" | "lbrace" => '{',
+                "}" | "rbrace" => '}',
+                "|" | "Vert" => '‖',
+                "vert" => '|',
+                "langle" => '⟨',
+                "rangle" => '⟩',
+                "lfloor" => '⌊',
+                "rfloor" => '⌋',
+                "lceil" => '⌈',
+                "rceil" => '⌉',
+                name => return Err(err(format!("Invalid delimiter in formula: '\\{name}'"))),
+            },
+            Some(c) => return Err(err(format!("Invalid delimiter in formula: '{c}'"))),
+            None => return Err(err("Missing delimiter in formula")),
+        }))
+    }
+
+    fn parse_command_name(&mut self) -> &'a str {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            self.next_char();
+        }
+        if self.pos == start {
+            self.next_char();
+        }
+        &self.input[start..self.pos]
+    }
+
+    fn parse_atom(&mut self) -> crate::Result<MathNode> {
+        let c = self.next_char().unwrap();
+        let variant = self.variant;
+        Ok(match c {
+            '{' => self.parse_row(RowEnd::Brace)?,
+            '\\' => self.parse_command()?,
+            'a'..='z' | 'A'..='Z' => {
+                symbol(c, AtomClass::Ord, variant.unwrap_or(MathVariant::Italic))
+            }
+            '0'..='9' | '.' => symbol(c, AtomClass::Ord, variant.unwrap_or(MathVariant::Normal)),
+            '+' => symbol('+', AtomClass::Bin, MathVariant::Normal),
+            '-' => symbol('−', AtomClass::Bin, MathVariant::Normal),
+            '*' => symbol('∗', AtomClass::Bin, MathVariant::Normal),
+            '=' | '<' | '>' | ':' => symbol(c, AtomClass::Rel, MathVariant::Normal),
+            // The prime character is already raised, so it is not a superscript
+            '\'' => symbol('′', AtomClass::Ord, MathVariant::Normal),
+            '(' | '[' => symbol(c, AtomClass::Open, MathVariant::Normal),
+            ')' | ']' | '!' | '?' => symbol(c, AtomClass::Close, MathVariant::Normal),
+            ',' | ';' => symbol(c, AtomClass::Punct, MathVariant::Normal),
+            '&' | '#' | '%' | '$' | '~' => {
+                return Err(err(format!("Unsupported character in formula: '{c}'")));
+            }
+            _ => symbol(c, AtomClass::Ord, variant.unwrap_or(MathVariant::Normal)),
+        })
+    }
+
+    fn parse_with_variant(&mut self, variant: MathVariant) -> crate::Result<MathNode> {
+        let old = self.variant.replace(variant);
+        let node = self.parse_argument();
+        self.variant = old;
+        node
+    }
+
+    fn parse_command(&mut self) -> crate::Result<MathNode> {
+        let name = self.parse_command_name();
+        if let Some((_, space)) = SPACES.iter().find(|(n, _)| *n == name) {
+            return Ok(MathNode::Space(*space));
+        }
+        if let Some((_, c, class)) = SYMBOLS.iter().find(|(n, _, _)| *n == name) {
+            // Lowercase Greek letters are italic, uppercase upright
+            let variant = if c.is_lowercase() {
+                MathVariant::Italic
+            } else {
+                MathVariant::Normal
+            };
+            return Ok(symbol(*c, *class, self.variant.unwrap_or(variant)));
+        }
+        if let Some((_, c, limits)) = OPERATORS.iter().find(|(n, _, _)| *n == name) {
+            return Ok(MathNode::Operator {
+                text: c.to_string(),
+                limits: *limits,
+            });
+        }
+        if let Some((name, limits)) = FUNCTIONS.iter().find(|(n, _)| *n == name) {
+            return Ok(MathNode::Operator {
+                text: name.to_string(),
+                limits: *limits,
+            });
+        }
+        if let Some((_, accent)) = ACCENTS.iter().find(|(n, _)| *n == name) {
+            return Ok(MathNode::Accent {
+                accent: *accent,
+                body: Box::new(self.parse_argument()?),
+            });
+        }
+        Ok(match name {
+            "{" => symbol('{', AtomClass::Open, MathVariant::Normal),
+            "}" => symbol('}', AtomClass::Close, MathVariant::Normal),
+            "%" | "$" | "#" | "&" | "_" => symbol(
+                name.chars().next().unwrap(),
+                AtomClass::Ord,
+                MathVariant::Normal,
+            ),
+            "|" => symbol('‖', AtomClass::Ord, MathVariant::Normal),
+            "frac" | "dfrac" | "tfrac" => {
+                let num = self.parse_argument()?;
+                let den = self.parse_argument()?;
+                MathNode::Fraction {
+                    num: Box::new(num),
+                    den: Box::new(den),
+                }
+            }
+            "sqrt" => {
+                self.skip_whitespace();
+                let index = if self.peek() == Some('[') {
+                    self.next_char();
+                    Some(Box::new(self.parse_row(RowEnd::Bracket)?))
+                } else {
+                    None
+                };
+                MathNode::Sqrt {
+                    index,
+                    body: Box::new(self.parse_argument()?),
+                }
+            }
+            "left" => {
+                let left = self.parse_delimiter()?;
+                let body = self.parse_row(RowEnd::Right)?;
+                let right = self.parse_delimiter()?;
+                MathNode::Delimited {
+                    left,
+                    right,
+                    body: Box::new(body),
+                }
+            }
+            "overline" => MathNode::Overline(Box::new(self.parse_argument()?)),
+            "underline" => MathNode::Underline(Box::new(self.parse_argument()?)),
+            "mathrm" | "mathup" => self.parse_with_variant(MathVariant::Normal)?,
+            "mathit" => self.parse_with_variant(MathVariant::Italic)?,
+            "mathbf" | "boldsymbol" => self.parse_with_variant(MathVariant::Bold)?,
+            "mathbb" => MathNode::Row(
+                self.parse_group_raw()?
+                    .chars()
+                    .filter(|c| !c.is_whitespace())
+                    .map(|c| symbol(double_struck(c), AtomClass::Ord, MathVariant::Normal))
+                    .collect(),
+            ),
+            "text" | "textrm" | "mbox" => MathNode::Symbol {
+                text: self.parse_group_raw()?.to_string(),
+                class: AtomClass::Ord,
+                variant: MathVariant::Normal,
+            },
+            "operatorname" => MathNode::Operator {
+                text: self.parse_group_raw()?.to_string(),
+                limits: false,
+            },
+            _ => return Err(err(format!("Unknown command in formula: '\\{name}'"))),
+        })
+    }
+}
+
+pub(crate) fn parse_math(formula: &str) -> crate::Result<MathNode> {
+    let mut parser = Parser {
+        input: formula,
+        pos: 0,
+        variant: None,
+    };
+    parser.parse_row(RowEnd::Input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sym(s: &str, class: AtomClass, variant: MathVariant) -> MathNode {
+        MathNode::Symbol {
+            text: s.to_string(),
+            class,
+            variant,
+        }
+    }
+
+    #[test]
+    fn test_parse_scripts() {
+        let node = parse_math("x^2_i").unwrap();
+        assert_eq!(
+            node,
+            MathNode::Row(vec![MathNode::Scripts {
+                base: Box::new(sym("x", AtomClass::Ord, MathVariant::Italic)),
+                sup: Some(Box::new(sym("2", AtomClass::Ord, MathVariant::Normal))),
+                sub: Some(Box::new(sym("i", AtomClass::Ord, MathVariant::Italic))),
+            }])
+        );
+        assert!(parse_math("x^2^3").is_err());
+    }
+
+    #[test]
+    fn test_parse_frac_and_delimiters() {
+        let node = parse_math(r"\left( \frac{a}{b} \right]").unwrap();
+        let MathNode::Row(items) = node else {
+            panic!("Expected row")
+        };
+        let [MathNode::Delimited { left, right, body }] = items.as_slice() else {
+            panic!("Expected delimited node")
+        };
+        assert_eq!(*left, Some('('));
+        assert_eq!(*right, Some(']'));
+        let MathNode::Row(items) = body.as_ref() else {
+            panic!("Expected row")
+        };
+        assert!(matches!(items.as_slice(), [MathNode::Fraction { .. }]));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse_math(r"\unknown").is_err());
+        assert!(parse_math(r"\frac{a}{b").is_err());
+        assert!(parse_math(r"a}").is_err());
+        assert!(parse_math(r"\left( a").is_err());
+        assert!(parse_math(r"{x^2}^3").is_ok());
+    }
+}
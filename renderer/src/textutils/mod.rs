@@ -1,4 +1,9 @@
+mod annotations;
+mod markdown;
+pub(crate) mod math;
 mod styling;
-mod syntaxhl;
+pub(crate) mod syntaxhl;
+#[cfg(feature = "tree-sitter")]
+pub(crate) mod treesitter;
 
-pub(crate) use styling::StyledText;
+pub(crate) use styling::{MATH_PLACEHOLDER, StyledText};
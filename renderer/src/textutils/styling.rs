@@ -1,6 +1,19 @@
 use crate::Resources;
-use crate::text::{InlineId, Text, TextAlign, TextStyle};
-use crate::textutils::syntaxhl::run_syntax_highlighting;
+use crate::text::{
+    DiffLine, InlineId, LineNumbers, SyntaxHighlightBackend, Text, TextAlign, TextDiff, TextStyle,
+};
+use crate::textutils::annotations::apply_annotations;
+use crate::textutils::markdown::parse_markdown;
+use crate::textutils::syntaxhl::{
+    DEFAULT_LINE_NUMBERS_COLOR, run_syntax_highlighting, text_theme_colors,
+};
+#[cfg(feature = "tree-sitter")]
+use crate::textutils::treesitter::run_tree_sitter_highlighting;
+use std::borrow::Cow;
+
+/// Character that takes place of an inline formula in the text.
+/// It does not allow line breaks around the formula.
+pub(crate) const MATH_PLACEHOLDER: char = '\u{2060}';
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub(crate) struct StyledRange {
@@ -15,9 +28,19 @@ pub(crate) struct StyledText {
     pub main_style: TextStyle,
     pub styles: Vec<StyledRange>,
     pub anchors: Vec<(InlineId, InlineAnchor)>,
+    pub math: Vec<InlineMath>,
+    /// Urls of links; a link covers the range of the anchor with the same id
+    pub links: Vec<(InlineId, String)>,
     pub text_align: TextAlign,
 }
 
+/// Formula placed inside a text; `pos` is the position of its placeholder
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub(crate) struct InlineMath {
+    pub pos: u32,
+    pub formula: String,
+}
+
 #[derive(Debug, Clone, Eq, Hash, PartialEq)]
 pub(crate) struct InlineAnchor {
     pub start: u32,
@@ -30,13 +53,67 @@ enum StackEntry<'a> {
     Anchor { start: u32, anchor_id: InlineId },
 }
 
-fn text_to_styled_text(text: &Text) -> crate::Result<StyledText> {
-    if let Some(styling) = &text.styling {
+fn markdown_to_styled_text(text: &Text, math: Vec<InlineMath>) -> StyledText {
+    let named_styles = text
+        .styling
+        .as_ref()
+        .map(|s| s.named_styles.as_slice())
+        .unwrap_or_default();
+    let markdown = parse_markdown(&text.text, named_styles, math);
+    StyledText {
+        text: markdown.text,
+        main_style: text.style.clone(),
+        styles: markdown.styles,
+        anchors: markdown.anchors,
+        math: markdown.math,
+        links: markdown.links,
+        text_align: text.text_align,
+    }
+}
+
+/// Moves formulas placed in `input[start..end]` to `dest + (pos - start)`,
+/// when this part of the input is copied to position `dest` of the output
+fn copy_math(
+    math: &[InlineMath],
+    start: usize,
+    end: usize,
+    dest: usize,
+    out: &mut Vec<InlineMath>,
+) {
+    out.extend(
+        math.iter()
+            .filter(|m| (start..end).contains(&(m.pos as usize)))
+            .map(|m| InlineMath {
+                pos: (dest + m.pos as usize - start) as u32,
+                formula: m.formula.clone(),
+            }),
+    );
+}
+
+/// `math` are formulas whose placeholders are already in the text, the returned styled text
+/// contains them at their positions in the parsed text
+fn text_to_styled_text(text: &Text, math: Vec<InlineMath>) -> crate::Result<StyledText> {
+    if text.markdown {
+        Ok(markdown_to_styled_text(text, math))
+    } else if let Some(styling) = &text.styling {
         let mut stack: Vec<StackEntry> = Vec::new();
         let mut input = text.text.as_str();
         let mut result_text = String::with_capacity(input.len());
         let mut result_styles = Vec::new();
         let mut result_anchors = Vec::new();
+        let mut result_math = Vec::new();
+        // Copies the first `len` bytes of the rest of the input into the result
+        let mut copy_input = |input: &str, len: usize, result_text: &mut String| {
+            let start = text.text.len() - input.len();
+            copy_math(
+                &math,
+                start,
+                start + len,
+                result_text.len(),
+                &mut result_math,
+            );
+            result_text.push_str(&input[..len]);
+        };
 
         let esc_char = styling.parsing_chars.escape_char;
         let block_begin = styling.parsing_chars.block_begin;
@@ -63,7 +140,7 @@ fn text_to_styled_text(text: &Text) -> crate::Result<StyledText> {
 
             if let Some(idx) = esc_index {
                 if idx > 0 {
-                    result_text.push_str(&input[..idx]);
+                    copy_input(input, idx, &mut result_text);
                 }
                 let start = idx + esc_len;
                 let end = input[start..].find(block_begin).ok_or_else(|| {
@@ -87,7 +164,7 @@ fn text_to_styled_text(text: &Text) -> crate::Result<StyledText> {
                 input = &input[end + start_len..];
             } else if let Some(idx) = end_index {
                 if idx > 0 {
-                    result_text.push_str(&input[..idx]);
+                    copy_input(input, idx, &mut result_text);
                 }
                 let end = result_text.len() as u32;
                 match stack.pop().unwrap() {
@@ -110,7 +187,7 @@ fn text_to_styled_text(text: &Text) -> crate::Result<StyledText> {
                 }
                 input = &input[idx + end_len..];
             } else {
-                result_text.push_str(input);
+                copy_input(input, input.len(), &mut result_text);
                 break;
             };
         }
@@ -127,6 +204,8 @@ fn text_to_styled_text(text: &Text) -> crate::Result<StyledText> {
             main_style: text.style.clone(),
             styles: result_styles,
             anchors: result_anchors,
+            math: result_math,
+            links: Vec::new(),
             text_align: text.text_align,
         })
     } else {
@@ -135,16 +214,252 @@ fn text_to_styled_text(text: &Text) -> crate::Result<StyledText> {
             main_style: text.style.clone(),
             styles: Vec::new(),
             anchors: Default::default(),
+            math,
+            links: Vec::new(),
             text_align: text.text_align,
         })
     }
 }
 
+/// Replaces formulas between '$' by placeholders; "\$" outside of a formula is a plain '$'.
+/// Formulas are returned with positions of their placeholders, as the text may contain
+/// the placeholder character also on its own.
+fn extract_math(input: &str) -> crate::Result<(String, Vec<InlineMath>)> {
+    let mut text = String::with_capacity(input.len());
+    let mut formulas = Vec::new();
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.as_str().starts_with('$') => {
+                chars.next();
+                text.push('$');
+            }
+            '$' => {
+                let mut formula = String::new();
+                loop {
+                    match chars.next() {
+                        Some('$') => break,
+                        Some('\\') if chars.as_str().starts_with('$') => {
+                            chars.next();
+                            formula.push_str("\\$");
+                        }
+                        Some(c) => formula.push(c),
+                        None => return Err(crate::Error::parsing_err("Unclosed math formula")),
+                    }
+                }
+                formulas.push(InlineMath {
+                    pos: text.len() as u32,
+                    formula,
+                });
+                text.push(MATH_PLACEHOLDER);
+            }
+            c => text.push(c),
+        }
+    }
+    Ok((text, formulas))
+}
+
+/// Text put in front of a line
+struct LinePrefix {
+    text: String,
+    /// Ranges are relative to the start of the prefix
+    styles: Vec<StyledRange>,
+}
+
+impl LinePrefix {
+    fn new(text: String, style: TextStyle) -> Self {
+        LinePrefix {
+            styles: vec![StyledRange {
+                start: 0,
+                end: text.len() as u32,
+                style,
+            }],
+            text,
+        }
+    }
+}
+
+fn line_number_prefixes(
+    line_count: usize,
+    line_numbers: &LineNumbers,
+    style: TextStyle,
+    diff: Option<&TextDiff>,
+) -> Vec<LinePrefix> {
+    let mut next = line_numbers.start as usize;
+    let numbers: Vec<Option<usize>> = (0..line_count)
+        .map(|idx| {
+            if diff.and_then(|d| d.lines.get(idx)) == Some(&DiffLine::Removed) {
+                None
+            } else {
+                next += 1;
+                Some(next - 1)
+            }
+        })
+        .collect();
+    let width = next.saturating_sub(1).max(1).to_string().len();
+    numbers
+        .into_iter()
+        .map(|number| {
+            let number = number.map(|n| n.to_string()).unwrap_or_default();
+            LinePrefix::new(
+                format!("{number:>width$}{}", line_numbers.separator),
+                style.clone(),
+            )
+        })
+        .collect()
+}
+
+fn diff_marker_prefixes(line_count: usize, diff: &TextDiff) -> Vec<LinePrefix> {
+    (0..line_count)
+        .map(|idx| match diff.lines.get(idx) {
+            Some(DiffLine::Added) => LinePrefix::new(
+                "+ ".to_string(),
+                TextStyle {
+                    color: Some(diff.added_color.opaque()),
+                    ..Default::default()
+                },
+            ),
+            Some(DiffLine::Removed) => LinePrefix::new(
+                "- ".to_string(),
+                TextStyle {
+                    color: Some(diff.removed_color.opaque()),
+                    ..Default::default()
+                },
+            ),
+            Some(DiffLine::Unchanged) | None => LinePrefix {
+                text: "  ".to_string(),
+                styles: Vec::new(),
+            },
+        })
+        .collect()
+}
+
+/// Puts prefixes (e.g. line numbers) in front of lines; ranges are moved after the prefixes,
+/// styles spanning more lines are split, so they do not cover the prefixes
+fn add_line_prefixes(styled_text: &mut StyledText, prefixes: &[LinePrefix]) {
+    let line_starts: Vec<u32> = std::iter::once(0)
+        .chain(
+            styled_text
+                .text
+                .match_indices('\n')
+                .map(|(idx, _)| idx as u32 + 1),
+        )
+        .collect();
+
+    // Number of bytes inserted before position `pos`; `inclusive` tells if a prefix of a line
+    // that starts at `pos` is counted
+    let shift = |pos: u32, inclusive: bool| -> u32 {
+        let count = if inclusive {
+            line_starts.partition_point(|start| *start <= pos)
+        } else {
+            line_starts.partition_point(|start| *start < pos)
+        };
+        prefixes[..count].iter().map(|p| p.text.len() as u32).sum()
+    };
+    let map_range = |start: u32, end: u32| -> (u32, u32) {
+        let new_start = start + shift(start, true);
+        (new_start, (end + shift(end, false)).max(new_start))
+    };
+
+    let mut styles = Vec::with_capacity(styled_text.styles.len() + prefixes.len());
+    for range in &styled_text.styles {
+        let mut start = range.start;
+        for line_start in &line_starts {
+            if *line_start > start && *line_start < range.end {
+                let (s, e) = map_range(start, *line_start);
+                styles.push(StyledRange {
+                    start: s,
+                    end: e,
+                    style: range.style.clone(),
+                });
+                start = *line_start;
+            }
+        }
+        let (s, e) = map_range(start, range.end);
+        styles.push(StyledRange {
+            start: s,
+            end: e,
+            style: range.style.clone(),
+        });
+    }
+    for anchor in &mut styled_text.anchors {
+        (anchor.1.start, anchor.1.end) = map_range(anchor.1.start, anchor.1.end);
+    }
+    for math in &mut styled_text.math {
+        math.pos += shift(math.pos, true);
+    }
+
+    let mut text = String::with_capacity(
+        styled_text.text.len() + prefixes.iter().map(|p| p.text.len()).sum::<usize>(),
+    );
+    for (idx, (line, prefix)) in styled_text.text.split('\n').zip(prefixes).enumerate() {
+        if idx > 0 {
+            text.push('\n');
+        }
+        let start = text.len() as u32;
+        text.push_str(&prefix.text);
+        styles.extend(prefix.styles.iter().map(|range| StyledRange {
+            start: start + range.start,
+            end: start + range.end,
+            style: range.style.clone(),
+        }));
+        text.push_str(line);
+    }
+    styled_text.text = text;
+    styled_text.styles = styles;
+}
+
 impl StyledText {
     pub fn from(resources: &Resources, text: &Text) -> crate::Result<Self> {
-        let mut styled_text = text_to_styled_text(text)?;
+        let (text, math) = if text.parse_math {
+            let (math_text, math) = extract_math(&text.text)?;
+            let text = Text {
+                text: math_text,
+                ..text.clone()
+            };
+            (Cow::Owned(text), math)
+        } else {
+            (Cow::Borrowed(text), Vec::new())
+        };
+        let mut styled_text = text_to_styled_text(&text, math)?;
+        styled_text.links.extend(text.links.iter().cloned());
         if let Some(hl) = &text.syntax_highlight {
-            run_syntax_highlighting(resources, &mut styled_text, &hl.language, &hl.theme)?;
+            match hl.backend {
+                SyntaxHighlightBackend::Syntect => {
+                    run_syntax_highlighting(resources, &mut styled_text, &hl.language, &hl.theme)?
+                }
+                #[cfg(feature = "tree-sitter")]
+                SyntaxHighlightBackend::TreeSitter => run_tree_sitter_highlighting(
+                    resources,
+                    &mut styled_text,
+                    &hl.language,
+                    &hl.theme,
+                )?,
+                #[cfg(not(feature = "tree-sitter"))]
+                SyntaxHighlightBackend::TreeSitter => {
+                    return Err(crate::Error::generic_err(
+                        "Tree-sitter syntax highlighting is not available (feature 'tree-sitter' is disabled)",
+                    ));
+                }
+            }
+        }
+        apply_annotations(&mut styled_text, &text.annotations)?;
+        let line_count = styled_text.text.split('\n').count();
+        if let Some(diff) = &text.diff {
+            add_line_prefixes(&mut styled_text, &diff_marker_prefixes(line_count, diff));
+        }
+        if let Some(line_numbers) = &text.line_numbers {
+            let mut style = line_numbers.style.clone();
+            if style.color.is_none() {
+                style.color = Some(
+                    text_theme_colors(resources, &text)?
+                        .gutter_foreground
+                        .unwrap_or(DEFAULT_LINE_NUMBERS_COLOR),
+                );
+            }
+            let prefixes =
+                line_number_prefixes(line_count, line_numbers, style, text.diff.as_ref());
+            add_line_prefixes(&mut styled_text, &prefixes);
         }
         Ok(styled_text)
     }
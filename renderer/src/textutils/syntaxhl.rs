@@ -1,8 +1,8 @@
-use crate::text::TextStyle;
+use crate::text::{Text, TextStyle};
 use crate::textutils::styling::{StyledRange, StyledText};
-use crate::{Color, Resources};
+use crate::{Color, Resources, ThemeColors};
 use syntect::easy::HighlightLines;
-use syntect::highlighting::Style;
+use syntect::highlighting::{Style, Theme};
 
 impl From<syntect::highlighting::Color> for Color {
     fn from(value: syntect::highlighting::Color) -> Self {
@@ -12,7 +12,31 @@ impl From<syntect::highlighting::Color> for Color {
     }
 }
 
-fn create_style(s_style: Style) -> TextStyle {
+/// Used for line numbers when neither the style nor the theme defines a color
+pub(crate) const DEFAULT_LINE_NUMBERS_COLOR: Color = Color::new(svgtypes::Color {
+    red: 0x88,
+    green: 0x88,
+    blue: 0x88,
+    alpha: 0xff,
+});
+/// Used for line highlights when the theme defines no selection or line highlight color
+pub(crate) const DEFAULT_LINE_HIGHLIGHT_COLOR: Color = Color::new(svgtypes::Color {
+    red: 0xff,
+    green: 0xee,
+    blue: 0x00,
+    alpha: 0x60,
+});
+
+/// Theme colors of a text; empty if the text is not syntax highlighted
+pub(crate) fn text_theme_colors(resources: &Resources, text: &Text) -> crate::Result<ThemeColors> {
+    text.syntax_highlight
+        .as_ref()
+        .map(|hl| resources.theme_colors(&hl.theme))
+        .transpose()
+        .map(Option::unwrap_or_default)
+}
+
+pub(crate) fn create_style(s_style: Style) -> TextStyle {
     TextStyle {
         font: None,
         color: Some(s_style.foreground.into()),
@@ -43,10 +67,28 @@ fn create_style(s_style: Style) -> TextStyle {
         } else {
             None
         },
+        overline: None,
         line_through: None,
+        bg_color: None,
+        decoration_color: None,
+        decoration_width: None,
+        decoration_style: None,
+        baseline_shift: None,
+        script: None,
     }
 }
 
+pub(crate) fn find_theme<'a>(
+    resources: &'a Resources,
+    theme_name: &str,
+) -> crate::Result<&'a Theme> {
+    resources
+        .theme_set
+        .themes
+        .get(theme_name)
+        .ok_or_else(|| crate::Error::generic_err(format!("Theme '{theme_name}' not found")))
+}
+
 pub fn run_syntax_highlighting(
     resources: &Resources,
     text: &mut StyledText,
@@ -62,11 +104,7 @@ pub fn run_syntax_highlighting(
                 "Language '{language_name}' for syntax highlighting not found"
             ))
         })?;
-    let theme = resources
-        .theme_set
-        .themes
-        .get(theme_name)
-        .ok_or_else(|| crate::Error::generic_err(format!("Theme '{theme_name}' not found")))?;
+    let theme = find_theme(resources, theme_name)?;
     let mut highlight = HighlightLines::new(syntax, theme);
     let mut styles = Vec::new();
     let mut offset = 0;
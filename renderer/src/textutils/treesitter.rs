@@ -0,0 +1,219 @@
+use crate::Resources;
+use crate::textutils::styling::{StyledRange, StyledText};
+use crate::textutils::syntaxhl::{create_style, find_theme};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use syntect::highlighting::Highlighter;
+use syntect::parsing::Scope;
+use tree_sitter::Language;
+use tree_sitter_highlight::{HighlightConfiguration, HighlightEvent};
+
+/// Capture names of highlight queries and TextMate scopes used to find their colors in a theme.
+/// A capture that is not listed uses the longest listed prefix (e.g. "function.method" -> "function").
+const CAPTURE_SCOPES: &[(&str, &str)] = &[
+    ("attribute", "entity.other.attribute-name"),
+    ("boolean", "constant.language"),
+    ("character", "constant.character"),
+    ("comment", "comment"),
+    ("constant", "constant"),
+    ("constant.builtin", "constant.language"),
+    ("constructor", "entity.name.type"),
+    ("escape", "constant.character.escape"),
+    ("function", "entity.name.function"),
+    ("function.builtin", "support.function"),
+    ("function.macro", "entity.name.function.macro"),
+    ("keyword", "keyword"),
+    ("keyword.operator", "keyword.operator"),
+    ("label", "entity.name.label"),
+    ("module", "entity.name.namespace"),
+    ("number", "constant.numeric"),
+    ("operator", "keyword.operator"),
+    ("property", "variable.other.member"),
+    ("punctuation", "punctuation"),
+    ("string", "string"),
+    ("string.escape", "constant.character.escape"),
+    ("string.special", "string.other"),
+    ("type", "entity.name.type"),
+    ("type.builtin", "storage.type"),
+    ("variable", "variable"),
+    ("variable.builtin", "variable.language"),
+    ("variable.parameter", "variable.parameter"),
+];
+
+struct TreeSitterLanguage {
+    /// Lowercase names and file extensions
+    names: &'static [&'static str],
+    language: fn() -> Language,
+    highlights: &'static [&'static str],
+    locals: &'static [&'static str],
+}
+
+const LANGUAGES: &[TreeSitterLanguage] = &[
+    TreeSitterLanguage {
+        names: &["bash", "sh", "shell"],
+        language: || tree_sitter_bash::LANGUAGE.into(),
+        highlights: &[tree_sitter_bash::HIGHLIGHT_QUERY],
+        locals: &[],
+    },
+    TreeSitterLanguage {
+        names: &["c", "h"],
+        language: || tree_sitter_c::LANGUAGE.into(),
+        highlights: &[tree_sitter_c::HIGHLIGHT_QUERY],
+        locals: &[],
+    },
+    TreeSitterLanguage {
+        names: &["go"],
+        language: || tree_sitter_go::LANGUAGE.into(),
+        highlights: &[tree_sitter_go::HIGHLIGHTS_QUERY],
+        locals: &[],
+    },
+    TreeSitterLanguage {
+        names: &["javascript", "js", "jsx"],
+        language: || tree_sitter_javascript::LANGUAGE.into(),
+        highlights: &[
+            tree_sitter_javascript::JSX_HIGHLIGHT_QUERY,
+            tree_sitter_javascript::HIGHLIGHT_QUERY,
+        ],
+        locals: &[tree_sitter_javascript::LOCALS_QUERY],
+    },
+    TreeSitterLanguage {
+        names: &["json"],
+        language: || tree_sitter_json::LANGUAGE.into(),
+        highlights: &[tree_sitter_json::HIGHLIGHTS_QUERY],
+        locals: &[],
+    },
+    TreeSitterLanguage {
+        names: &["python", "py"],
+        language: || tree_sitter_python::LANGUAGE.into(),
+        highlights: &[tree_sitter_python::HIGHLIGHTS_QUERY],
+        locals: &[],
+    },
+    TreeSitterLanguage {
+        names: &["rust", "rs"],
+        language: || tree_sitter_rust::LANGUAGE.into(),
+        highlights: &[tree_sitter_rust::HIGHLIGHTS_QUERY],
+        locals: &[],
+    },
+    TreeSitterLanguage {
+        names: &["typescript", "ts"],
+        language: || tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+        // TypeScript queries extend JavaScript queries
+        highlights: &[
+            tree_sitter_typescript::HIGHLIGHTS_QUERY,
+            tree_sitter_javascript::HIGHLIGHT_QUERY,
+        ],
+        locals: &[
+            tree_sitter_typescript::LOCALS_QUERY,
+            tree_sitter_javascript::LOCALS_QUERY,
+        ],
+    },
+    TreeSitterLanguage {
+        names: &["tsx"],
+        language: || tree_sitter_typescript::LANGUAGE_TSX.into(),
+        highlights: &[
+            tree_sitter_javascript::JSX_HIGHLIGHT_QUERY,
+            tree_sitter_typescript::HIGHLIGHTS_QUERY,
+            tree_sitter_javascript::HIGHLIGHT_QUERY,
+        ],
+        locals: &[
+            tree_sitter_typescript::LOCALS_QUERY,
+            tree_sitter_javascript::LOCALS_QUERY,
+        ],
+    },
+    TreeSitterLanguage {
+        names: &["zig"],
+        language: || tree_sitter_zig::LANGUAGE.into(),
+        highlights: &[tree_sitter_zig::HIGHLIGHTS_QUERY],
+        locals: &[],
+    },
+];
+
+/// Names of languages that can be highlighted by tree-sitter
+pub fn tree_sitter_languages() -> impl Iterator<Item = &'static str> {
+    LANGUAGES.iter().map(|lang| lang.names[0])
+}
+
+/// Compiling queries is expensive, so configurations are created once and shared
+fn highlight_config(language_name: &str) -> crate::Result<&'static HighlightConfiguration> {
+    static CONFIGS: OnceLock<Vec<OnceLock<Result<HighlightConfiguration, String>>>> =
+        OnceLock::new();
+    let name = language_name.to_lowercase();
+    let idx = LANGUAGES
+        .iter()
+        .position(|lang| lang.names.contains(&name.as_str()))
+        .ok_or_else(|| {
+            crate::Error::generic_err(format!(
+                "Language '{language_name}' for tree-sitter syntax highlighting not found"
+            ))
+        })?;
+    let configs = CONFIGS.get_or_init(|| LANGUAGES.iter().map(|_| OnceLock::new()).collect());
+    configs[idx]
+        .get_or_init(|| {
+            let lang = &LANGUAGES[idx];
+            let mut config = HighlightConfiguration::new(
+                (lang.language)(),
+                lang.names[0],
+                &lang.highlights.concat(),
+                "",
+                &lang.locals.concat(),
+            )
+            .map_err(|e| e.to_string())?;
+            let names: Vec<_> = CAPTURE_SCOPES.iter().map(|(name, _)| *name).collect();
+            config.configure(&names);
+            Ok(config)
+        })
+        .as_ref()
+        .map_err(|e| {
+            crate::Error::generic_err(format!(
+                "Invalid tree-sitter query for '{language_name}': {e}"
+            ))
+        })
+}
+
+pub(crate) fn run_tree_sitter_highlighting(
+    resources: &Resources,
+    text: &mut StyledText,
+    language_name: &str,
+    theme_name: &str,
+) -> crate::Result<()> {
+    let config = highlight_config(language_name)?;
+    let theme = find_theme(resources, theme_name)?;
+    let highlighter = Highlighter::new(theme);
+    let scopes = CAPTURE_SCOPES
+        .iter()
+        .map(|(_, scope)| {
+            Scope::new(scope).map_err(|e| crate::Error::generic_err(format!("{e:?}")))
+        })
+        .collect::<crate::Result<Vec<_>>>()?;
+    let mut styles_cache = HashMap::new();
+
+    let mut ts_highlighter = tree_sitter_highlight::Highlighter::new();
+    let events = ts_highlighter
+        .highlight(config, text.text.as_bytes(), None, |_| None)
+        .map_err(|e| crate::Error::generic_err(format!("Syntax highlight error: {e}")))?;
+    let mut stack: Vec<Scope> = Vec::new();
+    let mut styles = Vec::new();
+    for event in events {
+        match event
+            .map_err(|e| crate::Error::generic_err(format!("Syntax highlight error: {e}")))?
+        {
+            HighlightEvent::HighlightStart(highlight) => stack.push(scopes[highlight.0]),
+            HighlightEvent::HighlightEnd => {
+                stack.pop();
+            }
+            HighlightEvent::Source { start, end } => {
+                let style = styles_cache
+                    .entry(stack.clone())
+                    .or_insert_with(|| create_style(highlighter.style_for_stack(&stack)));
+                styles.push(StyledRange {
+                    start: start as u32,
+                    end: end as u32,
+                    style: style.clone(),
+                });
+            }
+        }
+    }
+    styles.append(&mut text.styles);
+    text.styles = styles;
+    Ok(())
+}
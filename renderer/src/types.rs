@@ -18,6 +18,19 @@ impl NodeId {
     // }
 }
 
+#[derive(Debug, Copy, Clone, Hash, PartialOrd, PartialEq, Ord, Eq)]
+pub struct ShapeId(usize);
+
+impl ShapeId {
+    pub fn new(shape_id: usize) -> Self {
+        ShapeId(shape_id)
+    }
+
+    pub fn as_usize(self) -> usize {
+        self.0
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 #[cfg_attr(test, derive(PartialEq))]
 pub enum Length {
@@ -101,6 +114,13 @@ pub enum LayoutExpr {
     ParentHeight {
         fraction: f32,
     },
+    /// Width of the page; the page starts at (0, 0), so a constant is a page-relative coordinate
+    PageWidth {
+        fraction: f32,
+    },
+    PageHeight {
+        fraction: f32,
+    },
     X {
         node_id: NodeId,
     },
@@ -151,6 +171,31 @@ pub enum LayoutExpr {
         inline_id: InlineId,
         fraction: f32,
     },
+    /// Left edge of the bounding box of a shape
+    ShapeX {
+        shape_id: ShapeId,
+    },
+    /// Top edge of the bounding box of a shape
+    ShapeY {
+        shape_id: ShapeId,
+    },
+    ShapeWidth {
+        shape_id: ShapeId,
+        fraction: f32,
+    },
+    ShapeHeight {
+        shape_id: ShapeId,
+        fraction: f32,
+    },
+    /// X coordinate of a point at the given fraction of the length of the shape outline
+    ShapePointX {
+        shape_id: ShapeId,
+        fraction: f32,
+    },
+    ShapePointY {
+        shape_id: ShapeId,
+        fraction: f32,
+    },
     Add {
         expressions: Box<(LayoutExpr, LayoutExpr)>,
     },
@@ -160,9 +205,32 @@ pub enum LayoutExpr {
     Mul {
         expressions: Box<(LayoutExpr, LayoutExpr)>,
     },
+    /// Division by zero gives zero
+    Div {
+        expressions: Box<(LayoutExpr, LayoutExpr)>,
+    },
+    Neg {
+        expression: Box<LayoutExpr>,
+    },
+    Abs {
+        expression: Box<LayoutExpr>,
+    },
+    /// Maximum of expressions; zero for an empty list
     Max {
         expressions: Vec<LayoutExpr>,
     },
+    /// Minimum of expressions; zero for an empty list
+    Min {
+        expressions: Vec<LayoutExpr>,
+    },
+    /// (value, min, max); the lower bound wins when min > max
+    Clamp {
+        expressions: Box<(LayoutExpr, LayoutExpr, LayoutExpr)>,
+    },
+    /// Linear interpolation (a, b, t); a for t = 0 and b for t = 1
+    Lerp {
+        expressions: Box<(LayoutExpr, LayoutExpr, LayoutExpr)>,
+    },
 }
 
 #[allow(clippy::should_implement_trait)]
@@ -186,6 +254,25 @@ impl LayoutExpr {
         LayoutExpr::Max { expressions }
     }
 
+    #[inline]
+    pub fn min(expressions: Vec<LayoutExpr>) -> LayoutExpr {
+        LayoutExpr::Min { expressions }
+    }
+
+    #[inline]
+    pub fn clamp(self, min: LayoutExpr, max: LayoutExpr) -> LayoutExpr {
+        LayoutExpr::Clamp {
+            expressions: Box::new((self, min, max)),
+        }
+    }
+
+    #[inline]
+    pub fn lerp(self, other: LayoutExpr, t: LayoutExpr) -> LayoutExpr {
+        LayoutExpr::Lerp {
+            expressions: Box::new((self, other, t)),
+        }
+    }
+
     #[inline]
     pub fn sub(self, other: LayoutExpr) -> LayoutExpr {
         LayoutExpr::Sub {
@@ -199,4 +286,25 @@ impl LayoutExpr {
             expressions: Box::new((self, other)),
         }
     }
+
+    #[inline]
+    pub fn div(self, other: LayoutExpr) -> LayoutExpr {
+        LayoutExpr::Div {
+            expressions: Box::new((self, other)),
+        }
+    }
+
+    #[inline]
+    pub fn neg(self) -> LayoutExpr {
+        LayoutExpr::Neg {
+            expression: Box::new(self),
+        }
+    }
+
+    #[inline]
+    pub fn abs(self) -> LayoutExpr {
+        LayoutExpr::Abs {
+            expression: Box::new(self),
+        }
+    }
 }
@@ -0,0 +1,17 @@
+use std::fmt;
+
+fn helper() -> u32 {
+    42
+}
+
+impl Point {
+    // region: new
+    pub fn new(x: f32) -> Self {
+        Point { x }
+    }
+    // endregion: new
+
+    // region: other
+    fn other(&self) {}
+    // endregion
+}
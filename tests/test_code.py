@@ -1,6 +1,12 @@
+import os
+
+import pytest
+
+from conftest import ASSETS_DIR
 from testutils import check
 
-from nelsie import TextStyle
+from nelsie import SlideDeck, StepVal, TextStyle, load_code
+from nelsie import watch
 
 
 @check(n_slides=2)
@@ -114,3 +120,281 @@ def test_code_language_none(deck):
 def test_code_language_default(deck):
     slide = deck.new_slide(width=200, height=30)
     slide.code("print('Hello world!')", style=TextStyle(size=12))
+
+
+def test_code_line_numbers(deck):
+    slide = deck.new_slide(width=400, height=300)
+    code = "x = 1\ny = 2\n# comment\nz = x + y"
+    c1 = slide.code(code, "Python")
+    c2 = slide.code(code, "Python", line_numbers=True)
+    c3 = slide.code(code, "Python", line_numbers=98)
+    c4 = slide.code(code, "Python", line_numbers=True, line_numbers_separator=" | ")
+    layout = deck.render(None, "layout")[0]
+    w1 = layout[id(c1)]["width"]
+    w2 = layout[id(c2)]["width"]
+    w3 = layout[id(c3)]["width"]
+    w4 = layout[id(c4)]["width"]
+    assert w1 < w2 < w3
+    assert w2 < w4
+    assert layout[id(c1)]["height"] == pytest.approx(layout[id(c2)]["height"])
+
+
+def test_code_line_numbers_negative(deck):
+    slide = deck.new_slide()
+    with pytest.raises(ValueError, match="Number of the first line cannot be negative: -1"):
+        slide.code("x = 1", "Python", line_numbers=-1)
+
+
+def test_code_line_numbers_style(deck):
+    deck.set_style("line_numbers", TextStyle(color="#00ff00"))
+    slide = deck.new_slide(width=400, height=300)
+    slide.code("x = 1", "Python", line_numbers=True)
+    slide.code("x = 1", "Python", line_numbers=True, line_numbers_style=TextStyle(color="#0000ff"))
+    svg = deck.render(None, "svg")[0]
+    assert "fill='#00ff00'" in svg
+    assert "fill='#0000ff'" in svg
+
+
+def test_code_highlight_lines(deck):
+    slide = deck.new_slide(width=400, height=300)
+    slide.code(
+        "a = 1\nb = 2\nc = 3",
+        "Python",
+        line_numbers=10,
+        highlight_lines=StepVal("10").at(2, "11-12").at(3, None),
+        highlight_color="#ff0000",
+    )
+    svgs = deck.render(None, "svg")
+    assert len(svgs) == 3
+    assert svgs[0].count("fill='#ff0000'") == 1
+    assert svgs[1].count("fill='#ff0000'") == 2
+    assert "fill='#ff0000'" not in svgs[2]
+
+
+def test_code_highlight_lines_invalid(deck):
+    slide = deck.new_slide()
+    with pytest.raises(Exception, match="Invalid line range"):
+        slide.code("a = 1", "Python", highlight_lines="3-1")
+
+
+def test_code_theme_colors(deck):
+    slide = deck.new_slide(width=400, height=300)
+    slide.code("a = 1\nb = 2", "Python", theme="base16-ocean.dark", theme_background=True, highlight_lines="2")
+    slide.code("a = 1", "Python", theme="base16-ocean.dark", theme_background=True, bg_color="#00ff00")
+    slide.code("a = 1", "Python", theme="InspiredGitHub", line_numbers=True)
+    svg = deck.render(None, "svg")[0]
+    assert svg.count("fill='#2b303b'") == 1
+    assert svg.count("fill='#4f5b66'") == 1
+    assert svg.count("fill='#00ff00'") == 1
+    assert "fill='#b3b3b3'" in svg
+
+
+def test_code_tree_sitter(deck):
+    slide = deck.new_slide(width=400, height=300)
+    slide.code("fn main() { println!(\"Hi\"); }", "Rust", syntax_backend="tree-sitter")
+    slide.code("x = 'Hi'  # comment", "py", syntax_backend="tree-sitter")
+    layout = deck.render(None, "layout")
+    assert len(layout) == 1
+    svg = deck.render(None, "svg")[0]
+    # keyword, string and comment colors of InspiredGitHub
+    assert "fill='#a71d5d'" in svg
+    assert "fill='#183691'" in svg
+    assert "fill='#969896'" in svg
+
+
+def test_code_tree_sitter_default_backend(resources):
+    deck = SlideDeck(resources=resources, default_syntax_backend="tree-sitter")
+    slide = deck.new_slide()
+    # Zig is not available in syntect
+    slide.code("const x = 1;", "zig")
+    svg = deck.render(None, "svg")[0]
+    assert "fill='#a71d5d'" in svg
+
+
+def test_code_tree_sitter_invalid(deck):
+    slide = deck.new_slide()
+    with pytest.raises(Exception, match="Invalid syntax highlighting backend"):
+        slide.code("x = 1", "Python", syntax_backend="xyz")
+    slide.code("x = 1", "Brainfuck", syntax_backend="tree-sitter")
+    with pytest.raises(Exception, match="Language 'Brainfuck' for tree-sitter syntax highlighting not found"):
+        deck.render(None, "svg")
+
+
+def test_code_diff(deck):
+    slide = deck.new_slide(width=400, height=300)
+    slide.code(
+        "a = 1\nb = 3\nc = 4",
+        "Python",
+        diff_from="a = 1\nb = 2",
+        diff_added_color="#00ff0040",
+        diff_removed_color="#ff000040",
+    )
+    svg = deck.render(None, "svg")[0]
+    # backgrounds
+    assert svg.count("fill='#ff000040'") == 1
+    assert svg.count("fill='#00ff0040'") == 2
+    # markers
+    assert svg.count("fill='#ff0000'") == 1
+    assert svg.count("fill='#00ff00'") == 2
+
+
+def test_code_unified_diff(deck):
+    slide = deck.new_slide(width=400, height=300)
+    slide.code(
+        """
+--- a/x.py
++++ b/x.py
+@@ -1,2 +1,2 @@
+ a = 1
+-b = 2
++b = 3
+\\ No newline at end of file
+""",
+        "Python",
+        unified_diff=True,
+        line_numbers=True,
+        highlight_lines="2",
+        highlight_color="#0000ff",
+        diff_added_color="#00ff0040",
+        diff_removed_color="#ff000040",
+    )
+    svg = deck.render(None, "svg")[0]
+    assert svg.count("fill='#ff000040'") == 1
+    assert svg.count("fill='#00ff0040'") == 1
+    # Line 2 is the added line, removed line is not numbered
+    assert svg.count("fill='#0000ff'") == 1
+
+
+def test_code_diff_invalid(deck):
+    slide = deck.new_slide()
+    with pytest.raises(Exception, match="cannot be used together"):
+        slide.code("+a", "Python", unified_diff=True, diff_from="a")
+    slide.code("x = 1\n?", "Python", unified_diff=True, strip=False)
+    with pytest.raises(Exception, match="Invalid line in unified diff"):
+        deck.render(None, "svg")
+
+
+def test_code_anchors(deck):
+    slide = deck.new_slide(width=400, height=300)
+    code = "let x = 1;\nlet yy = x + 2;"
+    c1 = slide.code(code, "Rust", anchors={1: "yy", 2: (2, 10, 10), 3: r"let (x)", 4: "zz"})
+    c2 = slide.code("~1{let} x = 1;\nlet ~2{yy} = ~3{x} + 2;", "Rust", parse_styles=True, line_numbers=True)
+    boxes = {}
+    for code_box, anchor_id in [(c1, 1), (c1, 2), (c1, 3), (c1, 4), (c2, 2), (c2, 3)]:
+        boxes[(code_box, anchor_id)] = slide.box(
+            x=code_box.inline_x(anchor_id), width=code_box.inline_width(anchor_id), height=1
+        )
+    layout = deck.render(None, "layout")[0]
+
+    def width(code_box, anchor_id):
+        return layout[id(boxes[(code_box, anchor_id)])]["width"]
+
+    assert width(c1, 1) == pytest.approx(width(c2, 2))
+    assert width(c1, 2) == pytest.approx(width(c2, 3))
+    assert width(c1, 3) == pytest.approx(width(c2, 3))
+    # Pattern is not found
+    assert width(c1, 4) == 0
+
+
+def test_code_anchor_styles(deck):
+    slide = deck.new_slide(width=400, height=300)
+    slide.code(
+        "let x = 1;\nlet y = x;",
+        "Rust",
+        line_numbers=True,
+        anchors={1: (2, 9, 9)},
+        anchor_styles={1: StepVal(None).at(2, TextStyle(color="#ff0000"))},
+    )
+    svgs = deck.render(None, "svg")
+    assert len(svgs) == 2
+    assert "fill='#ff0000'" not in svgs[0]
+    assert svgs[1].count("fill='#ff0000'") == 1
+
+
+def test_code_anchors_invalid(deck):
+    slide = deck.new_slide()
+    with pytest.raises(ValueError, match="Invalid anchor target"):
+        slide.code("x", "Rust", anchors={1: (0, 1, 1)})
+    with pytest.raises(ValueError, match="Style for an unknown anchor"):
+        slide.code("x", "Rust", anchors={1: "x"}, anchor_styles={2: TextStyle(color="red")})
+    slide.code("x", "Rust", anchors={1: "("})
+    with pytest.raises(Exception, match="Invalid pattern"):
+        deck.render(None, "svg")
+
+
+def test_load_code():
+    path = os.path.join(ASSETS_DIR, "snippet.rs")
+    assert load_code(path, region="new") == "pub fn new(x: f32) -> Self {\n    Point { x }\n}"
+    assert load_code(path, region="new", dedent=False) == "    pub fn new(x: f32) -> Self {\n        Point { x }\n    }"
+    assert load_code(path, region="other") == "fn other(&self) {}"
+    assert load_code(path, lines=(3, 5)) == "fn helper() -> u32 {\n    42\n}"
+    assert load_code(path, region="new", lines=(2, 2)) == "Point { x }"
+    with pytest.raises(ValueError, match="Region 'xyz' not found"):
+        load_code(path, region="xyz")
+    with pytest.raises(ValueError, match="Invalid line range"):
+        load_code(path, lines=(3, 1))
+
+
+def test_load_code_region_word_in_code(tmp_path):
+    path = os.path.join(tmp_path, "code.py")
+    with open(path, "w") as f:
+        f.write(
+            "region = 1\n"
+            "# region: main\n"
+            "regions = find_region(region)\n"
+            "# region: inner\n"
+            "print(region, 'endregion')\n"
+            "# endregion: inner\n"
+            "# endregion: main\n"
+        )
+    # Only marker lines of the requested region are removed
+    assert load_code(path, region="main") == (
+        "regions = find_region(region)\n# region: inner\nprint(region, 'endregion')\n# endregion: inner"
+    )
+    assert load_code(path, region="inner") == "print(region, 'endregion')"
+    assert load_code(path, lines=(1, 1)) == "region = 1"
+
+
+def test_load_code_region_marker_forms(tmp_path):
+    path = os.path.join(tmp_path, "code.txt")
+    with open(path, "w") as f:
+        f.write(
+            "#region a\n"
+            "A\n"
+            "#endregion\n"
+            "// #region b\n"
+            "B\n"
+            "// #endregion b\n"
+            "//region c\n"
+            "C\n"
+            "//endregion\n"
+            "<!-- #region d -->\n"
+            "D\n"
+            "<!-- #endregion -->\n"
+            "#region e\n"
+            "E\n"
+            "#endregion e\n"
+        )
+    for region in "abcde":
+        assert load_code(path, region=region) == region.upper()
+
+
+def test_load_code_watch():
+    path = os.path.join(ASSETS_DIR, "snippet.rs")
+    watch._WATCH_SET = set()
+    try:
+        load_code(path, region="new")
+        assert watch._WATCH_SET == {os.path.abspath(path)}
+    finally:
+        watch._WATCH_SET = None
+
+
+def test_code_file(deck):
+    path = os.path.join(ASSETS_DIR, "snippet.rs")
+    slide = deck.new_slide(width=400, height=300)
+    slide.code_file(path, region="new", line_numbers=True)
+    slide = deck.new_slide(width=400, height=300)
+    slide.code("pub fn new(x: f32) -> Self {\n    Point { x }\n}", "Rust", line_numbers=9)
+    svgs = deck.render(None, "svg")
+    # Lines are numbered as in the file and the language is derived from the extension
+    assert svgs[0] == svgs[1]
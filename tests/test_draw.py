@@ -1,7 +1,8 @@
+import pytest
 from nelsie.shapes import Point
 from testutils import check
 
-from nelsie import Arrow, Path, TextStyle, Rect, Stroke, Oval, StepVal
+from nelsie import Arrow, Path, TextStyle, Rect, Stroke, Oval, StepVal, Connector
 
 
 @check(n_slides=2)
@@ -201,3 +202,56 @@ def test_path_close(deck):
         .line_to(Point(70, 150))
         .close()
     )
+
+
+def test_connector_points(deck):
+    slide = deck.new_slide(width=400, height=300)
+    a = slide.box(x=0, y=0, width=100, height=100)
+    b = slide.box(x=300, y=100, width=100, height=100)
+    c = slide.box(x=0, y=200, width=100, height=100, border_radius=50)
+    straight = Connector(a, b)
+    sides = Connector(a, b, start_side="bottom", end_side="top")
+    rounded = Connector(c, a, end_side="right")
+    orthogonal = Connector(a, b, routing="orthogonal", arrow_end=Arrow())
+    curved = Connector(c, b, routing="curved")
+    for connector in (straight, sides, rounded, orthogonal, curved):
+        slide.add(connector)
+    markers = {}
+    for name, connector in (
+        ("straight", straight),
+        ("sides", sides),
+        ("rounded", rounded),
+        ("orthogonal", orthogonal),
+        ("curved", curved),
+    ):
+        for fraction in (0, 0.5, 1):
+            p = connector.point_at(fraction)
+            markers[name, fraction] = slide.box(x=p.x, y=p.y, width=1, height=1)
+
+    layout = deck.render(None, "layout")[0]
+
+    def point(name, fraction):
+        r = layout[id(markers[name, fraction])]
+        return r["x"], r["y"]
+
+    assert point("straight", 0) == pytest.approx((100, 200 / 3))
+    assert point("straight", 1) == pytest.approx((300, 400 / 3))
+    assert point("sides", 0) == pytest.approx((50, 100))
+    assert point("sides", 1) == pytest.approx((350, 100))
+    # The line from the center of the rounded box to the right side of "a" leaves it through the rounded corner
+    assert point("rounded", 0) == pytest.approx((62.28, 200.86), abs=0.05)
+    assert point("rounded", 1) == pytest.approx((100, 50))
+    assert point("orthogonal", 0) == pytest.approx((100, 50))
+    assert point("orthogonal", 0.5) == pytest.approx((200, 100))
+    assert point("orthogonal", 1) == pytest.approx((300, 150))
+    assert point("curved", 0) == pytest.approx((100, 250))
+    assert point("curved", 1) == pytest.approx((300, 150))
+
+
+def test_connector_invalid(deck):
+    slide = deck.new_slide()
+    a = slide.box()
+    with pytest.raises(ValueError, match="Invalid connector side"):
+        Connector(a, a, start_side="middle")
+    with pytest.raises(ValueError, match="Invalid connector routing"):
+        Connector(a, a, routing="zigzag")
@@ -1,4 +1,21 @@
-from nelsie import Stroke, Path, TextStyle, GridOptions as G, Point
+import warnings
+
+import pytest
+
+from nelsie import (
+    LayoutWarning,
+    Box,
+    LayoutExpr,
+    page,
+    Stroke,
+    Path,
+    TextStyle,
+    GridOptions as G,
+    Point,
+    Arrow,
+    Rect,
+    Oval,
+)
 from testutils import check
 
 
@@ -201,6 +218,41 @@ def test_m_xy_p_xy(deck):
     box.box(width=30, height=30, bg_color="red")
 
 
+def test_layout_min_max_size(deck):
+    slide = deck.new_slide(width=400, height=300)
+    row = slide.box(row=True, width=400)
+    limited = row.box(flex_grow=1, height=10, max_width=100)
+    rest = row.box(flex_grow=1, height=10)
+    wide = slide.box(width=500, height=10, max_width="50%")
+    empty = slide.box(min_width=120, min_height=30)
+    text = slide.text("Hello world", min_width=300, max_height=10)
+
+    layout = deck.render(None, "layout")[0]
+    assert layout[id(limited)]["width"] == pytest.approx(100)
+    assert layout[id(rest)]["width"] == pytest.approx(300)
+    assert layout[id(wide)]["width"] == pytest.approx(200)
+    assert layout[id(empty)]["width"] == pytest.approx(120)
+    assert layout[id(empty)]["height"] == pytest.approx(30)
+    assert layout[id(text)]["width"] == pytest.approx(300)
+    assert layout[id(text)]["height"] == pytest.approx(10)
+
+
+def test_layout_flex_basis(deck):
+    slide = deck.new_slide(width=400, height=300)
+    row = slide.box(row=True, width=400)
+    a = row.box(flex_basis=100, flex_grow=1, height=10)
+    b = row.box(flex_basis=0, flex_grow=1, height=10)
+    row = slide.box(row=True, width=400)
+    c = row.box(flex_basis="25%", height=10)
+    d = row.box(width=50, height=5, m_left="auto")
+
+    layout = deck.render(None, "layout")[0]
+    assert layout[id(a)]["width"] == pytest.approx(250)
+    assert layout[id(b)]["width"] == pytest.approx(150)
+    assert layout[id(c)]["width"] == pytest.approx(100)
+    assert layout[id(d)]["x"] == pytest.approx(350)
+
+
 @check()
 def test_layout_grid_in_pixels(deck):
     slide = deck.new_slide(width=300, height=100)
@@ -277,3 +329,367 @@ def test_layout_grid_table(deck):
         table.box(grid=G(column=1, row=i)).text(row[0], s)
         table.box(grid=G(column=2, row=i), row=True, justify_content="end", m_right=30).text(str(row[1]), s)
         table.box(grid=G(column=3, row=i), row=True, justify_content="start", m_left=30).text(row[2], s)
+
+
+def test_layout_grid_tracks(deck):
+    slide = deck.new_slide(width=400, height=200)
+    grid = slide.box(
+        width=400,
+        height=200,
+        grid=G(
+            template_columns=["repeat(2, 50, 1fr)", "minmax(20, 40)", "fit-content(30)"],
+            template_rows=["repeat(auto-fill, 50)"],
+        ),
+    )
+    boxes = [grid.box(grid=G(column=i, row=1), width=10, height=20) for i in range(1, 7)]
+    last = grid.box(grid=G(column=-2, row=-2), width=100, height=10)
+
+    layout = deck.render(None, "layout")[0]
+    # The last column is as wide as its content (100), fractions share the remaining 160
+    assert [layout[id(b)]["x"] for b in boxes] == pytest.approx([0, 50, 130, 180, 260, 300])
+    assert layout[id(boxes[0])]["y"] == pytest.approx(0)
+    assert layout[id(last)]["x"] == pytest.approx(300)
+    # Four rows of 50 fit into the grid
+    assert layout[id(last)]["y"] == pytest.approx(150)
+
+
+def test_layout_grid_auto_flow(deck):
+    slide = deck.new_slide(width=400, height=200)
+    grid = slide.box(
+        width=200,
+        height=200,
+        grid=G(template_columns=[100, 100], template_rows=[50, 50], auto_flow="column", auto_columns=[30]),
+    )
+    boxes = [grid.box(width=10, height=10) for _ in range(6)]
+
+    layout = deck.render(None, "layout")[0]
+    positions = [(layout[id(b)]["x"] - layout[id(grid)]["x"], layout[id(b)]["y"]) for b in boxes]
+    assert positions == pytest.approx([(0, 0), (0, 50), (100, 0), (100, 50), (200, 0), (200, 50)])
+
+
+def test_layout_grid_areas(deck):
+    slide = deck.new_slide(width=400, height=300)
+    grid = slide.box(
+        width=400,
+        height=300,
+        grid=G(
+            template_areas=["head head", "nav main", ". foot"],
+            template_rows=[50, "1fr", 30],
+            template_columns=[100, "1fr"],
+        ),
+    )
+    head = grid.box(grid=G(area="head"))
+    nav = grid.box(grid=G(area="nav"))
+    main = grid.box(grid=G(row="main", column="main"))
+    middle = grid.box(grid=G(row=("head", "nav"), column="nav-end"))
+    bottom = grid.box(grid=G(row="foot", column=("nav", "main")))
+
+    layout = deck.render(None, "layout")[0]
+    assert layout[id(head)] == pytest.approx({"x": 0, "y": 0, "width": 400, "height": 50})
+    assert layout[id(nav)] == pytest.approx({"x": 0, "y": 50, "width": 100, "height": 220})
+    assert layout[id(main)] == pytest.approx({"x": 100, "y": 50, "width": 300, "height": 220})
+    assert layout[id(middle)] == pytest.approx({"x": 100, "y": 0, "width": 300, "height": 270})
+    assert layout[id(bottom)] == pytest.approx({"x": 0, "y": 270, "width": 400, "height": 30})
+
+
+def test_layout_grid_areas_without_templates(deck):
+    slide = deck.new_slide(width=400, height=300)
+    grid = slide.box(width=300, height=100, grid=G(template_areas=["a b c"]))
+    c = grid.box(grid=G(area="c"), width=20, height=20)
+    b = grid.box(grid=G(area="b"), width=20, height=20)
+
+    layout = deck.render(None, "layout")[0]
+    assert layout[id(b)]["x"] < layout[id(c)]["x"]
+    assert layout[id(b)]["y"] == layout[id(c)]["y"]
+
+
+def test_layout_grid_invalid(deck):
+    with pytest.raises(Exception, match="Invalid grid template"):
+        G(template_columns=["minmax(1fr)"])
+    with pytest.raises(Exception, match="Invalid grid template"):
+        G(template_rows=["repeat(0, 10)"])
+    with pytest.raises(Exception, match="Invalid grid template"):
+        G(auto_rows=["repeat(2, 10)"])
+    with pytest.raises(Exception, match="Grid area 'a' is not a rectangle"):
+        G(template_areas=["a b", "b a"])
+    with pytest.raises(Exception, match="same number of cells"):
+        G(template_areas=["a b", "c"])
+    with pytest.raises(Exception, match="Invalid grid auto flow"):
+        G(auto_flow="diagonal")
+
+    slide = deck.new_slide()
+    grid = slide.box(grid=G(template_areas=["a b"]))
+    grid.box(grid=G(area="x"))
+    with pytest.raises(Exception, match="Unknown grid area 'x'"):
+        deck.render(None, "layout")
+
+
+def test_layout_grid_line_zero(deck_builder):
+    # There is no grid line 0
+    for placement in [dict(column=0), dict(row=(1, "0")), dict(column="span 0")]:
+        deck = deck_builder()
+        deck.new_slide().box(grid=G(template_columns=[10, 10])).box(grid=G(**placement))
+        with pytest.raises(Exception, match="Invalid grid placement"):
+            deck.render(None, "layout")
+
+
+def test_layout_expr_forward_reference(deck):
+    slide = deck.new_slide(width=200, height=200)
+    a = slide.box(width=50, height=20)
+    b = slide.box(width=30, height=40)
+    c = a.box(x=b.x(), y=b.y(0.5), width=b.width(2), height=b.height())
+    layout = deck.render(None, "layout")[0]
+    assert layout[id(c)]["x"] == pytest.approx(layout[id(b)]["x"])
+    assert layout[id(c)]["y"] == pytest.approx(layout[id(b)]["y"] + 20)
+    assert layout[id(c)]["width"] == pytest.approx(60)
+    assert layout[id(c)]["height"] == pytest.approx(40)
+
+
+def test_layout_expr_depends_on_child(deck):
+    slide = deck.new_slide(width=200, height=200)
+    child = Box(width=40, height=30)
+    parent = slide.box(width=child.width(2), height=child.height(), bg_color="green")
+    parent.add(child)
+    # Position of the child box depends on the parent box, but not on its size
+    child2 = Box(x=parent.x(0.5), y=20, width=10, height=10)
+    parent.add(child2)
+    layout = deck.render(None, "layout")[0]
+    assert layout[id(parent)]["width"] == pytest.approx(80)
+    assert layout[id(parent)]["height"] == pytest.approx(30)
+    assert layout[id(child2)]["x"] == pytest.approx(layout[id(parent)]["x"] + 40)
+
+
+def test_layout_expr_cycle(deck):
+    slide = deck.new_slide(width=200, height=200)
+    child = Box(width=10, height=10, name="child")
+    parent = slide.box(y=child.y(), name="parent")
+    parent.add(child)
+    with pytest.raises(
+        Exception, match="^Cycle in layout expressions: y of box 'parent' -> y of box 'child' -> y of box 'parent'$"
+    ):
+        deck.render(None, "layout")
+
+
+def test_layout_expr_other_page(deck):
+    slide1 = deck.new_slide(width=200, height=200)
+    a = slide1.box(width=10, height=10, name="a")
+    slide2 = deck.new_slide(width=200, height=200)
+    slide2.box(x=a.x(), name="b")
+    with pytest.raises(
+        Exception, match="^Layout expression for x of box 'b' refers to box 'a' that is not on the same page$"
+    ):
+        deck.render(None, "layout")
+
+
+def test_layout_shape_other_page(deck):
+    slide1 = deck.new_slide(width=200, height=200)
+    a = slide1.box(width=10, height=10, name="a")
+    slide2 = deck.new_slide(width=200, height=200)
+    b = slide2.box(width=10, height=10, name="b")
+    b.draw_line(a.p(0, 0), b.p(1, 1))
+    with pytest.raises(Exception, match="^A shape in box 'b' refers to box 'a' that is not on the same page$"):
+        deck.render(None, "layout")
+
+
+def test_layout_expr_functions(deck):
+    slide = deck.new_slide(width=400, height=300)
+    a = slide.box(x=-50, y=10, width=100, height=40)
+    b = slide.box(x=200, y=100, width=60, height=20)
+
+    def box(**kwargs):
+        return slide.box(**{"width": 10, "height": 10, **kwargs})
+
+    boxes = [
+        box(x=LayoutExpr.max([a.x(), -100]), y=LayoutExpr.min([a.y(), b.y()])),
+        box(x=a.width() / 4, y=b.x() / (a.y() - 10)),
+        box(x=-a.x(), y=abs(a.x())),
+        box(x=LayoutExpr.clamp(b.x(), 0, 150), y=LayoutExpr.clamp(a.x(), 0, 150)),
+        box(x=LayoutExpr.lerp(a.x(1), b.x(), 0.5), y=LayoutExpr.lerp(a.y(), b.y(), b.height() / 80)),
+        box(x=300 - b.x(), y=2 * b.y() + 1, width=1 / b.height(0.25)),
+    ]
+    layout = deck.render(None, "layout")[0]
+    result = [(layout[id(bx)]["x"], layout[id(bx)]["y"]) for bx in boxes]
+    assert result == [
+        pytest.approx((-50, 10)),
+        pytest.approx((25, 0)),
+        pytest.approx((50, 50)),
+        pytest.approx((150, 0)),
+        pytest.approx((125, 32.5)),
+        pytest.approx((100, 201)),
+    ]
+    assert layout[id(boxes[-1])]["width"] == pytest.approx(0.2)
+
+
+def test_layout_expr_invalid():
+    with pytest.raises(ValueError, match="At least one expression is required"):
+        LayoutExpr.min([])
+    with pytest.raises(Exception, match="Expect"):
+        LayoutExpr.clamp(1, "x", 2)
+
+
+def test_layout_expr_size_reflows_siblings(deck):
+    slide = deck.new_slide(width=400, height=300)
+    title = slide.box(width=120, height=20)
+    row = slide.box(row=True)
+    a = row.box(width=title.width(), height=title.height(0.5))
+    b = row.box(width=30, height=10)
+    column = slide.box()
+    c = column.box(width=10, height=b.width())
+    d = column.box(width=10, height=10)
+    layout = deck.render(None, "layout")[0]
+
+    assert layout[id(a)]["width"] == pytest.approx(120)
+    assert layout[id(a)]["height"] == pytest.approx(10)
+    assert layout[id(row)]["width"] == pytest.approx(150)
+    assert layout[id(row)]["x"] == pytest.approx(125)
+    assert layout[id(b)]["x"] == pytest.approx(245)
+
+    assert layout[id(column)]["height"] == pytest.approx(40)
+    assert layout[id(d)]["y"] == pytest.approx(layout[id(c)]["y"] + 30)
+
+
+def test_layout_expr_size_chain(deck):
+    slide = deck.new_slide(width=400, height=300)
+    row = slide.box(row=True)
+    a = row.box(width=50, height=10)
+    b = row.box(width=a.width(2), height=10)
+    # Depends on the position of "b" that depends on the size of "b"
+    c = slide.box(width=b.x(1) - row.x(), height=10)
+    layout = deck.render(None, "layout")[0]
+    assert layout[id(b)]["x"] == pytest.approx(layout[id(a)]["x"] + 50)
+    assert layout[id(c)]["width"] == pytest.approx(150)
+    assert layout[id(c)]["x"] == pytest.approx(125)
+
+
+def test_layout_page_anchors(deck):
+    slide = deck.new_slide(width=400, height=300)
+    component = slide.box(width=100, height=100).box(width=50, height=50)
+    logo = component.box(x=page.x(1) - 30, y=page.y(1) - 20, width=page.width(0.05), height=page.height(0.1))
+    footer = component.box(x=0, y="90%", width="100%", height=10, origin="page")
+    inner = component.box(x="50%", y=5, width=10, height=10, origin=slide)
+    layout = deck.render(None, "layout")[0]
+    assert layout[id(logo)] == pytest.approx({"x": 370, "y": 280, "width": 20, "height": 30})
+    assert layout[id(footer)]["x"] == pytest.approx(0)
+    assert layout[id(footer)]["y"] == pytest.approx(270)
+    # Sizes are still relative to the parent
+    assert layout[id(footer)]["width"] == pytest.approx(50)
+    assert layout[id(inner)]["x"] == pytest.approx(200)
+    assert layout[id(inner)]["y"] == pytest.approx(5)
+
+
+def test_layout_origin_ancestor(deck):
+    slide = deck.new_slide(width=400, height=300)
+    outer = slide.box(width=200, height=100)
+    inner = outer.box(width=20, height=20).box(width=10, height=10)
+    b = inner.box(x="100%", y=10, width=5, height=5, origin=outer)
+    layout = deck.render(None, "layout")[0]
+    assert layout[id(b)]["x"] == pytest.approx(layout[id(outer)]["x"] + 200)
+    assert layout[id(b)]["y"] == pytest.approx(layout[id(outer)]["y"] + 10)
+
+    with pytest.raises(Exception, match="Invalid origin"):
+        slide.box(origin="xxx")
+
+
+def test_layout_shape_anchors(deck):
+    slide = deck.new_slide(width=400, height=300)
+    arrow = Path(stroke=Stroke(color="black"), arrow_end=Arrow()).move_to(Point(100, 50)).line_to(Point(300, 150))
+    slide.add(arrow)
+    label = slide.box(x=arrow.point_at(0.5).x - 10, y=arrow.point_at(0.5).y, width=20, height=10)
+    # A connector attached to the middle of another connector
+    connector = Path().move_to(arrow.point_at(0.5)).line_to(Point(200, 250))
+    slide.add(connector)
+    end = slide.box(x=connector.point_at(0.25).x, y=connector.point_at(0.25).y, width=1, height=1)
+    bbox = slide.box(x=arrow.x(), y=arrow.y(1), width=arrow.width(), height=arrow.height(0.5))
+
+    rect = Rect(Point(10, 20), Point(60, 120))
+    oval = Oval(Point(100, 200), Point(200, 250))
+    slide.add(rect)
+    slide.add(oval)
+    corner = slide.box(x=rect.point_at(0.5).x, y=rect.point_at(0.5).y, width=oval.x(1), height=oval.point_at(0.25).y)
+
+    layout = deck.render(None, "layout")[0]
+    assert layout[id(label)] == pytest.approx({"x": 190, "y": 100, "width": 20, "height": 10})
+    assert layout[id(end)]["x"] == pytest.approx(200)
+    assert layout[id(end)]["y"] == pytest.approx(137.5)
+    assert layout[id(bbox)] == pytest.approx({"x": 100, "y": 150, "width": 200, "height": 50})
+    assert layout[id(corner)]["x"] == pytest.approx(60)
+    assert layout[id(corner)]["y"] == pytest.approx(120)
+    assert layout[id(corner)]["width"] == pytest.approx(200)
+    assert layout[id(corner)]["height"] == pytest.approx(250, abs=0.01)
+
+
+def test_layout_shape_cycle(deck):
+    slide = deck.new_slide(width=200, height=200)
+    b = slide.box(width=10, height=10, name="b")
+    path = Path()
+    b.add(path)
+    b2 = slide.box(x=path.point_at(0.5).x, width=10, height=10, name="b2")
+    path.move_to(b2.p(0, 0)).line_to(Point(10, 10))
+    with pytest.raises(
+        Exception, match="^Cycle in layout expressions: x of box 'b2' -> shape in box 'b' -> x of box 'b2'$"
+    ):
+        deck.render(None, "layout")
+
+
+def test_layout_shape_other_page_ref(deck):
+    slide1 = deck.new_slide(width=200, height=200)
+    path = slide1.box(width=10, height=10, name="a").draw_line(Point(0, 0), Point(10, 10))
+    slide2 = deck.new_slide(width=200, height=200)
+    slide2.box(x=path.point_at(1).x, name="b")
+    with pytest.raises(
+        Exception,
+        match="^Layout expression for x of box 'b' refers to a shape in box 'a' that is not on the same page$",
+    ):
+        deck.render(None, "layout")
+
+
+def render_layout_warnings(deck):
+    with warnings.catch_warnings(record=True) as caught:
+        warnings.simplefilter("always", LayoutWarning)
+        deck.render(None, "layout")
+    return [w.message for w in caught if isinstance(w.message, LayoutWarning)]
+
+
+def test_layout_warnings(deck):
+    slide = deck.new_slide(width=400, height=300)
+    parent = slide.box(width=100, height=50)
+    child = parent.box(width=150, height=20, name="child")
+    # Boxes placed by a position are checked only against the page
+    parent.box(x=-20, y=0, width=10, height=10)
+    slide.box(x=350, y=10, width=100, height=10, name="right")
+    # Children of a box outside the page are not reported again
+    slide.box(x=390, y=100, width=50, height=50, name="outside").box(width=40, height=40)
+    slide.text("Hello world", width=20, name="label")
+    slide.text("Hello world", width=0, name="empty")
+
+    slide = deck.new_slide(width=400, height=300)
+    slide.box(width=100, height=100).text("Hello")
+
+    result = render_layout_warnings(deck)
+    assert [(w.page_index, w.box_name, w.kind) for w in result] == [
+        (0, "child", "parent"),
+        (0, "right", "page"),
+        (0, "outside", "page"),
+        (0, "label", "text"),
+        (0, "empty", "empty"),
+    ]
+    assert result[0].node_id == id(child)
+    assert [w.amount for w in result[:3]] == pytest.approx([25, 50, 40])
+    assert str(result[0]) == "Page 1: Box 'child' extends beyond its parent box by 25.0"
+
+
+def test_layout_warnings_empty_content(deck):
+    # Empty texts have no size, so they do not make empty boxes
+    slide = deck.new_slide(width=400, height=300)
+    slide.text("")
+    slide.code("", "Python")
+    assert render_layout_warnings(deck) == []
+
+
+def test_layout_warnings_as_errors(deck):
+    slide = deck.new_slide(width=400, height=300)
+    slide.box(width=500, height=10, name="wide")
+    with warnings.catch_warnings():
+        warnings.simplefilter("error", LayoutWarning)
+        with pytest.raises(LayoutWarning, match="Page 1: Box 'wide' extends beyond the page by 50.0"):
+            deck.render(None, "layout")
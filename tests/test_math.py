@@ -0,0 +1,69 @@
+import pytest
+
+from nelsie import TextStyle
+
+
+def test_math_sizes(deck):
+    slide = deck.new_slide(width=600, height=400)
+    style = TextStyle(size=30)
+    b1 = slide.math("x", style)
+    b2 = slide.math("x^2 + y^2", style)
+    b3 = slide.math(r"\frac{a + b}{c}", style)
+    b4 = slide.math(r"\sum_{i=1}^{n} i", style)
+    b5 = slide.math(r"\sum_{i=1}^{n} i", style, display=False)
+    layout = deck.render(None, "layout")[0]
+    assert layout[id(b1)]["width"] < layout[id(b2)]["width"]
+    assert layout[id(b3)]["height"] > layout[id(b2)]["height"]
+    # Limits are placed above and below the sum only in display style
+    assert layout[id(b4)]["height"] > layout[id(b5)]["height"]
+    assert layout[id(b4)]["width"] < layout[id(b5)]["width"]
+
+
+def test_math_color(deck):
+    slide = deck.new_slide(width=200, height=100)
+    slide.math(r"\sqrt{x}", TextStyle(color="#ff0000"))
+    svg = deck.render(None, "svg")[0]
+    assert "fill='#ff0000'" in svg
+
+
+def test_math_invalid_formula(deck):
+    slide = deck.new_slide()
+    slide.math(r"\frac{a}")
+    with pytest.raises(Exception, match="Missing argument in formula"):
+        deck.render(None, "layout")
+
+
+def test_text_inline_math(deck):
+    slide = deck.new_slide(width=600, height=300)
+    style = TextStyle(size=30)
+    t1 = slide.text("Area $\\pi r^2$ costs \\$5", style, parse_math=True)
+    t2 = slide.text("Area $\\pi r^2$ costs \\$5", style)
+    t3 = slide.text("Area ~1{$x$} is ~2{big}$y$", style, parse_math=True)
+    b1 = slide.box(x=t3.inline_x(1), y=0, width=t3.inline_width(1), height=10)
+    b2 = slide.box(x=t3.inline_x(2), y=0, width=t3.inline_width(2), height=10)
+    t4 = slide.text("Area ~1{} is ~2{big}", style)
+    b3 = slide.box(x=t4.inline_x(2), y=0, width=t4.inline_width(2), height=10)
+    layout = deck.render(None, "layout")[0]
+    assert layout[id(t1)]["width"] < layout[id(t2)]["width"]
+    # An anchor around a formula has its width, an anchor before a formula does not contain it
+    assert layout[id(b1)]["width"] > 5
+    assert layout[id(b2)]["width"] == pytest.approx(layout[id(b3)]["width"])
+
+
+def test_text_inline_math_unclosed(deck):
+    slide = deck.new_slide()
+    slide.text("Price: $5", parse_math=True)
+    with pytest.raises(Exception, match="Unclosed math formula"):
+        deck.render(None, "layout")
+
+
+def test_text_inline_math_with_placeholder_char(deck):
+    # U+2060 (word joiner) in the text itself is not taken for a formula
+    slide = deck.new_slide(width=600, height=300)
+    style = TextStyle(size=30)
+    t1 = slide.text("~1{a\u2060b} $x^2$", style, parse_math=True)
+    b1 = slide.box(x=t1.inline_x(1), y=0, width=t1.inline_width(1), height=10)
+    t2 = slide.text("~1{a\u2060b} c", style)
+    b2 = slide.box(x=t2.inline_x(1), y=0, width=t2.inline_width(1), height=10)
+    layout = deck.render(None, "layout")[0]
+    assert layout[id(b1)]["width"] == pytest.approx(layout[id(b2)]["width"])
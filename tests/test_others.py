@@ -5,9 +5,10 @@ from testutils import check
 def test_box_debug_frame(deck):
     slide = deck.new_slide(width=200, height=150)
     slide.box(width=180, height="30%", debug_layout=True, name="Box 123")
-    slide.box(width=100, height="30%", debug_layout="orange")
+    # Unnamed boxes are labeled by their ids, which differ between runs
+    slide.box(width=100, height="30%", debug_layout="orange", name="B")
     slide.box(width=100, height="30%")
-    slide.box(debug_layout=True)
+    slide.box(debug_layout=True, name="C")
 
     # slide = deck.new_slide(width=200, height=150, debug_frames=True)
     # b = slide.box(width="80%", height="80%")
@@ -18,6 +19,18 @@ def test_box_debug_frame(deck):
     # deck.render(output_pdf="/tmp/out.pdf")
 
 
+def test_debug_layout_does_not_add_boxes(deck):
+    slide = deck.new_slide(width=200, height=150, debug_layout=True)
+    row = slide.box(row=True, gap_x=10, p_left=5, m_top=5, name="row")
+    a = row.box(width=50, height=20)
+    b = row.box(width=50, height=20, debug_layout="green")
+    text = slide.text("Hello\n~1{world}", debug_layout="orange")
+    layout = deck.render(None, "layout")[0]
+    assert set(layout) == {id(slide), id(row), id(a), id(b), id(text)}
+    assert layout[id(b)]["x"] - layout[id(a)]["x"] == 60
+    assert len(deck.render(None, "png")) == 1
+
+
 @check()
 def test_box_border_radius(deck):
     slide = deck.new_slide(width=150, height=150)
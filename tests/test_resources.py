@@ -1,3 +1,7 @@
+import os
+
+import pytest
+
 from conftest import new_resources, ASSETS_DIR
 
 from nelsie import SlideDeck
@@ -25,6 +29,56 @@ def test_resources_add_syntax():
     slide.code("if (x > 0) { return 1 }", "testC")
 
 
+def test_resources_add_syntax_file_and_str():
+    r = new_resources(default_code_syntaxes=False)
+    r.load_code_syntax_file(os.path.join(ASSETS_DIR, "testC.sublime-syntax"))
+    assert "testC" in dict(r.syntaxes())
+
+    r = new_resources(default_code_syntaxes=False)
+    with open(os.path.join(ASSETS_DIR, "testC.sublime-syntax")) as f:
+        r.load_code_syntax(f.read().replace("name: testC", "name: testD"))
+    assert list(dict(r.syntaxes())) == ["testD"]
+
+    with pytest.raises(Exception, match="Adding syntax failed"):
+        r.load_code_syntax("name: [")
+
+
+def test_resources_syntax_dump(tmp_path):
+    r = new_resources(default_code_syntaxes=False)
+    r.load_code_syntax_dir(ASSETS_DIR)
+    path = str(tmp_path / "syntaxes.bin")
+    r.dump_code_syntaxes(path)
+
+    r2 = new_resources()
+    r2.load_code_syntax_dump(path)
+    assert r2.syntaxes() == r.syntaxes()
+    deck = SlideDeck(resources=r2)
+    slide = deck.new_slide()
+    slide.code("if (x > 0) { return 1 }", "testC")
+    deck.render(None, "svg")
+
+    with pytest.raises(Exception, match="Loading syntax dump failed"):
+        r2.load_code_syntax_dump(os.path.join(ASSETS_DIR, "test.tmTheme"))
+
+
+def test_resources_load_failures(tmp_path):
+    (tmp_path / "sub").mkdir()
+    (tmp_path / "sub" / "broken.sublime-syntax").write_text("name: [")
+    (tmp_path / "broken.tmTheme").write_text("<plist>")
+    with open(os.path.join(ASSETS_DIR, "testC.sublime-syntax")) as f:
+        (tmp_path / "sub" / "good.sublime-syntax").write_text(f.read())
+
+    r = new_resources(default_code_syntaxes=False)
+    assert r.load_failures() == []
+    r.load_code_syntax_dir(str(tmp_path))
+    r.load_code_theme_dir(str(tmp_path))
+    assert "testC" in dict(r.syntaxes())
+    failures = r.load_failures()
+    assert [os.path.basename(path) for path, _ in failures] == ["broken.sublime-syntax", "broken.tmTheme"]
+    assert "Adding syntax failed" in failures[0][1]
+    assert "Adding theme failed" in failures[1][1]
+
+
 def test_resources_themes(resources):
     themes = resources.themes()
     assert "InspiredGitHub" in themes
@@ -38,3 +92,39 @@ def test_resources_add_theme():
     deck = SlideDeck(resources=r)
     slide = deck.new_slide()
     slide.code("if (x > 0) { return 1 }", "C", theme="test")
+
+
+def test_resources_add_theme_file_and_str():
+    path = os.path.join(ASSETS_DIR, "test.tmTheme")
+    r = new_resources()
+    r.load_code_theme_file(path)
+    assert "test" in r.themes()
+
+    with open(path) as f:
+        r.load_code_theme("my-theme", f.read())
+    assert r.theme_colors("my-theme") == r.theme_colors("test")
+
+    with pytest.raises(Exception, match="Adding theme failed"):
+        r.load_code_theme("broken", "<plist>")
+
+
+def test_resources_theme_colors(resources):
+    colors = resources.theme_colors("InspiredGitHub")
+    assert colors["background"] == "#ffffff"
+    assert colors["selection"] == "#f8eec7"
+    assert colors["gutter_foreground"] == "#b3b3b3"
+    assert set(colors) == {"background", "foreground", "selection", "line_highlight", "gutter", "gutter_foreground"}
+
+    colors = resources.theme_colors("base16-ocean.dark")
+    assert colors["background"] == "#2b303b"
+    assert colors["gutter"] is None
+
+    with pytest.raises(Exception, match="Theme 'xyz' not found"):
+        resources.theme_colors("xyz")
+
+
+def test_resources_tree_sitter_languages(resources):
+    languages = resources.tree_sitter_languages()
+    assert "rust" in languages
+    assert "python" in languages
+    assert "typescript" in languages
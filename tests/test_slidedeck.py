@@ -59,7 +59,7 @@ def test_slide_decorator_simple(deck):
 
     @deck.slide(width=300, height=120, debug_layout="green")
     def my_slide2(slide):
-        slide.text("Hello world!")
+        slide.text("Hello world!", name="text")
 
 
 @check(n_slides=1)
@@ -1,8 +1,9 @@
+import os
 from dataclasses import asdict
 
 import pytest
 
-from conftest import new_resources
+from conftest import ASSETS_DIR, new_resources
 from testutils import check
 
 from nelsie import FontStretch, TextStyle, StepVal
@@ -88,6 +89,9 @@ def test_render_text_unicode(deck):
 def test_set_get_styles_deck(deck):
     s = deck.get_style("default")
     for key, value in asdict(s).items():
+        # These values are derived from the text itself when not set
+        if key in ("bg_color", "decoration_color", "decoration_width"):
+            continue
         assert value is not None
 
     assert deck.get_style("big") is None
@@ -109,6 +113,139 @@ def test_text_lines(deck):
     slide.text("Test text", TextStyle(underline=True, line_through=True))
 
 
+def test_text_decorations(deck):
+    slide = deck.new_slide(width=300, height=100)
+    slide.set_style("hl", TextStyle(bg_color="#ffff00"))
+    slide.set_style("red", TextStyle(color="#ff0000"))
+    slide.text(
+        "A ~hl{marked ~red{term}}",
+        TextStyle(
+            underline=True,
+            overline=True,
+            decoration_color="#0000ff",
+            decoration_width=2,
+            decoration_style="wavy",
+        ),
+    )
+    svg = deck.render(None, "svg")[0]
+    assert "#ffff00" in svg
+    assert "#ff0000" in svg
+    assert "#0000ff" in svg
+
+
+def test_text_superscript_subscript(deck):
+    slide = deck.new_slide(width=300, height=300)
+    style = TextStyle(size=30)
+    b1 = slide.text("E = mc~sup{2}\nH~sub{2}O", style)
+    b2 = slide.text("E = mc2\nH2O", style)
+    b3 = slide.text("E = mc~shifted{2}", style)
+    b4 = slide.text("E = mc2", style)
+    slide.set_style("shifted", TextStyle(baseline_shift=-20))
+    layout = deck.render(None, "layout")[0]
+    assert layout[id(b1)]["width"] < layout[id(b2)]["width"]
+    assert layout[id(b1)]["height"] > layout[id(b2)]["height"]
+    assert layout[id(b3)]["width"] == pytest.approx(layout[id(b4)]["width"])
+    assert layout[id(b3)]["height"] > layout[id(b4)]["height"]
+
+
+def test_text_rtl_inline_anchors(deck):
+    slide = deck.new_slide(width=400, height=300)
+    style = TextStyle(font="DejaVu Sans")
+    t1 = slide.text("שלום ~1{עולם}", style)
+    b1 = slide.box(x=t1.inline_x(1), y=0, width=t1.inline_width(1), height=10)
+    t2 = slide.text("~1{שלום} עולם", style)
+    b2 = slide.box(x=t2.inline_x(1), y=0, width=t2.inline_width(1), height=10)
+    t3 = slide.text("abc ~1{def}", style, direction="rtl")
+    b3 = slide.box(x=t3.inline_x(1), y=0, width=t3.inline_width(1), height=10)
+    layout = deck.render(None, "layout")[0]
+    for t, b, is_left in [(t1, b1, True), (t2, b2, False), (t3, b3, False)]:
+        t = layout[id(t)]
+        b = layout[id(b)]
+        assert 10 < b["width"] < t["width"] - 10
+        if is_left:
+            assert b["x"] == pytest.approx(t["x"], abs=0.5)
+        else:
+            assert b["x"] + b["width"] == pytest.approx(t["x"] + t["width"], abs=0.5)
+
+
+def test_text_direction_alignment(deck):
+    slide = deck.new_slide(width=400, height=300)
+    t1 = slide.text("abc ~1{def}\nx", direction="ltr")
+    b1 = slide.box(x=t1.line_x(1), y=0, width=10, height=10)
+    t2 = slide.text("abc ~1{def}\nx", direction="rtl")
+    b2 = slide.box(x=t2.line_x(1), y=0, width=10, height=10)
+    layout = deck.render(None, "layout")[0]
+    assert layout[id(b1)]["x"] == pytest.approx(layout[id(t1)]["x"])
+    assert layout[id(b2)]["x"] > layout[id(t2)]["x"] + 10
+
+
+def test_text_invalid_direction(deck):
+    slide = deck.new_slide()
+    with pytest.raises(ValueError, match="Invalid text direction"):
+        slide.text("Hello", direction="up")
+
+
+def test_text_vertical(deck):
+    slide = deck.new_slide(width=400, height=400)
+    t1 = slide.text("Hello\nworld!")
+    t2 = slide.text("Hello\n~1{world}!", writing_mode="vertical")
+    b1 = slide.box(x=t2.line_x(0), y=t2.line_y(0), width=10, height=10)
+    b2 = slide.box(x=t2.line_x(1), y=t2.inline_y(1), width=10, height=10)
+    b3 = slide.box(x=0, y=0, width=t2.inline_width(1), height=t2.inline_height(1))
+    layout = deck.render(None, "layout")[0]
+    t1 = layout[id(t1)]
+    t2 = layout[id(t2)]
+    assert t2["width"] == pytest.approx(t1["height"])
+    assert t2["height"] == pytest.approx(t1["width"])
+    assert layout[id(b1)]["x"] == pytest.approx(t2["x"] + t2["width"] / 2)
+    assert layout[id(b1)]["y"] == pytest.approx(t2["y"])
+    assert layout[id(b2)]["x"] == pytest.approx(t2["x"])
+    assert layout[id(b2)]["y"] == pytest.approx(t2["y"])
+    assert layout[id(b3)]["width"] == pytest.approx(t2["width"] / 2)
+    assert 0 < layout[id(b3)]["height"] < t2["height"]
+
+
+def test_text_invalid_writing_mode(deck):
+    slide = deck.new_slide()
+    with pytest.raises(ValueError, match="Invalid writing mode"):
+        slide.text("Hello", writing_mode="diagonal")
+
+
+def test_text_color_glyphs(deck_builder):
+    resources = new_resources()
+    resources.load_fonts_dir(os.path.join(ASSETS_DIR, "fonts"))
+    deck = deck_builder(resources=resources)
+    slide = deck.new_slide(width=200, height=100)
+    # "A" is a COLR glyph with a CPAL color layer and a text color layer, "B" is a sbix bitmap
+    slide.text("AB", TextStyle(font="Nelsie Color Test", color="#ff0000"))
+    svg = deck.render(None, "svg")[0]
+    assert "fill='#12ab34'" in svg
+    assert "fill='#ff0000'" in svg
+    assert svg.count("<image") == 1
+
+
+def test_text_color_glyph_images_embedded_once(deck_builder):
+    resources = new_resources()
+    resources.load_fonts_dir(os.path.join(ASSETS_DIR, "fonts"))
+    deck = deck_builder(resources=resources)
+    style = TextStyle(font="Nelsie Color Test")
+    deck.new_slide(width=200, height=100).text("BBB", style)
+    deck.new_slide(width=200, height=100).text("B\nB", style)
+    pdf = deck.render(None, "pdf", compression_level=0)
+    # One image of "B" and its alpha mask
+    assert pdf.count(b"/Subtype /Image") == 2
+
+
+def test_text_invalid_script():
+    with pytest.raises(ValueError, match="Invalid script position"):
+        TextStyle(script="top")
+
+
+def test_text_invalid_decoration_style():
+    with pytest.raises(ValueError, match="Invalid decoration style"):
+        TextStyle(decoration_style="zigzag")
+
+
 @check()
 def test_text_color_opacity(deck):
     slide = deck.new_slide(width=220, height=50)
@@ -376,3 +513,65 @@ def test_set_generic_families():
         res.set_sans_serif("xxx")
     with pytest.raises(Exception, match="Font 'xxx' not found"):
         res.set_monospace("xxx")
+
+
+def test_text_markdown(deck):
+    deck.set_style("emph", TextStyle(color="#ff0000"))
+    slide = deck.new_slide(width=600, height=300)
+    t1 = slide.text("A **bold** and *emph*", markdown=True)
+    t2 = slide.text("A ~strong{bold} and ~emph{emph}")
+    t3 = slide.text("A **bold** and *emph*")
+    t4 = slide.text("# Title", markdown=True)
+    t5 = slide.text("Title")
+    layout = deck.render(None, "layout")[0]
+    assert layout[id(t1)]["width"] == pytest.approx(layout[id(t2)]["width"])
+    assert layout[id(t1)]["width"] < layout[id(t3)]["width"]
+    assert layout[id(t4)]["height"] > layout[id(t5)]["height"]
+    svg = deck.render(None, "svg")[0]
+    assert "fill='#ff0000'" in svg
+
+
+def test_text_markdown_heading_sizes(deck):
+    deck.set_style("h2", TextStyle(color="#ff0000"))
+    slide = deck.new_slide(width=600, height=300)
+    slide.set_style("default", TextStyle(size=20))
+    t1 = slide.text("# Title", markdown=True)
+    t2 = slide.text("Title", TextStyle(size=40, bold=True))
+    t3 = slide.text("## Title", TextStyle(size=40), markdown=True)
+    t4 = slide.text("Title", TextStyle(size=60, bold=True))
+    layout = deck.render(None, "layout")[0]
+    # Headings are sized relative to the resolved size of the text
+    assert layout[id(t1)]["width"] == pytest.approx(layout[id(t2)]["width"])
+    assert layout[id(t3)]["width"] == pytest.approx(layout[id(t4)]["width"])
+    svg = deck.render(None, "svg")[0]
+    assert "fill='#ff0000'" in svg
+
+
+def test_text_markdown_links(deck, tmp_path):
+    slide = deck.new_slide(width=600, height=300)
+    slide.text("See [docs](https://nelsie.org/docs) and <https://example.org>", markdown=True)
+    path = os.path.join(tmp_path, "out.pdf")
+    deck.render(path)
+    with open(path, "rb") as f:
+        data = f.read()
+    assert b"https://nelsie.org/docs" in data
+    assert b"https://example.org" in data
+
+
+def test_text_links(deck):
+    slide = deck.new_slide(width=400, height=300)
+    slide.text(
+        "See ~1{the docs\nof nelsie} and ~2{this}~3{}",
+        links={1: "https://nelsie.org/?a=1&b=2", 2: "https://example.org", 3: "https://empty.org"},
+    )
+    svg = deck.render(None, "svg")[0]
+    # A link over two lines is split into two rectangles; an empty range creates no link
+    assert svg.count("<a xlink:href='https://nelsie.org/?a=1&amp;b=2'>") == 2
+    assert svg.count("<a xlink:href='https://example.org'>") == 1
+    assert "https://empty.org" not in svg
+
+
+def test_text_links_invalid(deck):
+    slide = deck.new_slide()
+    with pytest.raises(Exception, match="Expect"):
+        slide.text("~1{x}", links={"1": "https://nelsie.org"})